@@ -0,0 +1,260 @@
+//! Benchmarks for the Rust reactivity core.
+//!
+//! Run with `cargo bench -p selene-core --bench reactivity [filter]`.
+//!
+//! Every workload reports time per iteration, throughput (iterations and
+//! effect runs per second) and heap allocations per iteration. Allocations
+//! are counted by a wrapping global allocator, so the numbers are exact and
+//! comparable across machines even when timings are not.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::hint::black_box;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use selene_core::{batch, computed, effect, signal, Effect, Signal};
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const WARMUP: Duration = Duration::from_millis(100);
+const MEASURE: Duration = Duration::from_millis(500);
+
+/// A benchmark workload: builds its reactive graph once and returns the
+/// closure timed on every iteration.
+struct Workload {
+    name: &'static str,
+    /// Effect runs triggered by a single iteration, used for throughput
+    effect_runs: usize,
+    setup: fn() -> Box<dyn FnMut(usize)>,
+}
+
+const WIDTH: usize = 1_000;
+const DEPTH: usize = 1_000;
+const DIAMOND: usize = 100;
+
+const WORKLOADS: &[Workload] = &[
+    Workload {
+        name: "signal_set_get",
+        effect_runs: 0,
+        setup: signal_set_get,
+    },
+    Workload {
+        name: "fan_out_1000",
+        effect_runs: WIDTH,
+        setup: fan_out,
+    },
+    Workload {
+        name: "deep_chain_1000",
+        effect_runs: DEPTH + 1,
+        setup: deep_chain,
+    },
+    Workload {
+        name: "diamond_100",
+        effect_runs: DIAMOND + 1,
+        setup: diamond,
+    },
+    Workload {
+        name: "batch_1000_signals",
+        effect_runs: 1,
+        setup: batched_writes,
+    },
+    Workload {
+        name: "create_dispose_1000",
+        effect_runs: WIDTH,
+        setup: create_dispose,
+    },
+];
+
+/// One signal without subscribers: the raw cost of a write and a read
+fn signal_set_get() -> Box<dyn FnMut(usize)> {
+    let source = signal(0usize);
+    Box::new(move |i| {
+        source.set(i);
+        black_box(source.get());
+    })
+}
+
+/// One signal read by many independent effects
+fn fan_out() -> Box<dyn FnMut(usize)> {
+    let source = signal(0usize);
+    let sink = Rc::new(Cell::new(0usize));
+    let effects: Vec<Effect> = (0..WIDTH)
+        .map(|_| {
+            let (source, sink) = (source.clone(), Rc::clone(&sink));
+            effect(move || sink.set(sink.get().wrapping_add(source.get())))
+        })
+        .collect();
+    Box::new(move |i| {
+        source.set(i + 1);
+        black_box(&effects);
+    })
+}
+
+/// A long chain of computeds, each derived from the previous one
+fn deep_chain() -> Box<dyn FnMut(usize)> {
+    let head = signal(0usize);
+    let mut tail: Signal<usize> = head.clone();
+    for _ in 0..DEPTH {
+        let previous = tail.clone();
+        tail = computed(move || previous.get() + 1);
+    }
+    let sink = Rc::new(Cell::new(0usize));
+    let _effect = effect({
+        let sink = Rc::clone(&sink);
+        move || sink.set(tail.get())
+    });
+    Box::new(move |i| {
+        head.set(i + 1);
+        black_box(sink.get());
+    })
+}
+
+/// One source fanning out to many computeds that join in a single effect
+fn diamond() -> Box<dyn FnMut(usize)> {
+    let head = signal(0usize);
+    let branches: Vec<Signal<usize>> = (0..DIAMOND)
+        .map(|n| {
+            let head = head.clone();
+            computed(move || head.get() * n)
+        })
+        .collect();
+    let sink = Rc::new(Cell::new(0usize));
+    let _effect = effect({
+        let sink = Rc::clone(&sink);
+        move || sink.set(branches.iter().map(Signal::get).sum())
+    });
+    Box::new(move |i| {
+        head.set(i + 1);
+        black_box(sink.get());
+    })
+}
+
+/// Many signals written in one batch, observed by a single effect
+fn batched_writes() -> Box<dyn FnMut(usize)> {
+    let sources: Vec<Signal<usize>> = (0..WIDTH).map(signal).collect();
+    let sink = Rc::new(Cell::new(0usize));
+    let _effect = effect({
+        let (sources, sink) = (sources.clone(), Rc::clone(&sink));
+        move || sink.set(sources.iter().map(Signal::get).sum())
+    });
+    Box::new(move |i| {
+        batch(|| {
+            for source in &sources {
+                source.set(i + 1);
+            }
+        });
+        black_box(sink.get());
+    })
+}
+
+/// Creating effects that subscribe to a signal, then disposing them all
+fn create_dispose() -> Box<dyn FnMut(usize)> {
+    let source = signal(0usize);
+    let mut effects = Vec::with_capacity(WIDTH);
+    Box::new(move |_| {
+        for _ in 0..WIDTH {
+            let source = source.clone();
+            effects.push(effect(move || {
+                black_box(source.get());
+            }));
+        }
+        for effect in effects.drain(..) {
+            effect.dispose();
+        }
+    })
+}
+
+struct Measurement {
+    iterations: usize,
+    elapsed: Duration,
+    allocations: usize,
+    bytes: usize,
+}
+
+fn measure(workload: &Workload) -> Measurement {
+    let mut iteration = (workload.setup)();
+
+    let mut i = 0;
+    let warmup_start = Instant::now();
+    while warmup_start.elapsed() < WARMUP {
+        iteration(i);
+        i += 1;
+    }
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let mut iterations = 0;
+    while start.elapsed() < MEASURE {
+        iteration(i);
+        i += 1;
+        iterations += 1;
+    }
+    let elapsed = start.elapsed();
+
+    Measurement {
+        iterations,
+        elapsed,
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        bytes: ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes,
+    }
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`; any other argument filters workloads by name
+    let filters: Vec<String> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect();
+
+    println!(
+        "{:<22} {:>10} {:>12} {:>12} {:>14} {:>12} {:>12}",
+        "workload", "iters", "ns/iter", "iters/s", "effect runs/s", "allocs/iter", "bytes/iter"
+    );
+
+    for workload in WORKLOADS {
+        if !filters.is_empty() && !filters.iter().any(|f| workload.name.contains(f.as_str())) {
+            continue;
+        }
+
+        let m = measure(workload);
+        let iterations = m.iterations as f64;
+        let secs = m.elapsed.as_secs_f64();
+        println!(
+            "{:<22} {:>10} {:>12.0} {:>12.0} {:>14.0} {:>12.1} {:>12.0}",
+            workload.name,
+            m.iterations,
+            m.elapsed.as_nanos() as f64 / iterations,
+            iterations / secs,
+            (m.iterations * workload.effect_runs) as f64 / secs,
+            m.allocations as f64 / iterations,
+            m.bytes as f64 / iterations,
+        );
+    }
+}
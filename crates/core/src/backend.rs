@@ -0,0 +1,656 @@
+use std::fmt;
+
+#[cfg(target_arch = "wasm32")]
+use std::collections::HashMap;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::closure::Closure;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{JsCast, JsValue};
+#[cfg(target_arch = "wasm32")]
+use web_sys::{CssStyleDeclaration, Document, Element, Node};
+
+#[cfg(target_arch = "wasm32")]
+use crate::event::{event_bubbles, Event};
+use crate::event::{HandlerTable, Listener};
+use crate::hydrate::{ExistingNode, HydrationMismatch};
+use crate::patch::{NodeId, Patch};
+use crate::prop::PropertyValue;
+#[cfg(target_arch = "wasm32")]
+use crate::reactivity::catch_js_errors;
+
+/// Failure while applying patches to a backend
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RenderError {
+    /// A patch referenced an id the backend does not know
+    UnknownNode(NodeId),
+    /// The backend rejected an operation, e.g. an invalid tag name
+    Backend(String),
+    /// The existing DOM does not match the tree being hydrated, and
+    /// recovery is disabled
+    HydrationMismatch(Vec<HydrationMismatch>),
+    /// A component panicked while rendering, with the panic message
+    Panic(String),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::UnknownNode(id) => write!(f, "unknown node id {}", id.0),
+            RenderError::Backend(message) => write!(f, "{}", message),
+            RenderError::HydrationMismatch(mismatches) => {
+                write!(f, "hydration failed with {} mismatch(es)", mismatches.len())?;
+                if let Some(first) = mismatches.first() {
+                    write!(f, ", first at {}", first)?;
+                }
+                Ok(())
+            }
+            RenderError::Panic(message) => write!(f, "component panicked: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+#[cfg(target_arch = "wasm32")]
+impl From<JsValue> for RenderError {
+    fn from(value: JsValue) -> Self {
+        RenderError::Backend(value.as_string().unwrap_or_else(|| format!("{:?}", value)))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl From<RenderError> for JsValue {
+    fn from(error: RenderError) -> Self {
+        js_sys::Error::new(&error.to_string()).into()
+    }
+}
+
+/// Node operations the renderer needs from a DOM implementation
+///
+/// Nodes are addressed by the ids the `Reconciler` hands out. When an id is
+/// reused by a create call, whatever was stored under it before, listeners
+/// included, is discarded.
+pub trait DomBackend {
+    /// Create a detached element
+    fn create_element(&mut self, id: NodeId, tag: &str) -> Result<(), RenderError>;
+
+    /// Create a detached element in `namespace`, e.g. an SVG `path`
+    ///
+    /// Backends without namespaces can keep the default, which creates a
+    /// plain element.
+    fn create_element_ns(
+        &mut self,
+        id: NodeId,
+        namespace: &str,
+        tag: &str,
+    ) -> Result<(), RenderError> {
+        let _ = namespace;
+        self.create_element(id, tag)
+    }
+
+    /// Create a detached text node
+    fn create_text(&mut self, id: NodeId, text: &str) -> Result<(), RenderError>;
+
+    /// Create a detached comment node, used as a placeholder or anchor
+    fn create_comment(&mut self, id: NodeId, text: &str) -> Result<(), RenderError>;
+
+    fn set_attribute(&mut self, id: NodeId, name: &str, value: &str) -> Result<(), RenderError>;
+
+    fn remove_attribute(&mut self, id: NodeId, name: &str) -> Result<(), RenderError>;
+
+    /// Set an attribute in `namespace` by its qualified name, e.g.
+    /// `xlink:href`; the default sets a plain attribute of that name
+    fn set_attribute_ns(
+        &mut self,
+        id: NodeId,
+        namespace: &str,
+        name: &str,
+        value: &str,
+    ) -> Result<(), RenderError> {
+        let _ = namespace;
+        self.set_attribute(id, name, value)
+    }
+
+    /// Remove an attribute set with `set_attribute_ns`
+    fn remove_attribute_ns(
+        &mut self,
+        id: NodeId,
+        namespace: &str,
+        name: &str,
+    ) -> Result<(), RenderError> {
+        let _ = namespace;
+        self.remove_attribute(id, name)
+    }
+
+    /// Assign a DOM property on the node object, e.g. an input's `value`
+    fn set_property(
+        &mut self,
+        id: NodeId,
+        name: &str,
+        value: &PropertyValue,
+    ) -> Result<(), RenderError>;
+
+    /// Set one inline style declaration, e.g. `color: red`
+    fn set_style(&mut self, id: NodeId, name: &str, value: &str) -> Result<(), RenderError>;
+
+    fn remove_style(&mut self, id: NodeId, name: &str) -> Result<(), RenderError>;
+
+    /// Add `name` to the class list of an element, keeping its other classes
+    fn add_class(&mut self, id: NodeId, name: &str) -> Result<(), RenderError> {
+        let _ = (id, name);
+        Err(RenderError::Backend(
+            "backend cannot change class lists".to_string(),
+        ))
+    }
+
+    /// Remove `name` from the class list of an element
+    fn remove_class(&mut self, id: NodeId, name: &str) -> Result<(), RenderError> {
+        let _ = (id, name);
+        Err(RenderError::Backend(
+            "backend cannot change class lists".to_string(),
+        ))
+    }
+
+    /// Position of an element's border box in the viewport, for animating
+    /// moves; backends without layout keep the default
+    fn position(&self, id: NodeId) -> Option<(f64, f64)> {
+        let _ = id;
+        None
+    }
+
+    /// Replace the content of a text or comment node
+    fn set_text(&mut self, id: NodeId, text: &str) -> Result<(), RenderError>;
+
+    /// Insert `id` into `parent` before `before`, or append it; moves attached nodes
+    fn insert_before(
+        &mut self,
+        parent: NodeId,
+        id: NodeId,
+        before: Option<NodeId>,
+    ) -> Result<(), RenderError>;
+
+    /// Detach a node, with its subtree, from its parent and forget its id
+    fn remove(&mut self, id: NodeId) -> Result<(), RenderError>;
+
+    /// Call `listener` for `event` on `id`, replacing any listener already set
+    fn add_event_listener(
+        &mut self,
+        id: NodeId,
+        event: &str,
+        listener: Listener,
+    ) -> Result<(), RenderError>;
+
+    /// Call `listener` for `event` fired on `id` or any of its descendants,
+    /// replacing any listener already set
+    ///
+    /// Non-bubbling events must reach it too, e.g. through a capture listener.
+    /// The event's path runs from the original target up to `id`.
+    fn add_delegated_listener(
+        &mut self,
+        id: NodeId,
+        event: &str,
+        listener: Listener,
+    ) -> Result<(), RenderError>;
+
+    fn remove_event_listener(&mut self, id: NodeId, event: &str) -> Result<(), RenderError>;
+
+    /// Children `parent` currently has, with their subtrees, for hydration
+    fn existing_children(&self, parent: NodeId) -> Result<Vec<ExistingNode>, RenderError> {
+        let _ = parent;
+        Err(RenderError::Backend(
+            "backend cannot hydrate existing nodes".to_string(),
+        ))
+    }
+
+    /// Register the `index`th child of `parent` under `id`, see `Patch::Claim`
+    fn claim(&mut self, parent: NodeId, index: usize, id: NodeId) -> Result<(), RenderError> {
+        let _ = (parent, index, id);
+        Err(RenderError::Backend(
+            "backend cannot hydrate existing nodes".to_string(),
+        ))
+    }
+
+    /// Register an existing DOM node, such as a render container, under `id`
+    #[cfg(target_arch = "wasm32")]
+    fn adopt(&mut self, id: NodeId, node: Node) -> Result<(), RenderError> {
+        let _ = (id, node);
+        Err(RenderError::Backend(
+            "backend cannot adopt DOM nodes".to_string(),
+        ))
+    }
+
+    /// The DOM node registered under `id`, for refs
+    #[cfg(target_arch = "wasm32")]
+    fn dom_node(&self, id: NodeId) -> Option<Node> {
+        let _ = id;
+        None
+    }
+
+    /// Apply patches in order, stopping at the first failure
+    ///
+    /// Listeners added by `AddEventListener`, `AddDelegatedListener` and
+    /// `AddPortalListener` dispatch through `handlers`.
+    fn apply(&mut self, patches: &[Patch], handlers: &HandlerTable) -> Result<(), RenderError> {
+        for patch in patches {
+            self.apply_patch(patch, handlers)?;
+        }
+        Ok(())
+    }
+
+    /// Apply a single patch; see `apply`
+    fn apply_patch(&mut self, patch: &Patch, handlers: &HandlerTable) -> Result<(), RenderError> {
+        match patch {
+            Patch::CreateElement { id, tag } => self.create_element(*id, tag)?,
+            Patch::CreateElementNs { id, namespace, tag } => {
+                self.create_element_ns(*id, namespace, tag)?
+            }
+            Patch::CreateText { id, text } => self.create_text(*id, text)?,
+            Patch::CreateComment { id, text } => self.create_comment(*id, text)?,
+            Patch::Claim { parent, index, id } => self.claim(*parent, *index, *id)?,
+            Patch::SetAttribute { id, name, value } => self.set_attribute(*id, name, value)?,
+            Patch::RemoveAttribute { id, name } => self.remove_attribute(*id, name)?,
+            Patch::SetAttributeNs {
+                id,
+                namespace,
+                name,
+                value,
+            } => self.set_attribute_ns(*id, namespace, name, value)?,
+            Patch::RemoveAttributeNs {
+                id,
+                namespace,
+                name,
+            } => self.remove_attribute_ns(*id, namespace, name)?,
+            Patch::SetProperty { id, name, value } => self.set_property(*id, name, value)?,
+            Patch::SetStyle { id, name, value } => self.set_style(*id, name, value)?,
+            Patch::RemoveStyle { id, name } => self.remove_style(*id, name)?,
+            Patch::AddClass { id, name } => self.add_class(*id, name)?,
+            Patch::RemoveClass { id, name } => self.remove_class(*id, name)?,
+            Patch::SetText { id, text } => self.set_text(*id, text)?,
+            Patch::InsertBefore { parent, id, before } => {
+                self.insert_before(*parent, *id, *before)?
+            }
+            Patch::Remove { id } => self.remove(*id)?,
+            Patch::AddEventListener { id, event } => {
+                self.add_event_listener(*id, event, handlers.listener(*id))?
+            }
+            Patch::AddDelegatedListener { id, event } => {
+                self.add_delegated_listener(*id, event, handlers.delegated_listener(*id))?
+            }
+            Patch::AddPortalListener { id, event } => {
+                self.add_event_listener(*id, event, handlers.portal_listener(*id))?
+            }
+            Patch::RemoveEventListener { id, event } => self.remove_event_listener(*id, event)?,
+        }
+        Ok(())
+    }
+}
+
+/// Browser listener; throws the first exception a JS handler threw
+#[cfg(target_arch = "wasm32")]
+type DomListener = Closure<dyn FnMut(web_sys::Event) -> Result<(), JsValue>>;
+
+/// `DomBackend` backed by the browser DOM through `web_sys`
+#[cfg(target_arch = "wasm32")]
+pub struct WebDom {
+    document: Document,
+    nodes: HashMap<NodeId, Node>,
+    /// Registered closures with their capture flag, dropped when their
+    /// node, or an ancestor, is removed or their node's id is reused
+    listeners: HashMap<NodeId, Vec<(String, DomListener, bool)>>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl WebDom {
+    pub fn new(document: Document) -> Self {
+        WebDom {
+            document,
+            nodes: HashMap::new(),
+            listeners: HashMap::new(),
+        }
+    }
+
+    /// The DOM node behind `id`
+    pub fn node(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(&id)
+    }
+
+    fn get(&self, id: NodeId) -> Result<&Node, RenderError> {
+        self.nodes.get(&id).ok_or(RenderError::UnknownNode(id))
+    }
+
+    fn element(&self, id: NodeId) -> Result<&Element, RenderError> {
+        self.get(id)?
+            .dyn_ref::<Element>()
+            .ok_or_else(|| RenderError::Backend(format!("node {} is not an element", id.0)))
+    }
+
+    /// Inline style of an element, HTML or SVG alike
+    fn style(&self, id: NodeId) -> Result<CssStyleDeclaration, RenderError> {
+        let style = js_sys::Reflect::get(self.element(id)?, &JsValue::from_str("style"))?;
+        style
+            .dyn_into::<CssStyleDeclaration>()
+            .map_err(|_| RenderError::Backend(format!("node {} has no inline style", id.0)))
+    }
+
+    fn insert(&mut self, id: NodeId, node: Node) {
+        self.listeners.remove(&id);
+        // Lets delegated listeners map DOM targets back to ids
+        let _ = js_sys::Reflect::set(&node, &JsValue::from_str(NODE_ID_KEY), &id.0.into());
+        self.nodes.insert(id, node);
+    }
+
+    /// Unmap `node` and every managed node below it, dropping their
+    /// listeners; ids already reused for another node stay mapped
+    fn forget(&mut self, node: &Node) -> Result<(), RenderError> {
+        if let Some(id) = node_id(node) {
+            if self
+                .nodes
+                .get(&id)
+                .is_some_and(|mapped| mapped.is_same_node(Some(node)))
+            {
+                self.nodes.remove(&id);
+                for (event, closure, capture) in self.listeners.remove(&id).unwrap_or_default() {
+                    node.remove_event_listener_with_callback_and_bool(
+                        &event,
+                        closure.as_ref().unchecked_ref(),
+                        capture,
+                    )?;
+                }
+            }
+        }
+        let children = node.child_nodes();
+        for index in 0..children.length() {
+            if let Some(child) = children.item(index) {
+                self.forget(&child)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn listen(
+        &mut self,
+        id: NodeId,
+        event: &str,
+        closure: DomListener,
+        capture: bool,
+    ) -> Result<(), RenderError> {
+        self.remove_event_listener(id, event)?;
+        self.get(id)?.add_event_listener_with_callback_and_bool(
+            event,
+            closure.as_ref().unchecked_ref(),
+            capture,
+        )?;
+        self.listeners
+            .entry(id)
+            .or_default()
+            .push((event.to_string(), closure, capture));
+        Ok(())
+    }
+}
+
+/// Expando property holding the `NodeId` of every node `WebDom` manages
+#[cfg(target_arch = "wasm32")]
+const NODE_ID_KEY: &str = "__seleneId";
+
+/// Id `WebDom` gave `node`, if it manages it
+#[cfg(target_arch = "wasm32")]
+fn node_id(node: &Node) -> Option<NodeId> {
+    js_sys::Reflect::get(node, &JsValue::from_str(NODE_ID_KEY))
+        .ok()
+        .and_then(|value| value.as_f64())
+        .map(|value| NodeId(value as u32))
+}
+
+/// Ids of the managed nodes from the event's target up to, excluding, `root`
+#[cfg(target_arch = "wasm32")]
+fn event_path(raw: &web_sys::Event, root: NodeId) -> Vec<NodeId> {
+    let mut path = Vec::new();
+    let mut current = raw
+        .target()
+        .and_then(|target| target.dyn_into::<Node>().ok());
+    while let Some(node) = current {
+        if let Some(id) = node_id(&node) {
+            if id == root {
+                break;
+            }
+            path.push(id);
+        }
+        current = node.parent_node();
+    }
+    path
+}
+
+#[cfg(target_arch = "wasm32")]
+impl DomBackend for WebDom {
+    fn create_element(&mut self, id: NodeId, tag: &str) -> Result<(), RenderError> {
+        let element = self.document.create_element(tag)?;
+        self.insert(id, element.into());
+        Ok(())
+    }
+
+    fn create_element_ns(
+        &mut self,
+        id: NodeId,
+        namespace: &str,
+        tag: &str,
+    ) -> Result<(), RenderError> {
+        let element = self.document.create_element_ns(Some(namespace), tag)?;
+        self.insert(id, element.into());
+        Ok(())
+    }
+
+    fn create_text(&mut self, id: NodeId, text: &str) -> Result<(), RenderError> {
+        let text_node = self.document.create_text_node(text);
+        self.insert(id, text_node.into());
+        Ok(())
+    }
+
+    fn create_comment(&mut self, id: NodeId, text: &str) -> Result<(), RenderError> {
+        let comment = self.document.create_comment(text);
+        self.insert(id, comment.into());
+        Ok(())
+    }
+
+    fn set_attribute(&mut self, id: NodeId, name: &str, value: &str) -> Result<(), RenderError> {
+        self.element(id)?.set_attribute(name, value)?;
+        Ok(())
+    }
+
+    fn remove_attribute(&mut self, id: NodeId, name: &str) -> Result<(), RenderError> {
+        self.element(id)?.remove_attribute(name)?;
+        Ok(())
+    }
+
+    fn set_attribute_ns(
+        &mut self,
+        id: NodeId,
+        namespace: &str,
+        name: &str,
+        value: &str,
+    ) -> Result<(), RenderError> {
+        self.element(id)?
+            .set_attribute_ns(Some(namespace), name, value)?;
+        Ok(())
+    }
+
+    fn remove_attribute_ns(
+        &mut self,
+        id: NodeId,
+        namespace: &str,
+        name: &str,
+    ) -> Result<(), RenderError> {
+        // Namespaced attributes are removed by their local name
+        let local_name = name.split_once(':').map_or(name, |(_, local)| local);
+        self.element(id)?
+            .remove_attribute_ns(Some(namespace), local_name)?;
+        Ok(())
+    }
+
+    fn set_property(
+        &mut self,
+        id: NodeId,
+        name: &str,
+        value: &PropertyValue,
+    ) -> Result<(), RenderError> {
+        js_sys::Reflect::set(self.get(id)?, &JsValue::from_str(name), &value.into())?;
+        Ok(())
+    }
+
+    fn set_style(&mut self, id: NodeId, name: &str, value: &str) -> Result<(), RenderError> {
+        self.style(id)?.set_property(name, value)?;
+        Ok(())
+    }
+
+    fn remove_style(&mut self, id: NodeId, name: &str) -> Result<(), RenderError> {
+        self.style(id)?.remove_property(name)?;
+        Ok(())
+    }
+
+    fn add_class(&mut self, id: NodeId, name: &str) -> Result<(), RenderError> {
+        self.element(id)?.class_list().add_1(name)?;
+        Ok(())
+    }
+
+    fn remove_class(&mut self, id: NodeId, name: &str) -> Result<(), RenderError> {
+        self.element(id)?.class_list().remove_1(name)?;
+        Ok(())
+    }
+
+    fn position(&self, id: NodeId) -> Option<(f64, f64)> {
+        let rect = self.element(id).ok()?.get_bounding_client_rect();
+        Some((rect.left(), rect.top()))
+    }
+
+    fn set_text(&mut self, id: NodeId, text: &str) -> Result<(), RenderError> {
+        self.get(id)?.set_text_content(Some(text));
+        Ok(())
+    }
+
+    fn insert_before(
+        &mut self,
+        parent: NodeId,
+        id: NodeId,
+        before: Option<NodeId>,
+    ) -> Result<(), RenderError> {
+        let before = match before {
+            Some(before) => Some(self.get(before)?),
+            None => None,
+        };
+        self.get(parent)?.insert_before(self.get(id)?, before)?;
+        Ok(())
+    }
+
+    fn remove(&mut self, id: NodeId) -> Result<(), RenderError> {
+        let Some(removed) = self.nodes.get(&id).cloned() else {
+            return Ok(());
+        };
+        if let Some(parent) = removed.parent_node() {
+            parent.remove_child(&removed)?;
+        }
+        self.forget(&removed)
+    }
+
+    fn add_event_listener(
+        &mut self,
+        id: NodeId,
+        event: &str,
+        listener: Listener,
+    ) -> Result<(), RenderError> {
+        let closure = DomListener::new(move |raw: web_sys::Event| {
+            // The target may be a descendant of the listening node
+            let mut path = event_path(&raw, id);
+            path.push(id);
+            let event = Event::from_raw(raw, path);
+            event.set_current_target(Some(id));
+            catch_js_errors(|| listener(&event))
+        });
+        self.listen(id, event, closure, false)
+    }
+
+    fn add_delegated_listener(
+        &mut self,
+        id: NodeId,
+        event: &str,
+        listener: Listener,
+    ) -> Result<(), RenderError> {
+        let closure = DomListener::new(move |raw: web_sys::Event| {
+            let path = event_path(&raw, id);
+            catch_js_errors(|| listener(&Event::from_raw(raw, path)))
+        });
+        self.listen(id, event, closure, !event_bubbles(event))
+    }
+
+    fn remove_event_listener(&mut self, id: NodeId, event: &str) -> Result<(), RenderError> {
+        let Some(registered) = self.listeners.get_mut(&id) else {
+            return Ok(());
+        };
+        if let Some(index) = registered.iter().position(|(name, _, _)| name == event) {
+            let (_, closure, capture) = registered.remove(index);
+            if let Some(node) = self.nodes.get(&id) {
+                node.remove_event_listener_with_callback_and_bool(
+                    event,
+                    closure.as_ref().unchecked_ref(),
+                    capture,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn adopt(&mut self, id: NodeId, node: Node) -> Result<(), RenderError> {
+        self.insert(id, node);
+        Ok(())
+    }
+
+    fn dom_node(&self, id: NodeId) -> Option<Node> {
+        self.nodes.get(&id).cloned()
+    }
+
+    fn existing_children(&self, parent: NodeId) -> Result<Vec<ExistingNode>, RenderError> {
+        Ok(existing_children(self.get(parent)?))
+    }
+
+    fn claim(&mut self, parent: NodeId, index: usize, id: NodeId) -> Result<(), RenderError> {
+        let child = u32::try_from(index)
+            .ok()
+            .and_then(|index| self.get(parent).ok()?.child_nodes().item(index))
+            .ok_or_else(|| {
+                RenderError::Backend(format!("node {} has no child {} to claim", parent.0, index))
+            })?;
+        self.insert(id, child);
+        Ok(())
+    }
+}
+
+/// Snapshot of the children of `node`
+///
+/// Other node types, which server-rendered markup does not contain, are
+/// reported as comments so indexes still line up with `childNodes`.
+#[cfg(target_arch = "wasm32")]
+fn existing_children(node: &Node) -> Vec<ExistingNode> {
+    let children = node.child_nodes();
+    (0..children.length())
+        .filter_map(|index| children.item(index))
+        .map(|child| match child.node_type() {
+            Node::ELEMENT_NODE => {
+                let element = child.unchecked_ref::<Element>();
+                let names = element.get_attribute_names();
+                let attributes = names
+                    .iter()
+                    .filter_map(|name| name.as_string())
+                    .filter_map(|name| {
+                        let value = element.get_attribute(&name)?;
+                        Some((name, value))
+                    })
+                    .collect();
+                ExistingNode::Element {
+                    tag: element.local_name(),
+                    attributes,
+                    children: existing_children(&child),
+                }
+            }
+            Node::TEXT_NODE => ExistingNode::Text(child.text_content().unwrap_or_default()),
+            _ => ExistingNode::Comment(child.text_content().unwrap_or_default()),
+        })
+        .collect()
+}
@@ -0,0 +1,60 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use crate::render::VNode;
+
+/// Async content of a `VNode::async_boundary`, shared by every clone of
+/// the vnode so the future is only driven once
+#[derive(Clone)]
+pub(crate) struct AsyncBoundary {
+    state: Rc<RefCell<BoundaryState>>,
+}
+
+enum BoundaryState {
+    Pending(Pin<Box<dyn Future<Output = VNode>>>),
+    Resolved(Box<VNode>),
+}
+
+impl AsyncBoundary {
+    pub(crate) fn new(content: impl Future<Output = VNode> + 'static) -> Self {
+        AsyncBoundary {
+            state: Rc::new(RefCell::new(BoundaryState::Pending(Box::pin(content)))),
+        }
+    }
+
+    /// Drive the content future, returning the content once it resolved
+    pub(crate) fn poll(&self, cx: &mut Context<'_>) -> Poll<VNode> {
+        let mut state = self.state.borrow_mut();
+        if let BoundaryState::Pending(future) = &mut *state {
+            match future.as_mut().poll(cx) {
+                Poll::Ready(content) => *state = BoundaryState::Resolved(Box::new(content)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        match &*state {
+            BoundaryState::Resolved(content) => Poll::Ready(VNode::clone(content)),
+            BoundaryState::Pending(_) => unreachable!("resolved above"),
+        }
+    }
+
+    /// Content if it is ready now, without registering for a wake-up
+    pub(crate) fn try_resolve(&self) -> Option<VNode> {
+        match self.poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(content) => Some(content),
+            Poll::Pending => None,
+        }
+    }
+}
+
+impl fmt::Debug for AsyncBoundary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let resolved = matches!(&*self.state.borrow(), BoundaryState::Resolved(_));
+        f.debug_struct("AsyncBoundary")
+            .field("resolved", &resolved)
+            .finish()
+    }
+}
@@ -10,12 +10,28 @@ use wasm_bindgen::prelude::*;
 /// - Works in wasm32 + non-wasm builds
 /// - Handles nested tags + self-closing tags + attributes
 /// - Text nodes preserve content (whitespace-only nodes dropped)
+///
+/// Element subtrees without `${}` text or `{expr}` attributes are static:
+/// they are built once, outside the render function, and flagged with
+/// `"$static": true` so the runtime skips them when re-rendering. Other
+/// elements list their `{expr}` attributes in `"$dynamic"`, the only props
+/// the runtime then compares.
 pub fn compile_template_sync(input: &str) -> String {
     let mut p = Parser::new(input);
     p.skip_ws();
     let nodes = p.parse_nodes();
-    let expr = emit_nodes(&nodes);
-    format!("() => {}", expr)
+    let mut emitter = Emitter::default();
+    let expr = emitter.emit_nodes(&nodes);
+    if emitter.hoisted.is_empty() {
+        return format!("() => {}", expr);
+    }
+    let consts = emitter
+        .hoisted
+        .iter()
+        .enumerate()
+        .map(|(i, node)| format!("const _hoisted_{} = {}; ", i + 1, node))
+        .collect::<String>();
+    format!("(() => {{ {}return () => {}; }})()", consts, expr)
 }
 
 #[derive(Debug, Clone)]
@@ -39,42 +55,84 @@ enum AttrValue {
     JsExpr(String),
 }
 
-fn emit_nodes(nodes: &[Node]) -> String {
-    if nodes.is_empty() {
-        return "(\"\")".to_string();
+/// Emits render expressions, collecting static subtrees to hoist
+#[derive(Default)]
+struct Emitter {
+    hoisted: Vec<String>,
+}
+
+impl Emitter {
+    fn emit_nodes(&mut self, nodes: &[Node]) -> String {
+        if nodes.is_empty() {
+            return "(\"\")".to_string();
+        }
+        if nodes.len() == 1 {
+            return self.emit_node(&nodes[0]);
+        }
+        // Multiple roots: wrap in Fragment so runtime can accept a single VNode.
+        // NOTE: This assumes `Fragment` is in scope where the generated function runs.
+        let children = nodes
+            .iter()
+            .map(|n| self.emit_node(n))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("h(Fragment, {{}}, [{}])", children)
     }
-    if nodes.len() == 1 {
-        return emit_node(&nodes[0]);
+
+    fn emit_node(&mut self, n: &Node) -> String {
+        match n {
+            Node::Text(t) => emit_text(t),
+            Node::Element(el) if is_static_element(el) => {
+                // Built once, shared by every render
+                let flag = ("$static".to_string(), AttrValue::BoolTrue);
+                self.hoisted.push(emit_element(el, Some(flag), &mut emit_node));
+                format!("_hoisted_{}", self.hoisted.len())
+            }
+            Node::Element(el) => {
+                let dynamic = el
+                    .attrs
+                    .iter()
+                    .filter(|(_, v)| matches!(v, AttrValue::JsExpr(_)))
+                    .map(|(k, _)| js_string(k))
+                    .collect::<Vec<_>>();
+                let flag = (
+                    "$dynamic".to_string(),
+                    AttrValue::JsExpr(format!("[{}]", dynamic.join(", "))),
+                );
+                emit_element(el, Some(flag), &mut |n| self.emit_node(n))
+            }
+        }
     }
-    // Multiple roots: wrap in Fragment so runtime can accept a single VNode.
-    // NOTE: This assumes `Fragment` is in scope where the generated function runs.
-    let children = nodes.iter().map(emit_node).collect::<Vec<_>>().join(", ");
-    format!("h(Fragment, {{}}, [{}])", children)
 }
 
+/// Emit a node as is, without flags or hoisting
 fn emit_node(n: &Node) -> String {
     match n {
         Node::Text(t) => emit_text(t),
-        Node::Element(el) => emit_element(el),
+        Node::Element(el) => emit_element(el, None, &mut emit_node),
     }
 }
 
 fn emit_text(t: &str) -> String {
     // If text contains ${...}, keep it as a template literal.
-    if t.contains("${") {
+    if is_dynamic_text(t) {
         let lit = t.replace('`', "\\`");
         return format!("`{}`", lit);
     }
     js_string(t)
 }
 
-fn emit_element(el: &ElementNode) -> String {
+fn emit_element(
+    el: &ElementNode,
+    flag: Option<(String, AttrValue)>,
+    emit_child: &mut dyn FnMut(&Node) -> String,
+) -> String {
     let tag_js = js_string(&el.tag);
-    let attrs_js = emit_attrs(&el.attrs);
+    let attrs_js = emit_attrs(el.attrs.iter().chain(flag.as_ref()));
     if el.self_closing || el.children.is_empty() {
         return format!("h({}, {})", tag_js, attrs_js);
     }
-    let children = el.children.iter().map(emit_node).collect::<Vec<_>>();
+    let children = el.children.iter().map(emit_child).collect::<Vec<_>>();
     if children.len() == 1 {
         format!("h({}, {}, {})", tag_js, attrs_js, children[0])
     } else {
@@ -82,10 +140,7 @@ fn emit_element(el: &ElementNode) -> String {
     }
 }
 
-fn emit_attrs(attrs: &[(String, AttrValue)]) -> String {
-    if attrs.is_empty() {
-        return "{}".to_string();
-    }
+fn emit_attrs<'a>(attrs: impl Iterator<Item = &'a (String, AttrValue)>) -> String {
     let mut parts = Vec::new();
     for (k, v) in attrs {
         let key = js_string(k);
@@ -96,9 +151,27 @@ fn emit_attrs(attrs: &[(String, AttrValue)]) -> String {
         };
         parts.push(format!("{}: {}", key, val));
     }
+    if parts.is_empty() {
+        return "{}".to_string();
+    }
     format!("{{{}}}", parts.join(", "))
 }
 
+fn is_dynamic_text(t: &str) -> bool {
+    t.contains("${")
+}
+
+/// Whether nothing in the element's subtree depends on render-time values
+fn is_static_element(el: &ElementNode) -> bool {
+    el.attrs
+        .iter()
+        .all(|(_, v)| !matches!(v, AttrValue::JsExpr(_)))
+        && el.children.iter().all(|child| match child {
+            Node::Text(t) => !is_dynamic_text(t),
+            Node::Element(el) => is_static_element(el),
+        })
+}
+
 fn js_string(s: &str) -> String {
     // Emit a JS string literal with minimal escaping.
     let mut out = String::with_capacity(s.len() + 2);
@@ -391,7 +464,7 @@ fn capitalize(s: &str) -> String {
     }
 }
 
-fn is_void_element(tag: &str) -> bool {
+pub(crate) fn is_void_element(tag: &str) -> bool {
     matches!(
         tag,
         "area"
@@ -0,0 +1,463 @@
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::rc::{Rc, Weak};
+
+use crate::error_boundary::ErrorBoundaryInstance;
+use crate::patch::NodeId;
+use crate::reactivity::{effect, untrack, Effect, Scope};
+use crate::render::VNode;
+use crate::suspense::{current_suspense, with_suspense, SuspenseInstance, SuspenseState};
+use crate::transition::TransitionRun;
+
+/// Reusable piece of UI with its own props, local state and reactive scope
+///
+/// A component is created once when it is mounted and kept across renders
+/// of its parent. `render` runs inside an effect: when a signal it reads
+/// changes, only this component re-renders and its subtree is patched.
+/// Parents re-render it when its props are no longer equal or it is given
+/// children. The `on_mount`, `on_update` and `on_unmount` hooks run around
+/// the DOM changes, e.g. to set up a third-party widget.
+///
+/// ```ignore
+/// struct Counter {
+///     count: Signal<i32>,
+/// }
+///
+/// impl Component for Counter {
+///     type Props = i32;
+///
+///     fn create(start: &i32) -> Self {
+///         Counter { count: signal(*start) }
+///     }
+///
+///     fn render(&self, _start: &i32, _children: &[VNode]) -> VNode {
+///         let count = self.count.clone();
+///         VNode::element("button")
+///             .on("click", move |_| count.set(count.peek() + 1))
+///             .add_child(VNode::text(&self.count.get().to_string()))
+///     }
+/// }
+///
+/// let view = VNode::component::<Counter>(0);
+/// ```
+pub trait Component: Sized + 'static {
+    type Props: PartialEq + 'static;
+
+    /// Create the component when it is mounted
+    ///
+    /// Local signals belong here. Reads are not tracked, and effects created
+    /// here are disposed when the component is unmounted.
+    fn create(props: &Self::Props) -> Self;
+
+    /// Describe the component's content from its props and the children it
+    /// was given
+    fn render(&self, props: &Self::Props, children: &[VNode]) -> VNode;
+
+    /// Called once the component's nodes are in the DOM, refs included
+    ///
+    /// Effects created here are disposed when the component is unmounted,
+    /// which makes it the place to subscribe to outside sources.
+    fn on_mount(&self, props: &Self::Props) {
+        let _ = props;
+    }
+
+    /// Called after a re-render of the component was applied to the DOM
+    fn on_update(&self, props: &Self::Props) {
+        let _ = props;
+    }
+
+    /// Called before the component's nodes are removed from the DOM, after
+    /// its effects were disposed
+    fn on_unmount(&self, props: &Self::Props) {
+        let _ = props;
+    }
+}
+
+/// Point in a component's life at which the renderer calls its hooks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Lifecycle {
+    /// After its nodes were inserted
+    Mount,
+    /// After a re-render was patched in
+    Update,
+    /// Before its nodes are removed
+    Unmount,
+}
+
+/// A component to mount: its type and props, with children kept on the vnode
+#[derive(Clone)]
+pub(crate) struct ComponentSpec {
+    type_id: TypeId,
+    props: Rc<dyn Any>,
+    create: fn(&Rc<dyn Any>) -> Box<dyn ErasedComponent>,
+    props_eq: fn(&Rc<dyn Any>, &Rc<dyn Any>) -> bool,
+}
+
+impl ComponentSpec {
+    pub(crate) fn new<C: Component>(props: C::Props) -> Self {
+        ComponentSpec {
+            type_id: TypeId::of::<C>(),
+            props: Rc::new(props),
+            create: create_erased::<C>,
+            props_eq: |a, b| Erased::<C>::props(a) == Erased::<C>::props(b),
+        }
+    }
+
+    pub(crate) fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// Whether both mount the same component type with equal props
+    pub(crate) fn same_as(&self, other: &ComponentSpec) -> bool {
+        self.type_id == other.type_id && (self.props_eq)(&self.props, &other.props)
+    }
+
+    /// Create the component and render it once, e.g. on the server, then
+    /// dispose whatever it set up
+    pub(crate) fn render_once(&self, children: &[VNode]) -> VNode {
+        let scope = Scope::new();
+        let output = scope.run(|| untrack(|| (self.create)(&self.props).render(children)));
+        scope.dispose();
+        output
+    }
+}
+
+/// Components rendered on the server inside a suspense boundary
+///
+/// They are kept until the boundary is written, so rendering its content
+/// again once its resources resolved reuses them, and the resources they
+/// hold, instead of starting over. Components are matched by position in
+/// render order and type.
+#[derive(Default)]
+pub(crate) struct KeptComponents {
+    scope: Scope,
+    components: Vec<(TypeId, Box<dyn ErasedComponent>)>,
+    next: usize,
+}
+
+impl KeptComponents {
+    /// Start rendering the content again from its first component
+    pub(crate) fn restart(&mut self) {
+        self.next = 0;
+    }
+
+    /// Render the next component, creating it unless kept from last time
+    pub(crate) fn render(&mut self, spec: &ComponentSpec, children: &[VNode]) -> VNode {
+        let index = self.next;
+        self.next += 1;
+        match self.components.get(index) {
+            Some((type_id, component)) if *type_id == spec.type_id => {
+                component.update_props(&spec.props);
+            }
+            _ => {
+                self.components.truncate(index);
+                let component = self.scope.run(|| untrack(|| (spec.create)(&spec.props)));
+                self.components.push((spec.type_id, component));
+            }
+        }
+        let component = &self.components[index].1;
+        self.scope.run(|| untrack(|| component.render(children)))
+    }
+}
+
+impl Drop for KeptComponents {
+    fn drop(&mut self) {
+        self.scope.dispose();
+    }
+}
+
+/// Component part of a `VNode`, holding the instance once mounted
+#[derive(Clone)]
+pub(crate) struct ComponentNode {
+    pub(crate) spec: ComponentSpec,
+    pub(crate) instance: Option<Rc<ComponentInstance>>,
+}
+
+impl fmt::Debug for ComponentNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ComponentNode")
+            .field("mounted", &self.instance.is_some())
+            .finish()
+    }
+}
+
+/// Object-safe view of a mounted `Component` and its current props
+trait ErasedComponent {
+    fn render(&self, children: &[VNode]) -> VNode;
+
+    /// Replace the props if they differ; returns whether they did
+    fn update_props(&self, props: &Rc<dyn Any>) -> bool;
+
+    fn lifecycle(&self, hook: Lifecycle);
+}
+
+struct Erased<C: Component> {
+    component: C,
+    props: RefCell<Rc<dyn Any>>,
+}
+
+impl<C: Component> Erased<C> {
+    fn props(props: &Rc<dyn Any>) -> &C::Props {
+        props
+            .downcast_ref()
+            .expect("component props are of the component's own type")
+    }
+}
+
+impl<C: Component> ErasedComponent for Erased<C> {
+    fn render(&self, children: &[VNode]) -> VNode {
+        let props = Rc::clone(&self.props.borrow());
+        self.component.render(Self::props(&props), children)
+    }
+
+    fn update_props(&self, props: &Rc<dyn Any>) -> bool {
+        if Self::props(&self.props.borrow()) == Self::props(props) {
+            return false;
+        }
+        *self.props.borrow_mut() = Rc::clone(props);
+        true
+    }
+    fn lifecycle(&self, hook: Lifecycle) {
+        let props = Rc::clone(&self.props.borrow());
+        let props = Self::props(&props);
+        match hook {
+            Lifecycle::Mount => self.component.on_mount(props),
+            Lifecycle::Update => self.component.on_update(props),
+            Lifecycle::Unmount => self.component.on_unmount(props),
+        }
+    }
+}
+
+fn create_erased<C: Component>(props: &Rc<dyn Any>) -> Box<dyn ErasedComponent> {
+    Box::new(Erased::<C> {
+        component: C::create(Erased::<C>::props(props)),
+        props: RefCell::new(Rc::clone(props)),
+    })
+}
+
+/// Components that re-rendered on their own, suspense boundaries whose
+/// resources resolved, error boundaries reset and elements done leaving,
+/// waiting to be patched
+#[derive(Default)]
+pub(crate) struct ComponentQueue {
+    dirty: RefCell<Vec<Weak<ComponentInstance>>>,
+    ready: RefCell<Vec<Weak<SuspenseInstance>>>,
+    resets: RefCell<Vec<Weak<ErrorBoundaryInstance>>>,
+    left: RefCell<Vec<(NodeId, Rc<TransitionRun>)>>,
+    notify: RefCell<Option<Rc<dyn Fn()>>>,
+}
+
+impl ComponentQueue {
+    pub(crate) fn set_notify(&self, notify: Rc<dyn Fn()>) {
+        *self.notify.borrow_mut() = Some(notify);
+    }
+
+    pub(crate) fn take(&self) -> Vec<Rc<ComponentInstance>> {
+        std::mem::take(&mut *self.dirty.borrow_mut())
+            .into_iter()
+            .filter_map(|instance| instance.upgrade())
+            .collect()
+    }
+
+    pub(crate) fn take_ready(&self) -> Vec<Rc<SuspenseInstance>> {
+        std::mem::take(&mut *self.ready.borrow_mut())
+            .into_iter()
+            .filter_map(|boundary| boundary.upgrade())
+            .collect()
+    }
+
+    pub(crate) fn take_resets(&self) -> Vec<Rc<ErrorBoundaryInstance>> {
+        std::mem::take(&mut *self.resets.borrow_mut())
+            .into_iter()
+            .filter_map(|boundary| boundary.upgrade())
+            .collect()
+    }
+
+    pub(crate) fn take_left(&self) -> Vec<(NodeId, Rc<TransitionRun>)> {
+        std::mem::take(&mut *self.left.borrow_mut())
+    }
+
+    fn push(&self, instance: Weak<ComponentInstance>) {
+        self.dirty.borrow_mut().push(instance);
+        self.notify();
+    }
+
+    pub(crate) fn push_ready(&self, boundary: Weak<SuspenseInstance>) {
+        self.ready.borrow_mut().push(boundary);
+        self.notify();
+    }
+
+    pub(crate) fn push_reset(&self, boundary: Weak<ErrorBoundaryInstance>) {
+        self.resets.borrow_mut().push(boundary);
+        self.notify();
+    }
+
+    pub(crate) fn push_left(&self, id: NodeId, run: Rc<TransitionRun>) {
+        self.left.borrow_mut().push((id, run));
+        self.notify();
+    }
+
+    fn notify(&self) {
+        let notify = self.notify.borrow().clone();
+        if let Some(notify) = notify {
+            notify();
+        }
+    }
+}
+
+/// A mounted component
+///
+/// Its render effect stores each new output in `pending`. Runs requested by
+/// the reconciler are picked up right away; runs caused by a signal change
+/// put the instance in the `ComponentQueue`. Beneath an error boundary, a
+/// render that panics outputs an empty placeholder and leaves the panic in
+/// `failure` for the reconciler to report. Effects created by `render` are
+/// owned by `render_scope`, which is disposed before each re-render.
+pub(crate) struct ComponentInstance {
+    component: Box<dyn ErasedComponent>,
+    children: RefCell<Vec<VNode>>,
+    /// Output currently rendered to the DOM, with node ids assigned
+    pub(crate) output: RefCell<Option<VNode>>,
+    pending: RefCell<Option<VNode>>,
+    /// DOM parent of the output and the root it was rendered into
+    pub(crate) parent: Cell<NodeId>,
+    pub(crate) root: Cell<NodeId>,
+    /// Boundary it was mounted beneath, which its renders may suspend
+    suspense: Option<Rc<SuspenseState>>,
+    /// Error boundary it was mounted beneath, catching its panics
+    pub(crate) error_boundary: Option<Weak<ErrorBoundaryInstance>>,
+    failure: RefCell<Option<Box<dyn Any + Send>>>,
+    scope: Scope,
+    render_scope: Scope,
+    effect: RefCell<Option<Effect>>,
+    inline: Cell<bool>,
+    mounted: Cell<bool>,
+}
+
+impl ComponentInstance {
+    /// Create the component described by `spec` and render it once
+    ///
+    /// Beneath an error boundary, a panic while creating the component is
+    /// returned instead.
+    pub(crate) fn mount(
+        spec: &ComponentSpec,
+        children: Vec<VNode>,
+        parent: NodeId,
+        root: NodeId,
+        error_boundary: Option<&Rc<ErrorBoundaryInstance>>,
+        queue: &Rc<ComponentQueue>,
+    ) -> Result<Rc<Self>, Box<dyn Any + Send>> {
+        let scope = Scope::new();
+        let create = || scope.run(|| untrack(|| (spec.create)(&spec.props)));
+        let component = match error_boundary {
+            Some(_) => catch_unwind(AssertUnwindSafe(create)).inspect_err(|_| scope.dispose())?,
+            None => create(),
+        };
+        let instance = Rc::new(ComponentInstance {
+            component,
+            children: RefCell::new(children),
+            output: RefCell::new(None),
+            pending: RefCell::new(None),
+            parent: Cell::new(parent),
+            root: Cell::new(root),
+            suspense: current_suspense(),
+            error_boundary: error_boundary.map(Rc::downgrade),
+            failure: RefCell::new(None),
+            scope,
+            render_scope: Scope::new(),
+            effect: RefCell::new(None),
+            inline: Cell::new(true),
+            mounted: Cell::new(true),
+        });
+
+        let weak = Rc::downgrade(&instance);
+        let queue = Rc::clone(queue);
+        let handle = instance.scope.run(|| {
+            effect(move || {
+                let Some(instance) = weak.upgrade() else {
+                    return;
+                };
+                let children = instance.children.borrow().clone();
+                instance.render_scope.dispose();
+                let render = || {
+                    instance.render_scope.run(|| {
+                        with_suspense(instance.suspense.clone(), || {
+                            instance.component.render(&children)
+                        })
+                    })
+                };
+                let output = match instance.error_boundary {
+                    Some(_) => catch_unwind(AssertUnwindSafe(render)).unwrap_or_else(|panic| {
+                        *instance.failure.borrow_mut() = Some(panic);
+                        VNode::empty()
+                    }),
+                    None => render(),
+                };
+                *instance.pending.borrow_mut() = Some(output);
+                if !instance.inline.get() {
+                    queue.push(Weak::clone(&weak));
+                }
+            })
+        });
+        *instance.effect.borrow_mut() = Some(handle);
+        instance.inline.set(false);
+        Ok(instance)
+    }
+
+    /// Pass new props and children from the parent, re-rendering if needed
+    pub(crate) fn update(&self, spec: &ComponentSpec, children: Vec<VNode>) {
+        let had_children = !self.children.borrow().is_empty();
+        let changed = self.component.update_props(&spec.props);
+        if !changed && !had_children && children.is_empty() {
+            return;
+        }
+
+        *self.children.borrow_mut() = children;
+        if let Some(effect) = self.effect.borrow().clone() {
+            self.inline.set(true);
+            effect.run();
+            self.inline.set(false);
+        }
+    }
+
+    /// Output rendered since the last patch, if any
+    pub(crate) fn take_pending(&self) -> Option<VNode> {
+        if !self.mounted.get() {
+            return None;
+        }
+        self.pending.borrow_mut().take()
+    }
+
+    /// Panic caught in the last render, if any
+    pub(crate) fn take_failure(&self) -> Option<Box<dyn Any + Send>> {
+        self.failure.borrow_mut().take()
+    }
+
+    /// Call the component's hook for `hook`
+    ///
+    /// Mount and update hooks of an instance unmounted since are skipped;
+    /// effects created by them belong to the component.
+    pub(crate) fn run_hook(&self, hook: Lifecycle) {
+        match hook {
+            Lifecycle::Mount | Lifecycle::Update => {
+                if self.mounted.get() {
+                    self.scope
+                        .run(|| untrack(|| self.component.lifecycle(hook)));
+                }
+            }
+            Lifecycle::Unmount => untrack(|| self.component.lifecycle(hook)),
+        }
+    }
+
+    /// Stop the component: dispose its effects and forget its output
+    pub(crate) fn unmount(&self) -> Option<VNode> {
+        self.mounted.set(false);
+        self.scope.dispose();
+        self.render_scope.dispose();
+        self.effect.borrow_mut().take();
+        self.pending.borrow_mut().take();
+        self.output.borrow_mut().take()
+    }
+}
@@ -0,0 +1,2174 @@
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
+use std::rc::{Rc, Weak};
+
+use crate::backend::RenderError;
+use crate::component::{ComponentInstance, ComponentQueue, Lifecycle};
+use crate::error_boundary::{panic_message, BoundaryFailure, ErrorBoundaryInstance};
+use crate::event::HandlerTable;
+use crate::hydrate::{ExistingNode, HydrationMismatch, MismatchKind};
+use crate::namespace::{attribute_namespace, Namespace};
+use crate::node_ref::{NodeRef, RefUpdate};
+use crate::patch::{NodeId, Patch};
+use crate::portal::PortalTarget;
+use crate::prop::PropValue;
+use crate::render::{NodeKind, PatchFlag, VNode};
+use crate::ssr::{is_fallback_marker, server_attributes, ERROR_END_MARKER, ERROR_MARKER};
+use crate::structure::vnode_label;
+use crate::suspense::{with_suspense, SuspenseInstance};
+use crate::transition::{Leaving, Phase, TransitionRun, TransitionStart};
+
+/// Platform-independent reconciler
+///
+/// Keeps the tree last rendered into each root and turns every new render
+/// into a list of `Patch`es against abstract node ids. It never touches a
+/// real DOM: a backend applies the patches, and native code can inspect them
+/// directly.
+#[derive(Default)]
+pub struct Reconciler {
+    next_id: u32,
+    free_ids: Vec<NodeId>,
+    roots: HashMap<NodeId, VNode>,
+    handlers: HandlerTable,
+    /// Whether roots rendered for the first time use event delegation
+    delegation: bool,
+    /// Event types listened for at each root in delegation mode
+    delegated_roots: HashMap<NodeId, BTreeSet<String>>,
+    /// Event types handled anywhere in each root, which the containers of
+    /// its portals listen for
+    root_events: HashMap<NodeId, BTreeSet<String>>,
+    /// Namespace the children of each element or root are created in, when
+    /// it is not HTML
+    namespaces: HashMap<NodeId, Namespace>,
+    /// Root of every mounted portal container
+    portals: BTreeMap<NodeId, NodeId>,
+    /// Ids given to the elements portals target
+    #[cfg(target_arch = "wasm32")]
+    targets: Vec<(web_sys::Element, NodeId)>,
+    /// Targets the backend does not know yet
+    #[cfg(target_arch = "wasm32")]
+    new_targets: Vec<(NodeId, web_sys::Element)>,
+    /// Root of the render in progress
+    current_root: Option<NodeId>,
+    /// Whether hydration fixes mismatches instead of failing
+    hydration_recovery: bool,
+    /// Mounted components that re-rendered on their own
+    components: Rc<ComponentQueue>,
+    /// Refs to set or clear once the current pass is applied, in order
+    refs: Vec<(NodeRef, RefUpdate)>,
+    /// Component hooks due once the current pass is applied
+    hooks: Vec<(Rc<ComponentInstance>, Lifecycle)>,
+    /// Innermost error boundary of what is being rendered
+    current_boundary: Option<Rc<ErrorBoundaryInstance>>,
+    /// Error boundaries that failed during the current pass, switched to
+    /// their fallback once it is done
+    failed: Vec<BoundaryFailure>,
+    /// Patches of the current pass rendered beneath each error boundary,
+    /// innermost first, which fail it if the backend rejects them
+    boundary_patches: Vec<(Rc<ErrorBoundaryInstance>, Range<usize>)>,
+    /// Enter and leave phases the current pass started, played once it is
+    /// applied
+    transitions: Vec<TransitionStart>,
+    /// Kept children of keyed lists whose moves the current pass animates
+    moves: Vec<TransitionStart>,
+    /// Removed elements still in the DOM until their leave phase ends
+    leaving: HashMap<NodeId, Leaving>,
+    patches: Vec<Patch>,
+}
+
+impl Reconciler {
+    /// Create an empty reconciler
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserve the id of a container node owned by the backend
+    ///
+    /// Root ids are never reused, even after `unmount`.
+    pub fn create_root(&mut self) -> NodeId {
+        let id = NodeId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    /// Event handlers of every mounted node, kept in sync with each render
+    pub fn handlers(&self) -> &HandlerTable {
+        &self.handlers
+    }
+
+    /// Listen for events at the root instead of on every node
+    ///
+    /// In delegation mode a root gets one listener per event type, dispatching
+    /// to the handlers along the event's path. The setting applies to roots
+    /// rendered into for the first time afterwards; mounted roots keep theirs.
+    pub fn set_event_delegation(&mut self, enabled: bool) {
+        self.delegation = enabled;
+    }
+
+    /// Create the children of `root` in `namespace`, e.g. for a container
+    /// that is an `<svg>` element
+    pub fn set_namespace(&mut self, root: NodeId, namespace: Namespace) {
+        match namespace {
+            Namespace::Html => self.namespaces.remove(&root),
+            namespace => self.namespaces.insert(root, namespace),
+        };
+    }
+
+    /// Re-render what does not match when hydrating, instead of failing
+    pub fn set_hydration_recovery(&mut self, enabled: bool) {
+        self.hydration_recovery = enabled;
+    }
+
+    /// The tree currently rendered into `root`, with node ids assigned
+    pub fn tree(&self, root: NodeId) -> Option<&VNode> {
+        self.roots.get(&root)
+    }
+
+    /// Diff `vnode` against what `root` currently holds
+    ///
+    /// The first render into a root creates the whole tree and appends it.
+    pub fn render(&mut self, root: NodeId, vnode: &VNode) -> Vec<Patch> {
+        self.start_pass();
+        let mut next = vnode.clone();
+        self.current_root = Some(root);
+
+        match self.roots.remove(&root) {
+            Some(current) => self.patch(root, &current, &mut next),
+            None => {
+                if self.delegation {
+                    self.delegated_roots.entry(root).or_default();
+                }
+                self.mount(root, &mut next, None);
+            }
+        }
+
+        self.roots.insert(root, next);
+        self.show_fallbacks();
+        self.current_root = None;
+        std::mem::take(&mut self.patches)
+    }
+
+    /// Call `notify` whenever a mounted component re-renders because one of
+    /// its signals changed; `update_components` then returns the patches
+    ///
+    /// `notify` may run while this reconciler is busy rendering, in which
+    /// case the update should be picked up once the render is done.
+    pub fn on_component_update(&mut self, notify: impl Fn() + 'static) {
+        self.components.set_notify(Rc::new(notify));
+    }
+
+    /// Patch every component that re-rendered on its own since the last
+    /// call, show the content of suspense boundaries that resolved, render
+    /// the content of error boundaries reset again and remove elements
+    /// done leaving
+    pub fn update_components(&mut self) -> Vec<Patch> {
+        self.start_pass();
+        for instance in self.components.take() {
+            let Some(mut next) = instance.take_pending() else {
+                continue;
+            };
+            self.check_failure(&instance);
+            let current = instance.output.borrow_mut().take();
+            if let Some(current) = current {
+                self.current_root = Some(instance.root.get());
+                let boundary = instance.error_boundary.as_ref().and_then(Weak::upgrade);
+                self.in_error_boundary(boundary, |this| {
+                    this.patch(instance.parent.get(), &current, &mut next)
+                });
+                self.current_root = None;
+            }
+            *instance.output.borrow_mut() = Some(next);
+            self.hooks.push((instance, Lifecycle::Update));
+        }
+        for boundary in self.components.take_ready() {
+            self.current_root = Some(boundary.root.get());
+            self.reveal(&boundary);
+            self.current_root = None;
+        }
+        for boundary in self.components.take_resets() {
+            self.current_root = Some(boundary.root.get());
+            self.reset(&boundary);
+            self.current_root = None;
+        }
+        for (id, run) in self.components.take_left() {
+            // The id may have left again since, or been cut short
+            if self
+                .leaving
+                .get(&id)
+                .is_some_and(|leaving| Rc::ptr_eq(&leaving.run, &run))
+            {
+                self.finish_leave(id);
+            }
+        }
+        self.show_fallbacks();
+        std::mem::take(&mut self.patches)
+    }
+
+    /// Show the fallback of error boundaries whose patches the backend
+    /// rejected, in place of their content
+    pub(crate) fn fail_boundaries(&mut self, failed: Vec<BoundaryFailure>) -> Vec<Patch> {
+        self.start_pass();
+        self.failed = failed;
+        self.show_fallbacks();
+        std::mem::take(&mut self.patches)
+    }
+
+    /// Remove whatever was rendered into `root`
+    pub fn unmount(&mut self, root: NodeId) -> Vec<Patch> {
+        self.start_pass();
+        if let Some(current) = self.roots.remove(&root) {
+            self.remove(&current);
+        }
+        self.root_events.remove(&root);
+        for event in self.delegated_roots.remove(&root).unwrap_or_default() {
+            self.patches
+                .push(Patch::RemoveEventListener { id: root, event });
+        }
+        std::mem::take(&mut self.patches)
+    }
+
+    /// Forget what the previous pass left behind
+    fn start_pass(&mut self) {
+        self.refs.clear();
+        self.hooks.clear();
+        self.failed.clear();
+        self.boundary_patches.clear();
+        self.transitions.clear();
+        self.moves.clear();
+    }
+
+    /// Refs set or cleared by the last render, update or unmount, to be
+    /// updated once its patches are applied
+    pub(crate) fn take_refs(&mut self) -> Vec<(NodeRef, RefUpdate)> {
+        std::mem::take(&mut self.refs)
+    }
+
+    /// Component hooks due since the last render, update or unmount:
+    /// unmount hooks before its patches are applied, the others after
+    pub(crate) fn take_hooks(&mut self) -> Vec<(Rc<ComponentInstance>, Lifecycle)> {
+        std::mem::take(&mut self.hooks)
+    }
+
+    /// Patches of the last render, update or unmount rendered beneath each
+    /// error boundary, innermost boundaries first
+    pub(crate) fn take_boundary_patches(
+        &mut self,
+    ) -> Vec<(Rc<ErrorBoundaryInstance>, Range<usize>)> {
+        std::mem::take(&mut self.boundary_patches)
+    }
+
+    /// Enter and leave phases started by the last render, update or
+    /// unmount, to play once its patches are applied
+    pub(crate) fn take_transitions(&mut self) -> Vec<TransitionStart> {
+        std::mem::take(&mut self.transitions)
+    }
+
+    /// Elements the last render, update or unmount moved within keyed
+    /// lists, to animate from where they were before its patches
+    pub(crate) fn take_moves(&mut self) -> Vec<TransitionStart> {
+        std::mem::take(&mut self.moves)
+    }
+
+    /// Elements targeted by portals for the first time since the last call,
+    /// to register with the backend before applying patches
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn take_targets(&mut self) -> Vec<(NodeId, web_sys::Element)> {
+        std::mem::take(&mut self.new_targets)
+    }
+
+    /// Point the ref of `vnode`, if any, at `id`
+    fn set_ref(&mut self, vnode: &VNode, id: NodeId) {
+        if let Some(node_ref) = &vnode.node_ref {
+            self.refs.push((node_ref.clone(), RefUpdate::Set(id)));
+        }
+    }
+
+    fn alloc_id(&mut self) -> NodeId {
+        self.free_ids.pop().unwrap_or_else(|| {
+            let id = NodeId(self.next_id);
+            self.next_id += 1;
+            id
+        })
+    }
+
+    /// Emit patches creating `vnode` and inserting it into `parent` before
+    /// `before`, or at the end
+    ///
+    /// A fragment inserts its children followed by an empty comment anchor,
+    /// so it keeps a position in `parent` even without children.
+    fn mount(&mut self, parent: NodeId, vnode: &mut VNode, before: Option<NodeId>) {
+        if vnode.component.is_some() {
+            let Some((instance, mut output)) = self.mount_component(parent, vnode) else {
+                self.mount(parent, vnode, before);
+                return;
+            };
+            self.mount(parent, &mut output, before);
+            *instance.output.borrow_mut() = Some(output);
+            self.hooks.push((Rc::clone(&instance), Lifecycle::Mount));
+            if let Some(component) = &mut vnode.component {
+                component.instance = Some(instance);
+            }
+            return;
+        }
+
+        if vnode.kind == NodeKind::Fragment {
+            for child in &mut vnode.children {
+                self.mount(parent, child, before);
+            }
+            self.mount_anchor(parent, vnode, before);
+            return;
+        }
+
+        if vnode.suspense.is_some() {
+            self.mount_suspense(parent, vnode, before);
+            return;
+        }
+
+        if vnode.error_boundary.is_some() {
+            self.mount_error_boundary(parent, vnode, before);
+            return;
+        }
+
+        if vnode.portal.is_some() {
+            self.mount_portal(parent, vnode);
+            self.mount_anchor(parent, vnode, before);
+            return;
+        }
+
+        let id = self.alloc_id();
+        vnode.id = Some(id);
+        self.set_ref(vnode, id);
+
+        match vnode.kind {
+            NodeKind::Text => self.patches.push(Patch::CreateText {
+                id,
+                text: vnode.text_content.clone().unwrap_or_default(),
+            }),
+            NodeKind::Comment => self.patches.push(Patch::CreateComment {
+                id,
+                text: vnode.text_content.clone().unwrap_or_default(),
+            }),
+            _ => {
+                let tag = vnode.node_type.clone();
+                let patch = match self.element_namespace(id, parent, &tag).uri() {
+                    Some(namespace) => Patch::CreateElementNs {
+                        id,
+                        namespace: namespace.to_string(),
+                        tag,
+                    },
+                    None => Patch::CreateElement { id, tag },
+                };
+                self.patches.push(patch);
+
+                for (name, value) in &vnode.props {
+                    self.patch_prop(id, name, None, Some(value));
+                }
+
+                for (event, handler) in &vnode.handlers {
+                    self.handlers.insert(id, event, handler.clone());
+                    self.listen(id, event);
+                }
+
+                // Children are built while their parent is still detached
+                for child in &mut vnode.children {
+                    self.mount(id, child, None);
+                }
+
+                if vnode.transition.is_some() {
+                    self.enter(parent, vnode, id);
+                }
+            }
+        }
+
+        self.handlers.set_parent(id, parent);
+        self.patches
+            .push(Patch::InsertBefore { parent, id, before });
+    }
+
+    /// Create the component of `vnode` and render it once, returning it with
+    /// its output
+    ///
+    /// A component that panics while created beneath an error boundary
+    /// fails the boundary and is replaced by an empty placeholder.
+    fn mount_component(
+        &mut self,
+        parent: NodeId,
+        vnode: &mut VNode,
+    ) -> Option<(Rc<ComponentInstance>, VNode)> {
+        let root = self.current_root.expect("mounting outside of a render");
+        let component = vnode.component.as_ref().expect("mounting a component");
+        let mounted = ComponentInstance::mount(
+            &component.spec,
+            vnode.children.clone(),
+            parent,
+            root,
+            self.current_boundary.as_ref(),
+            &self.components,
+        );
+        match mounted {
+            Ok(instance) => {
+                let output = instance
+                    .take_pending()
+                    .expect("components render when mounted");
+                self.check_failure(&instance);
+                Some((instance, output))
+            }
+            Err(panic) => {
+                self.fail(self.current_boundary.clone(), panic);
+                *vnode = VNode::empty();
+                None
+            }
+        }
+    }
+
+    /// Report a panic caught while `instance` rendered, if any
+    fn check_failure(&mut self, instance: &ComponentInstance) {
+        if let Some(panic) = instance.take_failure() {
+            let boundary = instance.error_boundary.as_ref().and_then(Weak::upgrade);
+            self.fail(boundary, panic);
+        }
+    }
+
+    /// Fail `boundary` with a caught panic, or carry on panicking without
+    /// one
+    fn fail(&mut self, boundary: Option<Rc<ErrorBoundaryInstance>>, panic: Box<dyn Any + Send>) {
+        match boundary {
+            Some(boundary) => {
+                let error = RenderError::Panic(panic_message(panic.as_ref()));
+                self.failed.push((boundary, error));
+            }
+            None => std::panic::resume_unwind(panic),
+        }
+    }
+
+    /// Namespace of a `tag` element mounted as `id` into `parent`,
+    /// remembering the one its children are in
+    fn element_namespace(&mut self, id: NodeId, parent: NodeId, tag: &str) -> Namespace {
+        let parent = self.namespaces.get(&parent).copied().unwrap_or_default();
+        let namespace = parent.of_element(tag);
+        match namespace.of_children(tag) {
+            Namespace::Html => self.namespaces.remove(&id),
+            children => self.namespaces.insert(id, children),
+        };
+        namespace
+    }
+
+    /// Insert the empty comment marking the end of a fragment, or the
+    /// position of a portal, as the id of `vnode`
+    fn mount_anchor(&mut self, parent: NodeId, vnode: &mut VNode, before: Option<NodeId>) {
+        let anchor = self.alloc_id();
+        vnode.id = Some(anchor);
+        self.patches.push(Patch::CreateComment {
+            id: anchor,
+            text: String::new(),
+        });
+        self.handlers.set_parent(anchor, parent);
+        self.patches.push(Patch::InsertBefore {
+            parent,
+            id: anchor,
+            before,
+        });
+    }
+
+    /// Mount the children of a portal into a new container appended to its
+    /// target
+    ///
+    /// The container stands in for the portal in `parent` when events
+    /// bubble, and listens for every event type handled in the root.
+    fn mount_portal(&mut self, parent: NodeId, vnode: &mut VNode) {
+        let root = self.current_root.expect("mounting outside of a render");
+        let portal = vnode.portal.as_mut().expect("mounting a portal");
+        let target = self.portal_target(&portal.target);
+        let container = self.alloc_id();
+        portal.container = Some(container);
+
+        self.patches.push(Patch::CreateElement {
+            id: container,
+            tag: "div".to_string(),
+        });
+        self.handlers.set_parent(container, parent);
+        self.portals.insert(container, root);
+        let events = self.root_events.get(&root).cloned().unwrap_or_default();
+        for event in events {
+            self.listen_portal(root, container, event);
+        }
+
+        for child in &mut vnode.children {
+            self.mount(container, child, None);
+        }
+        self.patches.push(Patch::InsertBefore {
+            parent: target,
+            id: container,
+            before: None,
+        });
+    }
+
+    /// Id of the node a portal renders into
+    fn portal_target(&mut self, target: &PortalTarget) -> NodeId {
+        match target {
+            PortalTarget::Node(id) => *id,
+            #[cfg(target_arch = "wasm32")]
+            PortalTarget::Element(element) => {
+                if let Some((_, id)) = self.targets.iter().find(|(known, _)| known == element) {
+                    return *id;
+                }
+                let id = self.create_root();
+                self.targets.push((element.clone(), id));
+                self.new_targets.push((id, element.clone()));
+                id
+            }
+        }
+    }
+
+    /// Mount a suspense boundary: its content into a detached container,
+    /// and its fallback in its place while the content is pending
+    fn mount_suspense(&mut self, parent: NodeId, vnode: &mut VNode, before: Option<NodeId>) {
+        let boundary = self.suspense_instance(parent);
+        self.mount_hidden(&boundary, vnode);
+        if boundary.state.is_pending() {
+            let mut fallback = suspense_fallback(vnode);
+            self.mount(parent, &mut fallback, before);
+            *boundary.fallback.borrow_mut() = Some(fallback);
+        }
+        self.mount_anchor(parent, vnode, before);
+        self.finish_suspense(boundary, vnode);
+    }
+
+    /// New boundary instance, queued to be revealed once its resources
+    /// resolve
+    fn suspense_instance(&mut self, parent: NodeId) -> Rc<SuspenseInstance> {
+        let root = self.current_root.expect("mounting outside of a render");
+        let boundary = Rc::new(SuspenseInstance::new(parent, root));
+        let weak = Rc::downgrade(&boundary);
+        let queue = Rc::clone(&self.components);
+        boundary.state.set_on_ready(Some(Rc::new(move || {
+            queue.push_ready(Weak::clone(&weak));
+        })));
+        boundary
+    }
+
+    /// Mount the content of a boundary into a new detached container
+    fn mount_hidden(&mut self, boundary: &Rc<SuspenseInstance>, vnode: &VNode) {
+        let parent = boundary.parent.get();
+        let container = self.alloc_id();
+        self.patches.push(Patch::CreateElement {
+            id: container,
+            tag: "div".to_string(),
+        });
+        self.handlers.set_parent(container, parent);
+        if let Some(namespace) = self.namespaces.get(&parent).copied() {
+            self.namespaces.insert(container, namespace);
+        }
+        boundary.container.set(Some(container));
+
+        let mut content = vnode.children.clone();
+        self.in_suspense(boundary, |this| {
+            for child in &mut content {
+                this.mount(container, child, None);
+            }
+        });
+        *boundary.content.borrow_mut() = content;
+    }
+
+    /// Record the anchor of a boundary just mounted or hydrated as `vnode`,
+    /// showing its content right away unless it is pending
+    fn finish_suspense(&mut self, boundary: Rc<SuspenseInstance>, vnode: &mut VNode) {
+        boundary.anchor.set(vnode.id);
+        if !boundary.state.is_pending() {
+            self.reveal(&boundary);
+        }
+        if let Some(suspense) = &mut vnode.suspense {
+            suspense.instance = Some(boundary);
+        }
+    }
+
+    /// Run `f` with pending resource reads suspending `boundary`
+    ///
+    /// Mount hooks of the components mounted while its content is hidden
+    /// wait until it is revealed.
+    fn in_suspense(&mut self, boundary: &Rc<SuspenseInstance>, f: impl FnOnce(&mut Self)) {
+        let start = self.hooks.len();
+        with_suspense(Some(Rc::clone(&boundary.state)), || f(self));
+        if !boundary.is_hidden() {
+            return;
+        }
+        let mut deferred = boundary.deferred.borrow_mut();
+        for (instance, hook) in self.hooks.split_off(start) {
+            match hook {
+                Lifecycle::Mount => deferred.push(instance),
+                hook => self.hooks.push((instance, hook)),
+            }
+        }
+    }
+
+    /// Move the hidden content of a boundary in place of its fallback
+    fn reveal(&mut self, boundary: &Rc<SuspenseInstance>) {
+        let Some(container) = boundary.container.take() else {
+            return;
+        };
+        let parent = boundary.parent.get();
+        let anchor = boundary.anchor.get();
+
+        let fallback = boundary.fallback.borrow_mut().take();
+        if let Some(fallback) = fallback {
+            self.remove(&fallback);
+        }
+        let content = boundary.content.borrow().clone();
+        for child in &content {
+            self.move_before(parent, child, anchor);
+            self.reparent(child, parent);
+        }
+
+        self.patches.push(Patch::Remove { id: container });
+        self.handlers.remove_node(container);
+        self.namespaces.remove(&container);
+        self.free_ids.push(container);
+        for instance in boundary.deferred.take() {
+            self.hooks.push((instance, Lifecycle::Mount));
+        }
+    }
+
+    /// Record `parent` as the parent of the top-level nodes of a mounted
+    /// `vnode` that was moved there from another parent
+    fn reparent(&mut self, vnode: &VNode, parent: NodeId) {
+        if let Some(instance) = mounted_instance(vnode) {
+            instance.parent.set(parent);
+            if let Some(output) = instance.output.borrow().as_ref() {
+                self.reparent(output, parent);
+            }
+        }
+        if let Some(boundary) = mounted_suspense(vnode) {
+            boundary.parent.set(parent);
+            if !boundary.is_hidden() {
+                for child in boundary.content.borrow().iter() {
+                    self.reparent(child, parent);
+                }
+            }
+            if let Some(fallback) = boundary.fallback.borrow().as_ref() {
+                self.reparent(fallback, parent);
+            }
+        }
+        if let Some(boundary) = mounted_error_boundary(vnode) {
+            boundary.parent.set(parent);
+            for child in boundary.content.borrow().iter() {
+                self.reparent(child, parent);
+            }
+            if let Some(fallback) = boundary.fallback.borrow().as_ref() {
+                self.reparent(fallback, parent);
+            }
+        }
+        if vnode.kind == NodeKind::Fragment {
+            for child in &vnode.children {
+                self.reparent(child, parent);
+            }
+        }
+        if let Some(container) = portal_container(vnode) {
+            self.handlers.set_parent(container, parent);
+        }
+        if let Some(id) = vnode.id {
+            self.handlers.set_parent(id, parent);
+        }
+    }
+
+    /// Diff the content and fallback of a mounted boundary
+    fn patch_suspense(&mut self, parent: NodeId, boundary: &Rc<SuspenseInstance>, new: &VNode) {
+        boundary.parent.set(parent);
+        let (content_parent, end) = match boundary.container.get() {
+            Some(container) => (container, None),
+            None => (parent, boundary.anchor.get()),
+        };
+        let old = boundary.content.take();
+        let mut content = new.children.clone();
+        self.in_suspense(boundary, |this| {
+            this.patch_children(content_parent, &old, &mut content, end)
+        });
+        *boundary.content.borrow_mut() = content;
+
+        let old = boundary.fallback.borrow_mut().take();
+        if let Some(old) = old {
+            let mut fallback = suspense_fallback(new);
+            self.patch(parent, &old, &mut fallback);
+            *boundary.fallback.borrow_mut() = Some(fallback);
+        }
+        if !boundary.state.is_pending() {
+            self.reveal(boundary);
+        }
+    }
+
+    /// Mount an error boundary: its content, like the children of a
+    /// fragment, followed by its anchor
+    fn mount_error_boundary(&mut self, parent: NodeId, vnode: &mut VNode, before: Option<NodeId>) {
+        let boundary = self.error_boundary_instance(parent, vnode);
+        let mut content = vnode.children.clone();
+        self.in_error_boundary(Some(Rc::clone(&boundary)), |this| {
+            for child in &mut content {
+                this.mount(parent, child, before);
+            }
+        });
+        *boundary.content.borrow_mut() = content;
+        self.mount_anchor(parent, vnode, before);
+        self.finish_error_boundary(boundary, vnode);
+    }
+
+    /// New boundary instance for `vnode`, beneath the current one
+    fn error_boundary_instance(
+        &mut self,
+        parent: NodeId,
+        vnode: &VNode,
+    ) -> Rc<ErrorBoundaryInstance> {
+        let root = self.current_root.expect("mounting outside of a render");
+        let node = vnode.error_boundary.as_ref().expect("an error boundary");
+        let boundary = ErrorBoundaryInstance::new(
+            node,
+            parent,
+            root,
+            self.current_boundary.as_ref(),
+            &self.components,
+        );
+        *boundary.children.borrow_mut() = vnode.children.clone();
+        Rc::new(boundary)
+    }
+
+    /// Record the anchor of a boundary just mounted or hydrated as `vnode`
+    fn finish_error_boundary(&mut self, boundary: Rc<ErrorBoundaryInstance>, vnode: &mut VNode) {
+        boundary.anchor.set(vnode.id);
+        if let Some(node) = &mut vnode.error_boundary {
+            node.instance = Some(boundary);
+        }
+    }
+
+    /// Run `f` with failures of what it renders failing `boundary`,
+    /// recording the patches it emits as rendered beneath it
+    fn in_error_boundary<R>(
+        &mut self,
+        boundary: Option<Rc<ErrorBoundaryInstance>>,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let start = self.patches.len();
+        let outer = std::mem::replace(&mut self.current_boundary, boundary);
+        let result = f(self);
+        let boundary = std::mem::replace(&mut self.current_boundary, outer);
+        if let Some(boundary) = boundary {
+            if self.patches.len() > start {
+                self.boundary_patches
+                    .push((boundary, start..self.patches.len()));
+            }
+        }
+        result
+    }
+
+    /// Diff the content of a mounted error boundary, or its fallback while
+    /// it shows it
+    fn patch_error_boundary(
+        &mut self,
+        parent: NodeId,
+        boundary: &Rc<ErrorBoundaryInstance>,
+        new: &VNode,
+    ) {
+        boundary.parent.set(parent);
+        if let Some(node) = &new.error_boundary {
+            boundary.set_fallback(node);
+        }
+        *boundary.children.borrow_mut() = new.children.clone();
+
+        if boundary.has_failed() {
+            let old = boundary.fallback.borrow_mut().take();
+            if let Some(old) = old {
+                let mut fallback = boundary.render_fallback();
+                self.patch(parent, &old, &mut fallback);
+                *boundary.fallback.borrow_mut() = Some(fallback);
+            }
+            return;
+        }
+
+        let old = boundary.content.take();
+        let mut content = new.children.clone();
+        self.in_error_boundary(Some(Rc::clone(boundary)), |this| {
+            this.patch_children(parent, &old, &mut content, boundary.anchor.get())
+        });
+        *boundary.content.borrow_mut() = content;
+    }
+
+    /// Replace the content of the error boundaries that failed during this
+    /// pass with their fallback
+    ///
+    /// Failures of a fallback reach the boundary around it in turn.
+    fn show_fallbacks(&mut self) {
+        while !self.failed.is_empty() {
+            for (boundary, error) in std::mem::take(&mut self.failed) {
+                self.show_fallback(&boundary, error);
+            }
+        }
+    }
+
+    fn show_fallback(&mut self, boundary: &Rc<ErrorBoundaryInstance>, error: RenderError) {
+        // The first failure is shown; the content is gone after that
+        if !boundary.is_mounted() || boundary.has_failed() {
+            return;
+        }
+        let root = self.current_root.replace(boundary.root.get());
+        let parent = boundary.parent.get();
+        let anchor = boundary.anchor.get();
+
+        let content = boundary.content.take();
+        self.in_error_boundary(Some(Rc::clone(boundary)), |this| {
+            for child in &content {
+                this.remove(child);
+            }
+        });
+        *boundary.error.borrow_mut() = Some(error);
+
+        let mut fallback = boundary.render_fallback();
+        let outer = boundary.outer.as_ref().and_then(Weak::upgrade);
+        self.in_error_boundary(outer, |this| this.mount(parent, &mut fallback, anchor));
+        *boundary.fallback.borrow_mut() = Some(fallback);
+        self.current_root = root;
+    }
+
+    /// Mount the content of a reset error boundary again in place of its
+    /// fallback
+    fn reset(&mut self, boundary: &Rc<ErrorBoundaryInstance>) {
+        if !boundary.is_mounted() || !boundary.has_failed() {
+            return;
+        }
+        let parent = boundary.parent.get();
+        let anchor = boundary.anchor.get();
+
+        let fallback = boundary.fallback.borrow_mut().take();
+        if let Some(fallback) = fallback {
+            self.remove(&fallback);
+        }
+        boundary.error.take();
+
+        let mut content = boundary.children.borrow().clone();
+        self.in_error_boundary(Some(Rc::clone(boundary)), |this| {
+            for child in &mut content {
+                this.mount(parent, child, anchor);
+            }
+        });
+        *boundary.content.borrow_mut() = content;
+    }
+
+    /// Emit patches removing a mounted subtree and reclaim its ids
+    fn remove(&mut self, vnode: &VNode) {
+        self.detach(vnode);
+        self.reclaim(vnode);
+    }
+
+    /// Emit a `Remove` for every top-level DOM node of `vnode`
+    fn detach(&mut self, vnode: &VNode) {
+        if let Some(instance) = mounted_instance(vnode) {
+            if let Some(output) = instance.output.borrow().as_ref() {
+                self.detach(output);
+            }
+        }
+        if vnode.kind == NodeKind::Fragment {
+            for child in &vnode.children {
+                self.detach(child);
+            }
+        }
+        if let Some(boundary) = mounted_suspense(vnode) {
+            match boundary.container.get() {
+                Some(container) => self.patches.push(Patch::Remove { id: container }),
+                None => {
+                    for child in boundary.content.borrow().iter() {
+                        self.detach(child);
+                    }
+                }
+            }
+            if let Some(fallback) = boundary.fallback.borrow().as_ref() {
+                self.detach(fallback);
+            }
+        }
+        if let Some(boundary) = mounted_error_boundary(vnode) {
+            for child in boundary.content.borrow().iter() {
+                self.detach(child);
+            }
+            if let Some(fallback) = boundary.fallback.borrow().as_ref() {
+                self.detach(fallback);
+            }
+        }
+        if let Some(container) = portal_container(vnode) {
+            self.patches.push(Patch::Remove { id: container });
+        }
+        if let Some(id) = vnode.id {
+            if has_transition(vnode) {
+                self.leave(vnode, id);
+            } else {
+                self.patches.push(Patch::Remove { id });
+            }
+        }
+    }
+
+    /// Forget a detached subtree and reuse its ids, except that a leaving
+    /// element keeps those of its subtree until it is removed
+    fn reclaim(&mut self, vnode: &VNode) {
+        let start = self.free_ids.len();
+        self.reclaim_node(vnode);
+        if let Some(leaving) = vnode.id.and_then(|id| self.leaving.get_mut(&id)) {
+            leaving.ids = self.free_ids.split_off(start);
+        }
+    }
+
+    fn reclaim_node(&mut self, vnode: &VNode) {
+        if let Some(instance) = mounted_instance(vnode) {
+            // Mounted and removed within one pass, it never was in the DOM
+            let mounted = self.hooks.iter().position(|(mounted, hook)| {
+                Rc::ptr_eq(mounted, instance) && *hook == Lifecycle::Mount
+            });
+            match mounted {
+                Some(index) => {
+                    self.hooks.remove(index);
+                }
+                None => self.hooks.push((Rc::clone(instance), Lifecycle::Unmount)),
+            }
+            if let Some(output) = instance.unmount() {
+                self.reclaim(&output);
+            }
+            return;
+        }
+        if let Some(boundary) = mounted_suspense(vnode) {
+            boundary.state.set_on_ready(None);
+            boundary.deferred.take();
+            for child in boundary.content.take() {
+                self.reclaim(&child);
+            }
+            if let Some(fallback) = boundary.fallback.take() {
+                self.reclaim(&fallback);
+            }
+            if let Some(container) = boundary.container.take() {
+                self.handlers.remove_node(container);
+                self.namespaces.remove(&container);
+                self.free_ids.push(container);
+            }
+        }
+        if let Some(boundary) = mounted_error_boundary(vnode) {
+            boundary.anchor.set(None);
+            for child in boundary.content.take() {
+                self.reclaim(&child);
+            }
+            if let Some(fallback) = boundary.fallback.take() {
+                self.reclaim(&fallback);
+            }
+        }
+        if let Some(container) = portal_container(vnode) {
+            self.handlers.remove_node(container);
+            self.free_ids.push(container);
+            self.portals.remove(&container);
+        }
+        if let Some(id) = vnode.id {
+            self.handlers.remove_node(id);
+            self.namespaces.remove(&id);
+            self.drop_leaving(id);
+            self.free_ids.push(id);
+            if let Some(node_ref) = &vnode.node_ref {
+                self.refs.push((node_ref.clone(), RefUpdate::Clear(id)));
+            }
+        }
+        for child in &vnode.children {
+            self.reclaim(child);
+        }
+    }
+
+    /// Emit patches moving the DOM nodes of a mounted `vnode` before `before`
+    fn move_before(&mut self, parent: NodeId, vnode: &VNode, before: Option<NodeId>) {
+        if let Some(instance) = mounted_instance(vnode) {
+            if let Some(output) = instance.output.borrow().as_ref() {
+                self.move_before(parent, output, before);
+            }
+        }
+        if vnode.kind == NodeKind::Fragment {
+            for child in &vnode.children {
+                self.move_before(parent, child, before);
+            }
+        }
+        if let Some(boundary) = mounted_suspense(vnode) {
+            if !boundary.is_hidden() {
+                for child in boundary.content.borrow().iter() {
+                    self.move_before(parent, child, before);
+                }
+            }
+            if let Some(fallback) = boundary.fallback.borrow().as_ref() {
+                self.move_before(parent, fallback, before);
+            }
+        }
+        if let Some(boundary) = mounted_error_boundary(vnode) {
+            for child in boundary.content.borrow().iter() {
+                self.move_before(parent, child, before);
+            }
+            if let Some(fallback) = boundary.fallback.borrow().as_ref() {
+                self.move_before(parent, fallback, before);
+            }
+        }
+        if let Some(id) = vnode.id {
+            self.patches
+                .push(Patch::InsertBefore { parent, id, before });
+        }
+    }
+
+    /// Start the enter phase of an element being mounted, first removing
+    /// an element with the same key still leaving `parent`
+    fn enter(&mut self, parent: NodeId, vnode: &mut VNode, id: NodeId) {
+        if let Some(key) = &vnode.key {
+            let leaving = self
+                .leaving
+                .iter()
+                .find(|(_, leaving)| {
+                    leaving.parent == Some(parent) && leaving.key.as_ref() == Some(key)
+                })
+                .map(|(id, _)| *id);
+            if let Some(leaving) = leaving {
+                self.finish_leave(leaving);
+            }
+        }
+        let Some(node) = &mut vnode.transition else {
+            return;
+        };
+        for step in ["from", "active"] {
+            if let Some(name) = node.transition.class(Phase::Enter, step) {
+                self.patches.push(Patch::AddClass { id, name });
+            }
+        }
+        let run = TransitionRun::new(Phase::Enter);
+        node.run = Some(Rc::clone(&run));
+        self.transitions.push(TransitionStart {
+            id,
+            transition: Rc::clone(&node.transition),
+            run,
+            queue: Rc::downgrade(&self.components),
+        });
+    }
+
+    /// Start the leave phase of an element being removed, leaving it in
+    /// place until the phase ends
+    fn leave(&mut self, vnode: &VNode, id: NodeId) {
+        let node = vnode
+            .transition
+            .as_ref()
+            .expect("leaving without a transition");
+        let transition = Rc::clone(&node.transition);
+        if let Some(run) = &node.run {
+            // Mounted and removed within one pass, it never was in the DOM
+            let entering = self
+                .transitions
+                .iter()
+                .position(|start| Rc::ptr_eq(&start.run, run));
+            if let Some(index) = entering {
+                self.transitions.remove(index);
+                self.patches.push(Patch::Remove { id });
+                return;
+            }
+            // Cut short what it is still playing, with its classes
+            if run.end() {
+                let steps: &[&str] = match run.phase {
+                    Phase::Enter => &["from", "active", "to"],
+                    _ => &[""],
+                };
+                for step in steps {
+                    if let Some(name) = transition.class(run.phase, step) {
+                        self.patches.push(Patch::RemoveClass { id, name });
+                    }
+                }
+            }
+        }
+        for step in ["from", "active"] {
+            if let Some(name) = transition.class(Phase::Leave, step) {
+                self.patches.push(Patch::AddClass { id, name });
+            }
+        }
+        let run = TransitionRun::new(Phase::Leave);
+        self.leaving.insert(
+            id,
+            Leaving {
+                parent: self.handlers.parent(id),
+                key: vnode.key.clone(),
+                run: Rc::clone(&run),
+                ids: Vec::new(),
+            },
+        );
+        self.transitions.push(TransitionStart {
+            id,
+            transition,
+            run,
+            queue: Rc::downgrade(&self.components),
+        });
+    }
+
+    /// Remove a leaving element now and reuse the ids of its subtree
+    fn finish_leave(&mut self, id: NodeId) {
+        if let Some(leaving) = self.leaving.remove(&id) {
+            leaving.run.end();
+            self.patches.push(Patch::Remove { id });
+            self.free_ids.extend(leaving.ids);
+        }
+    }
+
+    /// Forget the elements leaving `parent`, which is removed with them
+    fn drop_leaving(&mut self, parent: NodeId) {
+        if self.leaving.is_empty() {
+            return;
+        }
+        let ids: Vec<NodeId> = self
+            .leaving
+            .iter()
+            .filter(|(_, leaving)| leaving.parent == Some(parent))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ids {
+            if let Some(leaving) = self.leaving.remove(&id) {
+                leaving.run.end();
+                self.free_ids.extend(leaving.ids);
+            }
+        }
+    }
+
+    /// Animate the moves of the kept children of a keyed list, see
+    /// `Transition::flip`
+    fn queue_moves(&mut self, children: &mut [VNode]) {
+        for child in children {
+            let (Some(id), Some(node)) = (child.id, &mut child.transition) else {
+                continue;
+            };
+            if !node.transition.flips() {
+                continue;
+            }
+            if let Some(run) = &node.run {
+                // Entering children are placed, not moved
+                if run.phase == Phase::Enter && run.is_active() {
+                    continue;
+                }
+                run.end();
+            }
+            let run = TransitionRun::new(Phase::Move);
+            node.run = Some(Rc::clone(&run));
+            self.moves.push(TransitionStart {
+                id,
+                transition: Rc::clone(&node.transition),
+                run,
+                queue: Rc::downgrade(&self.components),
+            });
+        }
+    }
+
+    /// Emit patches turning the DOM rendered for `old` into `new`
+    fn patch(&mut self, parent: NodeId, old: &VNode, new: &mut VNode) {
+        // Different node types or keys share nothing worth keeping
+        if !same_node(old, new) {
+            self.mount(parent, new, first_node(old));
+            self.remove(old);
+            return;
+        }
+
+        if let Some(instance) = mounted_instance(old) {
+            let component = new.component.as_mut().expect("same_node checks components");
+            instance.update(&component.spec, new.children.clone());
+            component.instance = Some(Rc::clone(instance));
+            if let Some(mut next) = instance.take_pending() {
+                self.check_failure(instance);
+                let current = instance.output.borrow_mut().take();
+                if let Some(current) = current {
+                    self.patch(parent, &current, &mut next);
+                }
+                *instance.output.borrow_mut() = Some(next);
+                self.hooks.push((Rc::clone(instance), Lifecycle::Update));
+            }
+            return;
+        }
+
+        // A static subtree stays as it was mounted when rendered again
+        if matches!(old.patch_flag, PatchFlag::Static(_)) && old.patch_flag == new.patch_flag {
+            *new = old.clone();
+            return;
+        }
+
+        let id = old.id.expect("patching an unmounted vnode");
+        new.id = Some(id);
+        if let (Some(old_node), Some(node)) = (&old.transition, &mut new.transition) {
+            node.run = old_node.run.clone();
+        }
+
+        if new.kind == NodeKind::Fragment {
+            // Children are diffed in the parent, ending at the anchor
+            self.patch_children(parent, &old.children, &mut new.children, Some(id));
+            return;
+        }
+
+        if new.suspense.is_some() {
+            let boundary = mounted_suspense(old).expect("same_node checks suspense boundaries");
+            self.patch_suspense(parent, boundary, new);
+            if let Some(suspense) = &mut new.suspense {
+                suspense.instance = Some(Rc::clone(boundary));
+            }
+            return;
+        }
+
+        if new.error_boundary.is_some() {
+            let boundary = mounted_error_boundary(old).expect("same_node checks error boundaries");
+            self.patch_error_boundary(parent, boundary, new);
+            if let Some(node) = &mut new.error_boundary {
+                node.instance = Some(Rc::clone(boundary));
+            }
+            return;
+        }
+
+        if let Some(portal) = &mut new.portal {
+            let container = portal_container(old).expect("same_node checks portals");
+            portal.container = Some(container);
+            self.patch_children(container, &old.children, &mut new.children, None);
+            return;
+        }
+
+        if old.node_ref != new.node_ref {
+            if let Some(node_ref) = &old.node_ref {
+                self.refs.push((node_ref.clone(), RefUpdate::Clear(id)));
+            }
+            self.set_ref(new, id);
+        }
+
+        if matches!(new.kind, NodeKind::Text | NodeKind::Comment) {
+            if old.text_content != new.text_content {
+                self.patches.push(Patch::SetText {
+                    id,
+                    text: new.text_content.clone().unwrap_or_default(),
+                });
+            }
+            return;
+        }
+
+        self.patch_props(id, old, new);
+        self.patch_handlers(id, old, new);
+        self.patch_children(id, &old.children, &mut new.children, None);
+    }
+
+    fn patch_props(&mut self, id: NodeId, old: &VNode, new: &VNode) {
+        // Props left out of the same dynamic list are known to be unchanged
+        if let PatchFlag::DynamicProps(names) = &new.patch_flag {
+            if old.patch_flag == new.patch_flag {
+                for name in names {
+                    self.patch_prop(id, name, old.props.get(name), new.props.get(name));
+                }
+                return;
+            }
+        }
+        for (name, value) in &new.props {
+            self.patch_prop(id, name, old.props.get(name), Some(value));
+        }
+        for (name, value) in &old.props {
+            if !new.props.contains_key(name) {
+                self.patch_prop(id, name, Some(value), None);
+            }
+        }
+    }
+
+    /// Emit patches turning prop `name` from `old` into `new`
+    ///
+    /// A prop can change kind between renders, so the attribute, style and
+    /// property parts are diffed separately.
+    fn patch_prop(
+        &mut self,
+        id: NodeId,
+        name: &str,
+        old: Option<&PropValue>,
+        new: Option<&PropValue>,
+    ) {
+        if old == new {
+            return;
+        }
+
+        let old_attribute = old.and_then(PropValue::attribute_value);
+        match new.and_then(PropValue::attribute_value) {
+            Some(value) if old_attribute.as_ref() != Some(&value) => {
+                self.set_attribute(id, name, value)
+            }
+            Some(_) => {}
+            None if old_attribute.is_some() => self.remove_attribute(id, name),
+            None => {}
+        }
+
+        let no_style = BTreeMap::new();
+        let old_style = old.and_then(PropValue::style_map).unwrap_or(&no_style);
+        let new_style = new.and_then(PropValue::style_map).unwrap_or(&no_style);
+        for (property, value) in new_style {
+            if old_style.get(property) != Some(value) {
+                self.patches.push(Patch::SetStyle {
+                    id,
+                    name: property.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+        for property in old_style.keys() {
+            if !new_style.contains_key(property) {
+                self.patches.push(Patch::RemoveStyle {
+                    id,
+                    name: property.clone(),
+                });
+            }
+        }
+
+        match (old, new) {
+            (_, Some(PropValue::Property(value))) => self.patches.push(Patch::SetProperty {
+                id,
+                name: name.to_string(),
+                value: value.clone(),
+            }),
+            (Some(PropValue::Property(value)), _) => self.patches.push(Patch::SetProperty {
+                id,
+                name: name.to_string(),
+                value: value.reset(),
+            }),
+            _ => {}
+        }
+    }
+
+    /// Set attribute `name`, in its namespace for prefixed names such as
+    /// `xlink:href`
+    fn set_attribute(&mut self, id: NodeId, name: &str, value: String) {
+        let name = name.to_string();
+        self.patches.push(match attribute_namespace(&name) {
+            Some(namespace) => Patch::SetAttributeNs {
+                id,
+                namespace: namespace.to_string(),
+                name,
+                value,
+            },
+            None => Patch::SetAttribute { id, name, value },
+        });
+    }
+
+    fn remove_attribute(&mut self, id: NodeId, name: &str) {
+        let name = name.to_string();
+        self.patches.push(match attribute_namespace(&name) {
+            Some(namespace) => Patch::RemoveAttributeNs {
+                id,
+                namespace: namespace.to_string(),
+                name,
+            },
+            None => Patch::RemoveAttribute { id, name },
+        });
+    }
+
+    /// Swapped handlers only update the table; listeners are added or removed
+    /// when an event type appears or disappears
+    fn patch_handlers(&mut self, id: NodeId, old: &VNode, new: &VNode) {
+        for (event, handler) in &new.handlers {
+            self.handlers.insert(id, event, handler.clone());
+            if !old.handlers.contains_key(event) {
+                self.listen(id, event);
+            }
+        }
+        for event in old.handlers.keys() {
+            if !new.handlers.contains_key(event) {
+                self.handlers.remove(id, event);
+                self.unlisten(id, event);
+            }
+        }
+    }
+
+    /// Emit the listener needed for a new handler of `event` on `id`
+    ///
+    /// Delegated roots listen once per event type, for good.
+    fn listen(&mut self, id: NodeId, event: &str) {
+        let root = self.current_root.expect("listening outside of a render");
+        if self
+            .root_events
+            .entry(root)
+            .or_default()
+            .insert(event.to_string())
+        {
+            let containers: Vec<NodeId> = self
+                .portals
+                .iter()
+                .filter(|&(_, &portal_root)| portal_root == root)
+                .map(|(&container, _)| container)
+                .collect();
+            for container in containers {
+                self.listen_portal(root, container, event.to_string());
+            }
+        }
+        match self.delegated_roots.get_mut(&root) {
+            Some(events) => {
+                if events.insert(event.to_string()) {
+                    self.patches.push(Patch::AddDelegatedListener {
+                        id: root,
+                        event: event.to_string(),
+                    });
+                }
+            }
+            None => self.patches.push(Patch::AddEventListener {
+                id,
+                event: event.to_string(),
+            }),
+        }
+    }
+
+    /// Emit the listener passing `event` from a portal container on to the
+    /// portal's ancestors
+    fn listen_portal(&mut self, root: NodeId, container: NodeId, event: String) {
+        let id = container;
+        if self.delegated_roots.contains_key(&root) {
+            self.patches.push(Patch::AddDelegatedListener { id, event });
+        } else {
+            self.patches.push(Patch::AddPortalListener { id, event });
+        }
+    }
+
+    fn unlisten(&mut self, id: NodeId, event: &str) {
+        let root = self.current_root.expect("listening outside of a render");
+        if !self.delegated_roots.contains_key(&root) {
+            self.patches.push(Patch::RemoveEventListener {
+                id,
+                event: event.to_string(),
+            });
+        }
+    }
+
+    /// Diff the children of `parent`, or of a fragment in `parent`
+    ///
+    /// `end` is the node the children end before: `None` for an element's
+    /// own children, the anchor for a fragment's.
+    fn patch_children(
+        &mut self,
+        parent: NodeId,
+        old: &[VNode],
+        new: &mut [VNode],
+        end: Option<NodeId>,
+    ) {
+        if old
+            .iter()
+            .chain(new.iter())
+            .any(|child| child.key.is_some())
+        {
+            self.patch_keyed_children(parent, old, new, end);
+            self.queue_moves(new);
+        } else {
+            self.patch_unkeyed_children(parent, old, new, end);
+        }
+    }
+
+    /// Diff children position by position, then append or remove the tail
+    fn patch_unkeyed_children(
+        &mut self,
+        parent: NodeId,
+        old: &[VNode],
+        new: &mut [VNode],
+        end: Option<NodeId>,
+    ) {
+        let common = old.len().min(new.len());
+
+        for (old_child, new_child) in old.iter().zip(new.iter_mut()) {
+            self.patch(parent, old_child, new_child);
+        }
+
+        for child in &mut new[common..] {
+            self.mount(parent, child, end);
+        }
+
+        for child in &old[common..] {
+            self.remove(child);
+        }
+    }
+
+    /// Diff children by key, moving as few nodes as possible
+    ///
+    /// Common prefixes and suffixes are patched in place. In the remaining
+    /// middle section, children kept from the old list that form the longest
+    /// increasing subsequence of old positions stay put; every other kept
+    /// child is moved, new children are created and missing ones removed.
+    fn patch_keyed_children(
+        &mut self,
+        parent: NodeId,
+        old: &[VNode],
+        new: &mut [VNode],
+        end: Option<NodeId>,
+    ) {
+        let mut start = 0;
+        let mut old_end = old.len();
+        let mut new_end = new.len();
+
+        while start < old_end && start < new_end && same_node(&old[start], &new[start]) {
+            self.patch(parent, &old[start], &mut new[start]);
+            start += 1;
+        }
+
+        while start < old_end && start < new_end && same_node(&old[old_end - 1], &new[new_end - 1])
+        {
+            self.patch(parent, &old[old_end - 1], &mut new[new_end - 1]);
+            old_end -= 1;
+            new_end -= 1;
+        }
+
+        if start == old_end {
+            let before = new.get(new_end).map_or(end, first_node);
+            for child in &mut new[start..new_end] {
+                self.mount(parent, child, before);
+            }
+            return;
+        }
+
+        if start == new_end {
+            for child in &old[start..old_end] {
+                self.remove(child);
+            }
+            return;
+        }
+
+        let new_keys: HashMap<String, usize> = (start..new_end)
+            .filter_map(|index| new[index].key.clone().map(|key| (key, index)))
+            .collect();
+
+        // Old position of each new child in the middle section, if kept
+        let mut sources: Vec<Option<usize>> = vec![None; new_end - start];
+        let mut moved = false;
+        let mut last_index = start;
+
+        for (old_index, old_child) in old.iter().enumerate().take(old_end).skip(start) {
+            let candidate = match &old_child.key {
+                Some(key) => new_keys.get(key).copied(),
+                None => (start..new_end).find(|&index| {
+                    sources[index - start].is_none()
+                        && new[index].key.is_none()
+                        && new[index].kind == old_child.kind
+                        && new[index].node_type == old_child.node_type
+                }),
+            };
+
+            match candidate {
+                Some(index)
+                    if sources[index - start].is_none() && same_node(old_child, &new[index]) =>
+                {
+                    sources[index - start] = Some(old_index);
+                    if index < last_index {
+                        moved = true;
+                    } else {
+                        last_index = index;
+                    }
+                    self.patch(parent, old_child, &mut new[index]);
+                }
+                _ => self.remove(old_child),
+            }
+        }
+
+        let stable = if moved {
+            longest_increasing_subsequence(&sources)
+        } else {
+            Vec::new()
+        };
+        let mut stable = stable.into_iter().rev().peekable();
+
+        // Walk backwards so the following sibling is always in place to anchor on
+        for offset in (0..sources.len()).rev() {
+            let index = start + offset;
+            let before = new.get(index + 1).map_or(end, first_node);
+
+            match sources[offset] {
+                None => self.mount(parent, &mut new[index], before),
+                Some(_) if moved => {
+                    if stable.peek() == Some(&offset) {
+                        stable.next();
+                    } else {
+                        self.move_before(parent, &new[index], before);
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+/// Children of one parent claimed for hydration, adopted in order
+struct Claimed<'a> {
+    nodes: Vec<(NodeId, &'a ExistingNode)>,
+    next: usize,
+}
+
+impl<'a> Claimed<'a> {
+    fn peek(&self) -> Option<(NodeId, &'a ExistingNode)> {
+        self.nodes.get(self.next).copied()
+    }
+
+    /// The next existing node, to mount new nodes before
+    fn before(&self) -> Option<NodeId> {
+        self.peek().map(|(id, _)| id)
+    }
+}
+
+impl Reconciler {
+    /// Adopt the nodes `root` already holds, e.g. server-rendered HTML, as
+    /// the first render of `vnode`
+    ///
+    /// `existing` describes the children of `root`, as reported by the
+    /// backend. The patches claim the existing nodes and attach event
+    /// listeners; components are mounted as usual. Mismatches are returned
+    /// along with the patches when recovery is enabled, after re-rendering
+    /// the mismatched subtrees. Otherwise any mismatch fails the hydration
+    /// and leaves the reconciler as it was. A root that was already
+    /// rendered into is simply diffed.
+    pub fn hydrate(
+        &mut self,
+        root: NodeId,
+        vnode: &VNode,
+        existing: &[ExistingNode],
+    ) -> Result<(Vec<Patch>, Vec<HydrationMismatch>), RenderError> {
+        if self.roots.contains_key(&root) {
+            return Ok((self.render(root, vnode), Vec::new()));
+        }
+
+        self.start_pass();
+        let mut next = vnode.clone();
+        let ids = (self.next_id, self.free_ids.clone());
+        let events = (
+            self.root_events.get(&root).cloned(),
+            self.delegated_roots.get(&root).cloned(),
+        );
+        self.current_root = Some(root);
+        if self.delegation {
+            self.delegated_roots.entry(root).or_default();
+        }
+
+        let mut mismatches = Vec::new();
+        let mut claimed = self.claim(root, existing);
+        let path = vnode_label(&next);
+        self.hydrate_node(root, &mut next, &mut claimed, &path, &mut mismatches);
+        self.hydrate_rest(&mut claimed, &path, &mut mismatches);
+
+        if !mismatches.is_empty() && !self.hydration_recovery {
+            self.current_root = None;
+            self.reclaim(&next);
+            (self.next_id, self.free_ids) = ids;
+            // A later render starts listening from scratch
+            let (root_events, delegated_events) = events;
+            restore(&mut self.root_events, root, root_events);
+            restore(&mut self.delegated_roots, root, delegated_events);
+            self.patches.clear();
+            self.start_pass();
+            return Err(RenderError::HydrationMismatch(mismatches));
+        }
+
+        self.roots.insert(root, next);
+        self.show_fallbacks();
+        self.current_root = None;
+        Ok((std::mem::take(&mut self.patches), mismatches))
+    }
+
+    /// Give every existing child of `parent` an id
+    fn claim<'a>(&mut self, parent: NodeId, existing: &'a [ExistingNode]) -> Claimed<'a> {
+        let nodes = existing
+            .iter()
+            .enumerate()
+            .map(|(index, node)| {
+                let id = self.alloc_id();
+                self.patches.push(Patch::Claim { parent, index, id });
+                (id, node)
+            })
+            .collect();
+        Claimed { nodes, next: 0 }
+    }
+
+    /// Adopt the existing nodes `vnode` renders to, starting at `claimed`
+    fn hydrate_node(
+        &mut self,
+        parent: NodeId,
+        vnode: &mut VNode,
+        claimed: &mut Claimed,
+        path: &str,
+        mismatches: &mut Vec<HydrationMismatch>,
+    ) {
+        if vnode.component.is_some() {
+            let Some((instance, mut output)) = self.mount_component(parent, vnode) else {
+                self.mount(parent, vnode, claimed.before());
+                return;
+            };
+            self.hydrate_node(parent, &mut output, claimed, path, mismatches);
+            *instance.output.borrow_mut() = Some(output);
+            self.hooks.push((Rc::clone(&instance), Lifecycle::Mount));
+            if let Some(component) = &mut vnode.component {
+                component.instance = Some(instance);
+            }
+            return;
+        }
+
+        let tag = vnode.node_type.clone();
+        match vnode.kind {
+            NodeKind::Fragment => {
+                for (index, child) in vnode.children.iter_mut().enumerate() {
+                    let path = child_path(path, index, child);
+                    self.hydrate_node(parent, child, claimed, &path, mismatches);
+                }
+                self.hydrate_anchor(parent, vnode, claimed, path, mismatches);
+            }
+            NodeKind::Suspense => {
+                self.hydrate_suspense(parent, vnode, claimed, path, mismatches);
+            }
+            NodeKind::ErrorBoundary => {
+                self.hydrate_error_boundary(parent, vnode, claimed, path, mismatches);
+            }
+            // Portal children are not server-rendered
+            NodeKind::Portal => {
+                self.mount_portal(parent, vnode);
+                self.hydrate_anchor(parent, vnode, claimed, path, mismatches);
+            }
+            NodeKind::Text => {
+                let text = vnode.text_content.clone().unwrap_or_default();
+                // Empty text leaves no trace in server-rendered HTML
+                if text.is_empty() {
+                    self.mount(parent, vnode, claimed.before());
+                    return;
+                }
+                if let Some((id, ExistingNode::Comment(separator))) = claimed.peek() {
+                    if separator == " " {
+                        claimed.next += 1;
+                        self.discard(id);
+                    }
+                }
+                match claimed.peek() {
+                    Some((id, ExistingNode::Text(found))) => {
+                        claimed.next += 1;
+                        vnode.id = Some(id);
+                        self.set_ref(vnode, id);
+                        self.handlers.set_parent(id, parent);
+                        if *found != text {
+                            mismatches.push(HydrationMismatch {
+                                path: path.to_string(),
+                                kind: MismatchKind::Text {
+                                    expected: text.clone(),
+                                    found: found.clone(),
+                                },
+                            });
+                            if self.hydration_recovery {
+                                self.patches.push(Patch::SetText { id, text });
+                            }
+                        }
+                    }
+                    _ => self.replace_mismatched(parent, vnode, claimed, path, mismatches),
+                }
+            }
+            NodeKind::Comment => match claimed.peek() {
+                Some((id, ExistingNode::Comment(found))) => {
+                    claimed.next += 1;
+                    vnode.id = Some(id);
+                    self.set_ref(vnode, id);
+                    self.handlers.set_parent(id, parent);
+                    let text = vnode.text_content.clone().unwrap_or_default();
+                    if *found != text {
+                        self.patches.push(Patch::SetText { id, text });
+                    }
+                }
+                _ => self.replace_mismatched(parent, vnode, claimed, path, mismatches),
+            },
+            NodeKind::Element => match claimed.peek() {
+                Some((
+                    id,
+                    ExistingNode::Element {
+                        tag: found,
+                        attributes,
+                        children,
+                    },
+                )) if found.eq_ignore_ascii_case(&tag) => {
+                    claimed.next += 1;
+                    vnode.id = Some(id);
+                    self.set_ref(vnode, id);
+                    self.handlers.set_parent(id, parent);
+                    self.element_namespace(id, parent, &tag);
+                    let content =
+                        self.hydrate_attributes(id, &tag, vnode, attributes, path, mismatches);
+
+                    for (event, handler) in &vnode.handlers {
+                        self.handlers.insert(id, event, handler.clone());
+                        self.listen(id, event);
+                    }
+
+                    // A `textarea` holds its value, not vnode children
+                    if content.is_some() {
+                        return;
+                    }
+                    let mut inner = self.claim(id, children);
+                    for (index, child) in vnode.children.iter_mut().enumerate() {
+                        let path = child_path(path, index, child);
+                        self.hydrate_node(id, child, &mut inner, &path, mismatches);
+                    }
+                    self.hydrate_rest(&mut inner, path, mismatches);
+                }
+                _ => self.replace_mismatched(parent, vnode, claimed, path, mismatches),
+            },
+        }
+    }
+
+    /// Adopt a server-rendered suspense boundary
+    ///
+    /// Content the server rendered is adopted as is. When it rendered the
+    /// fallback, marked by a comment, the fallback is adopted and the
+    /// content mounted as when rendering.
+    fn hydrate_suspense(
+        &mut self,
+        parent: NodeId,
+        vnode: &mut VNode,
+        claimed: &mut Claimed,
+        path: &str,
+        mismatches: &mut Vec<HydrationMismatch>,
+    ) {
+        let boundary = self.suspense_instance(parent);
+        match claimed.peek() {
+            Some((marker, ExistingNode::Comment(text))) if is_fallback_marker(text) => {
+                claimed.next += 1;
+                self.discard(marker);
+                self.mount_hidden(&boundary, vnode);
+                let mut fallback = suspense_fallback(vnode);
+                let path = format!("{} > #fallback", path);
+                self.hydrate_node(parent, &mut fallback, claimed, &path, mismatches);
+                *boundary.fallback.borrow_mut() = Some(fallback);
+            }
+            _ => {
+                let mut content = vnode.children.clone();
+                self.in_suspense(&boundary, |this| {
+                    for (index, child) in content.iter_mut().enumerate() {
+                        let path = child_path(path, index, child);
+                        this.hydrate_node(parent, child, claimed, &path, mismatches);
+                    }
+                });
+                *boundary.content.borrow_mut() = content;
+            }
+        }
+        self.hydrate_anchor(parent, vnode, claimed, path, mismatches);
+        self.finish_suspense(boundary, vnode);
+    }
+
+    /// Adopt a server-rendered error boundary
+    ///
+    /// Content the server rendered is adopted as is. When it rendered the
+    /// fallback, between markers, the fallback is removed and the content
+    /// mounted as when rendering.
+    fn hydrate_error_boundary(
+        &mut self,
+        parent: NodeId,
+        vnode: &mut VNode,
+        claimed: &mut Claimed,
+        path: &str,
+        mismatches: &mut Vec<HydrationMismatch>,
+    ) {
+        let boundary = self.error_boundary_instance(parent, vnode);
+        let mut content = vnode.children.clone();
+        match claimed.peek() {
+            Some((marker, ExistingNode::Comment(text))) if text == ERROR_MARKER => {
+                claimed.next += 1;
+                self.discard(marker);
+                // Fallbacks may hold failed boundaries of their own
+                let mut depth = 0;
+                while let Some((id, node)) = claimed.peek() {
+                    claimed.next += 1;
+                    self.discard(id);
+                    match node {
+                        ExistingNode::Comment(text) if text == ERROR_MARKER => depth += 1,
+                        ExistingNode::Comment(text) if text == ERROR_END_MARKER => {
+                            if depth == 0 {
+                                break;
+                            }
+                            depth -= 1;
+                        }
+                        _ => {}
+                    }
+                }
+                let before = claimed.before();
+                self.in_error_boundary(Some(Rc::clone(&boundary)), |this| {
+                    for child in &mut content {
+                        this.mount(parent, child, before);
+                    }
+                });
+            }
+            _ => {
+                self.in_error_boundary(Some(Rc::clone(&boundary)), |this| {
+                    for (index, child) in content.iter_mut().enumerate() {
+                        let path = child_path(path, index, child);
+                        this.hydrate_node(parent, child, claimed, &path, mismatches);
+                    }
+                });
+            }
+        }
+        *boundary.content.borrow_mut() = content;
+        self.hydrate_anchor(parent, vnode, claimed, path, mismatches);
+        self.finish_error_boundary(boundary, vnode);
+    }
+
+    /// Adopt the empty comment marking the end of a fragment or the position
+    /// of a portal
+    fn hydrate_anchor(
+        &mut self,
+        parent: NodeId,
+        vnode: &mut VNode,
+        claimed: &mut Claimed,
+        path: &str,
+        mismatches: &mut Vec<HydrationMismatch>,
+    ) {
+        match claimed.peek() {
+            Some((id, ExistingNode::Comment(_))) => {
+                claimed.next += 1;
+                vnode.id = Some(id);
+                self.handlers.set_parent(id, parent);
+            }
+            found => {
+                let expected = if vnode.portal.is_some() {
+                    "the position of a portal"
+                } else if vnode.suspense.is_some() {
+                    "the end of a suspense boundary"
+                } else if vnode.error_boundary.is_some() {
+                    "the end of an error boundary"
+                } else {
+                    "the end of a fragment"
+                };
+                mismatches.push(HydrationMismatch {
+                    path: path.to_string(),
+                    kind: MismatchKind::Node {
+                        expected: expected.to_string(),
+                        found: found.map(|(_, node)| node.describe()),
+                    },
+                });
+                if self.hydration_recovery {
+                    self.mount_anchor(parent, vnode, claimed.before());
+                }
+            }
+        }
+    }
+
+    /// Compare the attributes of an adopted element with its props, and
+    /// return the content a `textarea` is server-rendered with
+    fn hydrate_attributes(
+        &mut self,
+        id: NodeId,
+        tag: &str,
+        vnode: &VNode,
+        found: &BTreeMap<String, String>,
+        path: &str,
+        mismatches: &mut Vec<HydrationMismatch>,
+    ) -> Option<String> {
+        let (attributes, content) = server_attributes(tag, &vnode.props);
+        let expected: BTreeMap<&str, String> = attributes
+            .into_iter()
+            .map(|(name, value)| (name, value.unwrap_or_default()))
+            .collect();
+
+        let names: BTreeSet<&str> = expected
+            .keys()
+            .copied()
+            .chain(found.keys().map(String::as_str))
+            .collect();
+        for name in names {
+            let (expected, found) = (expected.get(name), found.get(name));
+            if expected == found {
+                continue;
+            }
+            mismatches.push(HydrationMismatch {
+                path: path.to_string(),
+                kind: MismatchKind::Attribute {
+                    name: name.to_string(),
+                    expected: expected.cloned(),
+                    found: found.cloned(),
+                },
+            });
+            if self.hydration_recovery {
+                if found.is_some() {
+                    self.remove_attribute(id, name);
+                }
+                self.patch_prop(id, name, None, vnode.props.get(name));
+            }
+        }
+        content
+    }
+
+    /// Record that the next existing node does not match `vnode`, and with
+    /// recovery replace it with a newly mounted `vnode`
+    fn replace_mismatched(
+        &mut self,
+        parent: NodeId,
+        vnode: &mut VNode,
+        claimed: &mut Claimed,
+        path: &str,
+        mismatches: &mut Vec<HydrationMismatch>,
+    ) {
+        let found = claimed.peek();
+        mismatches.push(HydrationMismatch {
+            path: path.to_string(),
+            kind: MismatchKind::Node {
+                expected: vnode_description(vnode),
+                found: found.map(|(_, node)| node.describe()),
+            },
+        });
+        if found.is_some() {
+            claimed.next += 1;
+        }
+        if self.hydration_recovery {
+            self.mount(parent, vnode, found.map(|(id, _)| id));
+            if let Some((id, _)) = found {
+                self.discard(id);
+            }
+        }
+    }
+
+    /// Report existing nodes no vnode adopted, removing them with recovery
+    fn hydrate_rest(
+        &mut self,
+        claimed: &mut Claimed,
+        path: &str,
+        mismatches: &mut Vec<HydrationMismatch>,
+    ) {
+        while let Some((id, node)) = claimed.peek() {
+            claimed.next += 1;
+            mismatches.push(HydrationMismatch {
+                path: path.to_string(),
+                kind: MismatchKind::Extra {
+                    found: node.describe(),
+                },
+            });
+            if self.hydration_recovery {
+                self.discard(id);
+            }
+        }
+    }
+
+    /// Remove a claimed node no vnode uses
+    fn discard(&mut self, id: NodeId) {
+        self.patches.push(Patch::Remove { id });
+        self.free_ids.push(id);
+    }
+}
+
+fn child_path(path: &str, index: usize, child: &VNode) -> String {
+    format!("{} > {}[{}]", path, vnode_label(child), index)
+}
+
+/// Put back the entry `map` had for `key` before, or remove it if it had none
+fn restore<V>(map: &mut HashMap<NodeId, V>, key: NodeId, previous: Option<V>) {
+    match previous {
+        Some(value) => map.insert(key, value),
+        None => map.remove(&key),
+    };
+}
+
+/// What a vnode renders to, for mismatch reports
+fn vnode_description(vnode: &VNode) -> String {
+    let text = vnode.text_content.clone().unwrap_or_default();
+    match vnode.kind {
+        NodeKind::Text => format!("text {:?}", text),
+        NodeKind::Comment => format!("comment {:?}", text),
+        _ => format!("<{}>", vnode.node_type),
+    }
+}
+
+/// Whether two vnodes describe the same node, so one can be patched into the other
+fn same_node(a: &VNode, b: &VNode) -> bool {
+    let component_type = |vnode: &VNode| {
+        vnode
+            .component
+            .as_ref()
+            .map(|component| component.spec.type_id())
+    };
+    let portal_target = |vnode: &VNode| vnode.portal.as_ref().map(|portal| portal.target.clone());
+    a.kind == b.kind
+        && a.node_type == b.node_type
+        && a.key == b.key
+        && component_type(a) == component_type(b)
+        && portal_target(a) == portal_target(b)
+        && a.suspense.is_some() == b.suspense.is_some()
+        && a.error_boundary.is_some() == b.error_boundary.is_some()
+}
+
+/// Whether `vnode` is an element with a transition
+fn has_transition(vnode: &VNode) -> bool {
+    vnode.transition.is_some()
+        && vnode.component.is_none()
+        && vnode.suspense.is_none()
+        && vnode.error_boundary.is_none()
+        && vnode.portal.is_none()
+        && vnode.kind == NodeKind::Element
+}
+
+/// Instance of a mounted component vnode
+fn mounted_instance(vnode: &VNode) -> Option<&Rc<ComponentInstance>> {
+    vnode.component.as_ref()?.instance.as_ref()
+}
+
+/// Instance of a mounted suspense boundary
+fn mounted_suspense(vnode: &VNode) -> Option<&Rc<SuspenseInstance>> {
+    vnode.suspense.as_ref()?.instance.as_ref()
+}
+
+/// Instance of a mounted error boundary
+fn mounted_error_boundary(vnode: &VNode) -> Option<&Rc<ErrorBoundaryInstance>> {
+    vnode.error_boundary.as_ref()?.instance.as_ref()
+}
+
+/// Fallback of a suspense boundary, to mount
+fn suspense_fallback(vnode: &VNode) -> VNode {
+    let suspense = vnode.suspense.as_ref().expect("a suspense boundary");
+    VNode::clone(&suspense.fallback)
+}
+
+/// Element holding the children of a mounted portal
+fn portal_container(vnode: &VNode) -> Option<NodeId> {
+    vnode.portal.as_ref()?.container
+}
+
+/// First DOM node rendered for a mounted `vnode`
+fn first_node(vnode: &VNode) -> Option<NodeId> {
+    if let Some(instance) = mounted_instance(vnode) {
+        return instance.output.borrow().as_ref().and_then(first_node);
+    }
+    if vnode.kind == NodeKind::Fragment {
+        if let Some(child) = vnode.children.first() {
+            return first_node(child);
+        }
+    }
+    if let Some(boundary) = mounted_suspense(vnode) {
+        let first = match boundary.fallback.borrow().as_ref() {
+            Some(fallback) => first_node(fallback),
+            None if boundary.is_hidden() => None,
+            None => boundary.content.borrow().first().and_then(first_node),
+        };
+        if first.is_some() {
+            return first;
+        }
+    }
+    if let Some(boundary) = mounted_error_boundary(vnode) {
+        let first = match boundary.fallback.borrow().as_ref() {
+            Some(fallback) => first_node(fallback),
+            None => boundary.content.borrow().first().and_then(first_node),
+        };
+        if first.is_some() {
+            return first;
+        }
+    }
+    vnode.id
+}
+
+/// Positions of a longest strictly increasing subsequence of the `Some` values
+///
+/// Returned positions are in ascending order; `None` entries are skipped.
+fn longest_increasing_subsequence(sequence: &[Option<usize>]) -> Vec<usize> {
+    let mut predecessors: Vec<Option<usize>> = vec![None; sequence.len()];
+    // tails[k]: position of the smallest tail of an increasing run of length k + 1
+    let mut tails: Vec<usize> = Vec::new();
+
+    for (position, value) in sequence.iter().enumerate() {
+        let Some(value) = *value else {
+            continue;
+        };
+        let length = tails.partition_point(|&tail| sequence[tail] < Some(value));
+        if length > 0 {
+            predecessors[position] = Some(tails[length - 1]);
+        }
+        if length == tails.len() {
+            tails.push(position);
+        } else {
+            tails[length] = position;
+        }
+    }
+
+    let mut result = Vec::with_capacity(tails.len());
+    let mut current = tails.last().copied();
+    while let Some(position) = current {
+        result.push(position);
+        current = predecessors[position];
+    }
+    result.reverse();
+    result
+}
@@ -0,0 +1,157 @@
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+use crate::backend::RenderError;
+use crate::component::ComponentQueue;
+use crate::patch::NodeId;
+use crate::render::VNode;
+
+/// Builds the fallback of an error boundary from the failure
+pub(crate) type FallbackFn = Rc<dyn Fn(&RenderError, ErrorReset) -> VNode>;
+
+/// Error boundary whose content failed, with the failure
+pub(crate) type BoundaryFailure = (Rc<ErrorBoundaryInstance>, RenderError);
+
+/// Retries the content of a `VNode::error_boundary` showing its fallback
+///
+/// Handed to the fallback, e.g. for a "try again" button. Resetting a
+/// boundary that was unmounted since, or is not showing its fallback, does
+/// nothing.
+#[derive(Clone)]
+pub struct ErrorReset {
+    boundary: Weak<ErrorBoundaryInstance>,
+    queue: Weak<ComponentQueue>,
+}
+
+impl ErrorReset {
+    /// Reset doing nothing, for fallbacks rendered on the server
+    pub(crate) fn inert() -> Self {
+        ErrorReset {
+            boundary: Weak::new(),
+            queue: Weak::new(),
+        }
+    }
+
+    /// Render the content of the boundary again in place of its fallback
+    ///
+    /// The content is remounted from scratch, components included, with the
+    /// next component update.
+    pub fn reset(&self) {
+        if let Some(queue) = self.queue.upgrade() {
+            queue.push_reset(Weak::clone(&self.boundary));
+        }
+    }
+}
+
+impl fmt::Debug for ErrorReset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorReset")
+            .field("mounted", &(self.boundary.strong_count() > 0))
+            .finish()
+    }
+}
+
+/// Error boundary part of a `VNode`, holding the boundary once mounted
+#[derive(Clone)]
+pub(crate) struct ErrorBoundaryNode {
+    pub(crate) fallback: FallbackFn,
+    pub(crate) instance: Option<Rc<ErrorBoundaryInstance>>,
+}
+
+impl fmt::Debug for ErrorBoundaryNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorBoundaryNode")
+            .field("mounted", &self.instance.is_some())
+            .finish()
+    }
+}
+
+/// A mounted `VNode::error_boundary`
+///
+/// Its content is rendered before its anchor, like the children of a
+/// fragment, until it fails; the fallback then takes its place until reset.
+pub(crate) struct ErrorBoundaryInstance {
+    /// Content currently rendered, with node ids assigned; empty while the
+    /// fallback is shown
+    pub(crate) content: RefCell<Vec<VNode>>,
+    /// Content of the last render, mounted again on reset
+    pub(crate) children: RefCell<Vec<VNode>>,
+    /// Fallback rendered in place of the content, and the failure it shows
+    pub(crate) fallback: RefCell<Option<VNode>>,
+    pub(crate) error: RefCell<Option<RenderError>>,
+    render_fallback: RefCell<FallbackFn>,
+    /// DOM parent of the boundary and the root it was rendered into
+    pub(crate) parent: Cell<NodeId>,
+    pub(crate) root: Cell<NodeId>,
+    /// Comment marking the end of the boundary, cleared once unmounted
+    pub(crate) anchor: Cell<Option<NodeId>>,
+    /// Boundary this one was mounted beneath, which catches failures of
+    /// its fallback
+    pub(crate) outer: Option<Weak<ErrorBoundaryInstance>>,
+    queue: Weak<ComponentQueue>,
+}
+
+impl ErrorBoundaryInstance {
+    pub(crate) fn new(
+        node: &ErrorBoundaryNode,
+        parent: NodeId,
+        root: NodeId,
+        outer: Option<&Rc<ErrorBoundaryInstance>>,
+        queue: &Rc<ComponentQueue>,
+    ) -> Self {
+        ErrorBoundaryInstance {
+            content: RefCell::new(Vec::new()),
+            children: RefCell::new(Vec::new()),
+            fallback: RefCell::new(None),
+            error: RefCell::new(None),
+            render_fallback: RefCell::new(Rc::clone(&node.fallback)),
+            parent: Cell::new(parent),
+            root: Cell::new(root),
+            anchor: Cell::new(None),
+            outer: outer.map(Rc::downgrade),
+            queue: Rc::downgrade(queue),
+        }
+    }
+
+    /// Whether the fallback is shown in place of the content
+    pub(crate) fn has_failed(&self) -> bool {
+        self.error.borrow().is_some()
+    }
+
+    /// Whether the boundary is still in the tree
+    pub(crate) fn is_mounted(&self) -> bool {
+        self.anchor.get().is_some()
+    }
+
+    /// Keep the fallback builder of the latest render
+    pub(crate) fn set_fallback(&self, node: &ErrorBoundaryNode) {
+        *self.render_fallback.borrow_mut() = Rc::clone(&node.fallback);
+    }
+
+    /// Build the fallback for the current failure
+    pub(crate) fn render_fallback(self: &Rc<Self>) -> VNode {
+        let render = Rc::clone(&self.render_fallback.borrow());
+        let reset = ErrorReset {
+            boundary: Rc::downgrade(self),
+            queue: Weak::clone(&self.queue),
+        };
+        let error = self.error.borrow().clone();
+        render(
+            &error.expect("rendering the fallback of a failed boundary"),
+            reset,
+        )
+    }
+}
+
+/// Message of a caught panic
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        return message.to_string();
+    }
+    match payload.downcast_ref::<String>() {
+        Some(message) => message.clone(),
+        None => "unknown panic payload".to_string(),
+    }
+}
@@ -0,0 +1,279 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::patch::NodeId;
+
+/// Callback a backend invokes when a listened-for event fires on a node
+pub type Listener = Rc<dyn Fn(&Event)>;
+
+/// Event handler attached to a `VNode` with `VNode::on`
+#[derive(Clone)]
+pub struct EventHandler(Rc<dyn Fn(&Event)>);
+
+impl EventHandler {
+    pub fn new(handler: impl Fn(&Event) + 'static) -> Self {
+        EventHandler(Rc::new(handler))
+    }
+
+    pub fn call(&self, event: &Event) {
+        (self.0)(event)
+    }
+
+    /// Whether both handles point to the same closure
+    pub fn ptr_eq(&self, other: &EventHandler) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for EventHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EventHandler")
+    }
+}
+
+/// Current handler for every listened-to (node, event) pair
+///
+/// Backends get one dispatcher listener per pair, which looks the handler up
+/// here when the event fires. Re-renders that swap a handler only update the
+/// table, so no backend listener (or wasm `Closure`) is created or leaked.
+///
+/// The table also knows the parent every node was mounted into, so events
+/// leaving a portal can go on through the portal's ancestors.
+#[derive(Clone, Default)]
+pub struct HandlerTable {
+    handlers: Rc<RefCell<HashMap<NodeId, HashMap<String, EventHandler>>>>,
+    parents: Rc<RefCell<HashMap<NodeId, NodeId>>>,
+}
+
+impl HandlerTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, id: NodeId, event: &str, handler: EventHandler) {
+        self.handlers
+            .borrow_mut()
+            .entry(id)
+            .or_default()
+            .insert(event.to_string(), handler);
+    }
+
+    pub fn remove(&self, id: NodeId, event: &str) {
+        if let Some(handlers) = self.handlers.borrow_mut().get_mut(&id) {
+            handlers.remove(event);
+        }
+    }
+
+    /// Forget every handler registered for `id`, and its parent
+    pub fn remove_node(&self, id: NodeId) {
+        self.handlers.borrow_mut().remove(&id);
+        self.parents.borrow_mut().remove(&id);
+    }
+
+    /// Record the node `id` belongs to in the vnode tree: its DOM parent,
+    /// except for the container of a portal
+    pub fn set_parent(&self, id: NodeId, parent: NodeId) {
+        self.parents.borrow_mut().insert(id, parent);
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.parents.borrow().get(&id).copied()
+    }
+
+    pub fn get(&self, id: NodeId, event: &str) -> Option<EventHandler> {
+        self.handlers.borrow().get(&id)?.get(event).cloned()
+    }
+
+    /// Run the handler for `event` on `id`, if any; returns whether one ran
+    pub fn dispatch(&self, id: NodeId, event: &Event) -> bool {
+        // Release the table before calling out: handlers may re-render
+        match self.get(id, event.event_type()) {
+            Some(handler) => {
+                handler.call(event);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Backend listener dispatching `event` on `id` through this table
+    pub fn listener(&self, id: NodeId) -> Listener {
+        let table = self.clone();
+        Rc::new(move |event: &Event| {
+            table.dispatch(id, event);
+        })
+    }
+
+    /// Single listener at `root` dispatching to handlers of its descendants
+    ///
+    /// Walks `Event::path` from the target towards `root`, simulating
+    /// bubbling until a handler stops propagation. Non-bubbling events only
+    /// reach the target's handler. When `root` is the container of a
+    /// portal, bubbling goes on through the portal's ancestors.
+    pub fn delegated_listener(&self, root: NodeId) -> Listener {
+        let table = self.clone();
+        Rc::new(move |event: &Event| {
+            let bubbles = event_bubbles(event.event_type());
+            for &id in event.path().iter().take_while(|&&id| id != root) {
+                event.set_current_target(Some(id));
+                table.dispatch(id, event);
+                if !bubbles || event.is_propagation_stopped() {
+                    event.set_current_target(Some(root));
+                    return;
+                }
+            }
+            if bubbles {
+                table.bubble_past(root, event);
+            }
+            event.set_current_target(Some(root));
+        })
+    }
+
+    /// Listener at the container of a portal, passing events that bubbled
+    /// out of its children on to the handlers of the portal's ancestors
+    pub fn portal_listener(&self, container: NodeId) -> Listener {
+        let table = self.clone();
+        Rc::new(move |event: &Event| {
+            if event_bubbles(event.event_type()) {
+                table.bubble_past(container, event);
+                event.set_current_target(Some(container));
+            }
+        })
+    }
+
+    /// Dispatch `event` to the ancestors of `id`, nearest first, until a
+    /// handler stops propagation
+    fn bubble_past(&self, id: NodeId, event: &Event) {
+        let mut current = self.parent(id);
+        while let Some(id) = current {
+            if event.is_propagation_stopped() {
+                return;
+            }
+            event.set_current_target(Some(id));
+            self.dispatch(id, event);
+            current = self.parent(id);
+        }
+    }
+}
+
+/// Platform-independent view of a DOM event passed to handlers
+///
+/// On wasm it wraps the browser event, available through `raw`; stopping
+/// propagation or preventing the default is forwarded to it.
+pub struct Event {
+    event_type: String,
+    target: Option<NodeId>,
+    path: Vec<NodeId>,
+    current_target: Cell<Option<NodeId>>,
+    propagation_stopped: Cell<bool>,
+    default_prevented: Cell<bool>,
+    #[cfg(target_arch = "wasm32")]
+    raw: Option<web_sys::Event>,
+}
+
+impl Event {
+    /// Create a synthetic event targeting `target`
+    pub fn new(event_type: &str, target: Option<NodeId>) -> Self {
+        Event {
+            event_type: event_type.to_string(),
+            target,
+            path: target.into_iter().collect(),
+            current_target: Cell::new(target),
+            propagation_stopped: Cell::new(false),
+            default_prevented: Cell::new(false),
+            #[cfg(target_arch = "wasm32")]
+            raw: None,
+        }
+    }
+
+    /// Set the propagation path: node ids from the target up to the root
+    pub fn with_path(mut self, path: Vec<NodeId>) -> Self {
+        self.target = path.first().copied();
+        self.current_target.set(self.target);
+        self.path = path;
+        self
+    }
+
+    /// Wrap a browser event
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn from_raw(raw: web_sys::Event, path: Vec<NodeId>) -> Self {
+        let mut event = Event::new(&raw.type_(), None).with_path(path);
+        event.raw = Some(raw);
+        event
+    }
+
+    /// Event name, e.g. `"click"`
+    pub fn event_type(&self) -> &str {
+        &self.event_type
+    }
+
+    /// Node the event was dispatched on, if known
+    pub fn target(&self) -> Option<NodeId> {
+        self.target
+    }
+
+    /// Ids of the nodes the event propagates through, target first
+    pub fn path(&self) -> &[NodeId] {
+        &self.path
+    }
+
+    /// Node whose listener is currently running
+    pub fn current_target(&self) -> Option<NodeId> {
+        self.current_target.get()
+    }
+
+    pub(crate) fn set_current_target(&self, id: Option<NodeId>) {
+        self.current_target.set(id);
+    }
+
+    /// Keep the event from reaching listeners further up the tree
+    pub fn stop_propagation(&self) {
+        self.propagation_stopped.set(true);
+        #[cfg(target_arch = "wasm32")]
+        if let Some(raw) = &self.raw {
+            raw.stop_propagation();
+        }
+    }
+
+    pub fn is_propagation_stopped(&self) -> bool {
+        self.propagation_stopped.get()
+    }
+
+    /// Cancel the event's default action
+    pub fn prevent_default(&self) {
+        self.default_prevented.set(true);
+        #[cfg(target_arch = "wasm32")]
+        if let Some(raw) = &self.raw {
+            raw.prevent_default();
+        }
+    }
+
+    pub fn default_prevented(&self) -> bool {
+        self.default_prevented.get()
+    }
+
+    /// The underlying browser event
+    #[cfg(target_arch = "wasm32")]
+    pub fn raw(&self) -> Option<&web_sys::Event> {
+        self.raw.as_ref()
+    }
+}
+
+/// Whether events of this type bubble up the tree
+pub fn event_bubbles(event_type: &str) -> bool {
+    !matches!(
+        event_type,
+        "focus"
+            | "blur"
+            | "load"
+            | "unload"
+            | "error"
+            | "scroll"
+            | "mouseenter"
+            | "mouseleave"
+            | "pointerenter"
+            | "pointerleave"
+    )
+}
@@ -0,0 +1,417 @@
+use std::fmt;
+
+use js_sys::{Array, Function, Object, Reflect, WeakMap};
+use wasm_bindgen::convert::TryFromJsValue;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::node_ref::NodeRef;
+use crate::prop::PropValue;
+use crate::render::{next_static_id, PatchFlag, VNode};
+
+/// Nesting depth after which conversion gives up, e.g. on cyclic objects
+const MAX_DEPTH: usize = 512;
+
+thread_local! {
+    /// Static instance ids of the `$static` vnode objects converted so far
+    static STATIC_IDS: WeakMap = WeakMap::new();
+}
+
+/// Where and why a JS value could not be converted into a `VNode`
+#[derive(Clone, Debug, PartialEq)]
+pub struct FromJsError {
+    /// Location of the offending value, e.g. `vnode.children[1].props.class`
+    pub path: String,
+    pub kind: FromJsErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FromJsErrorKind {
+    /// Not a vnode object, string, number, array or hole
+    NotAVNode { found: String },
+    /// `type` is neither a tag name, the `Fragment` symbol nor a function
+    InvalidType { found: String },
+    /// `props` is neither an object nor `null`
+    InvalidProps { found: String },
+    /// A prop value has no `PropValue` equivalent
+    InvalidProp { reason: String },
+    /// `children` is neither an array nor absent
+    InvalidChildren { found: String },
+    /// A function component threw
+    ComponentThrew { message: String },
+    /// Nested more than `MAX_DEPTH` levels deep
+    TooDeep,
+}
+
+impl fmt::Display for FromJsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.path)?;
+        match &self.kind {
+            FromJsErrorKind::NotAVNode { found } => {
+                write!(
+                    f,
+                    "expected a vnode, string, number or array, found {}",
+                    found
+                )
+            }
+            FromJsErrorKind::InvalidType { found } => write!(
+                f,
+                "expected a tag name, Fragment or component function as type, found {}",
+                found
+            ),
+            FromJsErrorKind::InvalidProps { found } => {
+                write!(f, "expected an object as props, found {}", found)
+            }
+            FromJsErrorKind::InvalidProp { reason } => write!(f, "{}", reason),
+            FromJsErrorKind::InvalidChildren { found } => {
+                write!(f, "expected an array as children, found {}", found)
+            }
+            FromJsErrorKind::ComponentThrew { message } => {
+                write!(f, "component threw: {}", message)
+            }
+            FromJsErrorKind::TooDeep => write!(f, "nested more than {} levels deep", MAX_DEPTH),
+        }
+    }
+}
+
+impl std::error::Error for FromJsError {}
+
+impl From<FromJsError> for JsValue {
+    fn from(error: FromJsError) -> Self {
+        js_sys::TypeError::new(&error.to_string()).into()
+    }
+}
+
+impl VNode {
+    /// Convert a vnode built on the JS side, e.g. by `h()` in `@selene/core`
+    ///
+    /// Accepts `{ type, props, children }` objects, `VNode` instances (which
+    /// are moved out of their JS wrapper), strings and numbers as text,
+    /// arrays as fragments and `null`, `undefined` or booleans as empty
+    /// placeholders, so conditional children keep their position.
+    ///
+    /// `type` may be a tag name, the `Fragment` symbol
+    /// (`Symbol.for('selene.fragment')`) or a function component, which is
+    /// called right away with its props and `children`. Function props named
+    /// `on*` become event handlers; `key` sets the vnode key and `ref`, a
+    /// `NodeRef`, its ref.
+    pub fn from_js(value: &JsValue) -> Result<VNode, FromJsError> {
+        convert(value, "vnode".to_string(), 0)
+    }
+}
+
+#[wasm_bindgen]
+impl VNode {
+    /// Convert a vnode built on the JS side; see `VNode::from_js`
+    #[wasm_bindgen(js_name = from_js)]
+    pub fn from_js_value(value: &JsValue) -> Result<VNode, JsValue> {
+        Ok(VNode::from_js(value)?)
+    }
+}
+
+fn error(path: &str, kind: FromJsErrorKind) -> FromJsError {
+    FromJsError {
+        path: path.to_string(),
+        kind,
+    }
+}
+
+fn convert(value: &JsValue, path: String, depth: usize) -> Result<VNode, FromJsError> {
+    if depth > MAX_DEPTH {
+        return Err(error(&path, FromJsErrorKind::TooDeep));
+    }
+
+    if value.is_null() || value.is_undefined() || value.as_bool().is_some() {
+        return Ok(VNode::empty());
+    }
+    if let Some(text) = value.as_string() {
+        return Ok(VNode::text(&text));
+    }
+    if let Some(number) = value.as_f64() {
+        return Ok(VNode::text(&number.to_string()));
+    }
+    if Array::is_array(value) {
+        let children = convert_children(value, &path, depth)?;
+        return Ok(with_children(VNode::fragment(), children));
+    }
+    if !value.is_object() {
+        return Err(error(
+            &path,
+            FromJsErrorKind::NotAVNode {
+                found: describe(value),
+            },
+        ));
+    }
+
+    let node_type = get(value, "type");
+    if node_type.is_undefined() {
+        return VNode::try_from_js_value_ref(value).ok_or_else(|| {
+            error(
+                &path,
+                FromJsErrorKind::NotAVNode {
+                    found: describe(value),
+                },
+            )
+        });
+    }
+
+    let props = get(value, "props");
+    if !(props.is_null() || props.is_undefined() || props.is_object()) || Array::is_array(&props) {
+        return Err(error(
+            &format!("{}.props", path),
+            FromJsErrorKind::InvalidProps {
+                found: describe(&props),
+            },
+        ));
+    }
+
+    let children = get(value, "children");
+    if !(children.is_undefined() || Array::is_array(&children)) {
+        return Err(error(
+            &format!("{}.children", path),
+            FromJsErrorKind::InvalidChildren {
+                found: describe(&children),
+            },
+        ));
+    }
+
+    if let Some(component) = node_type.dyn_ref::<Function>() {
+        return call_component(component, &props, &children, &path, depth);
+    }
+
+    let vnode = if node_type == fragment_symbol() {
+        VNode::fragment()
+    } else {
+        match node_type.as_string() {
+            Some(tag) if !tag.is_empty() => VNode::element(&tag),
+            _ => {
+                return Err(error(
+                    &format!("{}.type", path),
+                    FromJsErrorKind::InvalidType {
+                        found: describe(&node_type),
+                    },
+                ));
+            }
+        }
+    };
+
+    let mut vnode = apply_props(vnode, &props, &path)?;
+    if matches!(vnode.patch_flag, PatchFlag::Static(_)) {
+        vnode.patch_flag = PatchFlag::Static(static_id(value));
+    }
+    let children = if children.is_undefined() {
+        Vec::new()
+    } else {
+        convert_children(&children, &path, depth)?
+    };
+    Ok(with_children(vnode, children))
+}
+
+fn convert_children(array: &JsValue, path: &str, depth: usize) -> Result<Vec<VNode>, FromJsError> {
+    Array::from(array)
+        .iter()
+        .enumerate()
+        .map(|(index, child)| convert(&child, format!("{}.children[{}]", path, index), depth + 1))
+        .collect()
+}
+
+fn with_children(vnode: VNode, children: Vec<VNode>) -> VNode {
+    children.into_iter().fold(vnode, VNode::add_child)
+}
+
+fn apply_props(mut vnode: VNode, props: &JsValue, path: &str) -> Result<VNode, FromJsError> {
+    if props.is_null() || props.is_undefined() {
+        return Ok(vnode);
+    }
+
+    for entry in Object::entries(props.unchecked_ref()).iter() {
+        let entry = Array::from(&entry);
+        let (Some(name), value) = (entry.get(0).as_string(), entry.get(1)) else {
+            continue;
+        };
+        let prop_path = || format!("{}.props.{}", path, name);
+
+        match name.as_str() {
+            "children" => {}
+            "ref" if value.is_null() || value.is_undefined() => {}
+            "ref" => match NodeRef::try_from_js_value_ref(&value) {
+                Some(node_ref) => vnode = vnode.node_ref(&node_ref),
+                None => {
+                    return Err(error(
+                        &prop_path(),
+                        FromJsErrorKind::InvalidProp {
+                            reason: format!(
+                                "expected a NodeRef as ref, found {}",
+                                describe(&value)
+                            ),
+                        },
+                    ));
+                }
+            },
+            "key" => match value
+                .as_string()
+                .or_else(|| value.as_f64().map(|key| key.to_string()))
+            {
+                Some(key) => vnode = vnode.set_key(&key),
+                None if value.is_null() || value.is_undefined() => {}
+                None => {
+                    return Err(error(
+                        &prop_path(),
+                        FromJsErrorKind::InvalidProp {
+                            reason: format!(
+                                "expected a string or number as key, found {}",
+                                describe(&value)
+                            ),
+                        },
+                    ));
+                }
+            },
+            "$static" => {
+                if value.is_truthy() {
+                    vnode = vnode.mark_static();
+                }
+            }
+            "$dynamic" if value.is_null() || value.is_undefined() => {}
+            "$dynamic" => {
+                let names = Array::is_array(&value)
+                    .then(|| Array::from(&value).iter().map(|name| name.as_string()).collect())
+                    .flatten();
+                match names {
+                    Some(names) => vnode = vnode.dynamic_props(names),
+                    None => {
+                        return Err(error(
+                            &prop_path(),
+                            FromJsErrorKind::InvalidProp {
+                                reason: format!(
+                                    "expected an array of prop names as $dynamic, found {}",
+                                    describe(&value)
+                                ),
+                            },
+                        ));
+                    }
+                }
+            }
+            _ => {
+                if let Some(handler) = value.dyn_ref::<Function>() {
+                    let Some(event) = name.strip_prefix("on").filter(|event| !event.is_empty())
+                    else {
+                        return Err(error(
+                            &prop_path(),
+                            FromJsErrorKind::InvalidProp {
+                                reason: "functions are only supported as `on*` event handlers"
+                                    .to_string(),
+                            },
+                        ));
+                    };
+                    vnode = vnode.on_js(&event.to_lowercase(), handler.clone());
+                    continue;
+                }
+
+                match PropValue::from_js(&name, &value) {
+                    Ok(Some(value)) => vnode = vnode.prop(&name, value),
+                    Ok(None) => {}
+                    Err(reason) => {
+                        return Err(error(&prop_path(), FromJsErrorKind::InvalidProp { reason }));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(vnode)
+}
+
+/// Expand a function component by calling it with its props and children
+fn call_component(
+    component: &Function,
+    props: &JsValue,
+    children: &JsValue,
+    path: &str,
+    depth: usize,
+) -> Result<VNode, FromJsError> {
+    let args = Object::new();
+    if props.is_object() {
+        Object::assign(&args, props.unchecked_ref());
+    }
+    let children = if children.is_undefined() {
+        Array::new().into()
+    } else {
+        children.clone()
+    };
+    let _ = Reflect::set(&args, &JsValue::from_str("children"), &children);
+
+    let name = String::from(component.name());
+    let path = if name.is_empty() {
+        format!("{} <anonymous>", path)
+    } else {
+        format!("{} <{}>", path, name)
+    };
+
+    let output = component.call1(&JsValue::NULL, &args).map_err(|thrown| {
+        error(
+            &path,
+            FromJsErrorKind::ComponentThrew {
+                message: thrown_message(&thrown),
+            },
+        )
+    })?;
+    let mut vnode = convert(&output, path, depth + 1)?;
+
+    // A key on the component identifies what it rendered
+    if vnode.key.is_none() {
+        if let Some(key) = get(&args, "key")
+            .as_string()
+            .or_else(|| get(&args, "key").as_f64().map(|key| key.to_string()))
+        {
+            vnode = vnode.set_key(&key);
+        }
+    }
+    Ok(vnode)
+}
+
+/// Id of the static instance `value` is, the same for every conversion of
+/// that object, e.g. one hoisted out of a compiled template
+fn static_id(value: &JsValue) -> u64 {
+    let object = value.unchecked_ref::<Object>();
+    STATIC_IDS.with(|ids| match ids.get(object).as_f64() {
+        Some(id) => id as u64,
+        None => {
+            let id = next_static_id();
+            ids.set(object, &JsValue::from_f64(id as f64));
+            id
+        }
+    })
+}
+
+fn fragment_symbol() -> JsValue {
+    js_sys::Symbol::for_("selene.fragment").into()
+}
+
+fn get(object: &JsValue, key: &str) -> JsValue {
+    Reflect::get(object, &JsValue::from_str(key)).unwrap_or(JsValue::UNDEFINED)
+}
+
+/// Short description of a JS value for error messages
+fn describe(value: &JsValue) -> String {
+    if value.is_null() {
+        return "null".to_string();
+    }
+    if Array::is_array(value) {
+        return "an array".to_string();
+    }
+    match value.js_typeof().as_string().as_deref() {
+        Some("string") => format!("string {:?}", value.as_string().unwrap_or_default()),
+        Some("number") => format!("number {}", value.as_f64().unwrap_or_default()),
+        Some("object") => "an object".to_string(),
+        Some(other) => format!("a {}", other),
+        None => "an unknown value".to_string(),
+    }
+}
+
+fn thrown_message(thrown: &JsValue) -> String {
+    thrown
+        .dyn_ref::<js_sys::Error>()
+        .map(|error| String::from(error.message()))
+        .or_else(|| thrown.as_string())
+        .unwrap_or_else(|| format!("{:?}", thrown))
+}
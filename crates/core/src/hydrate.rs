@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A node already in the DOM before the first render, e.g. from
+/// server-rendered HTML, as a backend reports it for hydration
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExistingNode {
+    Element {
+        /// Lowercase tag name
+        tag: String,
+        attributes: BTreeMap<String, String>,
+        children: Vec<ExistingNode>,
+    },
+    Text(String),
+    Comment(String),
+}
+
+impl ExistingNode {
+    /// Short description for mismatch reports, e.g. `<div>` or `text "hi"`
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            ExistingNode::Element { tag, .. } => format!("<{}>", tag),
+            ExistingNode::Text(text) => format!("text {:?}", text),
+            ExistingNode::Comment(text) => format!("comment {:?}", text),
+        }
+    }
+}
+
+/// Difference between the existing DOM and the tree being hydrated
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HydrationMismatch {
+    /// Location in the vnode tree, e.g. `div > ul > li[2]`
+    pub path: String,
+    pub kind: MismatchKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MismatchKind {
+    /// A different node, or none, is where `expected` should be
+    Node {
+        expected: String,
+        found: Option<String>,
+    },
+    /// A text node holds different text
+    Text { expected: String, found: String },
+    /// An attribute differs; `None` means it is absent
+    Attribute {
+        name: String,
+        expected: Option<String>,
+        found: Option<String>,
+    },
+    /// A node is left over after the last expected one
+    Extra { found: String },
+}
+
+impl fmt::Display for HydrationMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.path)?;
+        match &self.kind {
+            MismatchKind::Node {
+                expected,
+                found: Some(found),
+            } => write!(f, "expected {}, found {}", expected, found),
+            MismatchKind::Node {
+                expected,
+                found: None,
+            } => write!(f, "expected {}, found nothing", expected),
+            MismatchKind::Text { expected, found } => {
+                write!(f, "expected text {:?}, found {:?}", expected, found)
+            }
+            MismatchKind::Attribute {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "attribute `{}` should be {}, found {}",
+                name,
+                describe_attribute(expected),
+                describe_attribute(found)
+            ),
+            MismatchKind::Extra { found } => write!(f, "unexpected {}", found),
+        }
+    }
+}
+
+fn describe_attribute(value: &Option<String>) -> String {
+    match value {
+        Some(value) => format!("{:?}", value),
+        None => "absent".to_string(),
+    }
+}
@@ -1,10 +1,47 @@
+mod backend;
+mod boundary;
+mod compiler;
+mod component;
+mod diff;
+mod error_boundary;
+mod event;
+#[cfg(target_arch = "wasm32")]
+mod from_js;
+mod hydrate;
+mod mock_dom;
+mod namespace;
+mod node_ref;
+mod patch;
+mod portal;
+mod prop;
 mod reactivity;
 mod render;
-mod compiler;
+mod ssr;
+mod structure;
+mod suspense;
+mod transition;
 
+pub use backend::*;
+pub use compiler::compile_template;
+pub use component::Component;
+pub use diff::*;
+pub use error_boundary::ErrorReset;
+pub use event::*;
+#[cfg(target_arch = "wasm32")]
+pub use from_js::*;
+pub use hydrate::*;
+pub use mock_dom::*;
+pub use namespace::*;
+pub use node_ref::NodeRef;
+pub use patch::*;
+pub use portal::PortalTarget;
+pub use prop::*;
 pub use reactivity::*;
 pub use render::*;
-pub use compiler::compile_template;
+pub use ssr::{render_to_stream, render_to_string, HtmlStream};
+pub use structure::{DifferenceKind, VNodeDifference};
+pub use suspense::{poll_resources, resource, Resource};
+pub use transition::{Transition, TransitionEvent};
 
 // WebAssembly bindings
 #[cfg(target_arch = "wasm32")]
@@ -24,7 +61,7 @@ macro_rules! console_log {
     ($($t:tt)*) => {
         #[cfg(target_arch = "wasm32")]
         {
-            crate::log(&format_args!($($t)*).to_string())
+            $crate::log(&format_args!($($t)*).to_string())
         }
         #[cfg(not(target_arch = "wasm32"))]
         {
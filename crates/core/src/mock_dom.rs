@@ -0,0 +1,1141 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use crate::backend::{DomBackend, RenderError};
+use crate::compiler::is_void_element;
+use crate::event::{event_bubbles, Event, Listener};
+use crate::hydrate::ExistingNode;
+use crate::patch::NodeId;
+use crate::prop::PropertyValue;
+use crate::ssr::{escape_attribute, escape_text};
+
+/// In-memory `DomBackend`, so rendering can be tested with plain `cargo test`
+///
+/// Cloning is cheap and shares the same tree: keep a clone to inspect what a
+/// `Renderer` built with the other one.
+#[derive(Clone, Default)]
+pub struct MockDom {
+    tree: Rc<RefCell<MockTree>>,
+}
+
+#[derive(Default)]
+struct MockTree {
+    nodes: HashMap<NodeId, MockNode>,
+    /// Nodes loaded with `set_inner_html` get ids counting down from
+    /// `u32::MAX`, out of the way of reconciler ids, until they are claimed
+    parsed: u32,
+}
+
+struct MockNode {
+    data: MockNodeData,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    /// Event type, listener and whether it is delegated from descendants
+    listeners: Vec<(String, Listener, bool)>,
+}
+
+enum MockNodeData {
+    Element {
+        tag: String,
+        /// Namespace URI, `None` for HTML
+        namespace: Option<String>,
+        attributes: BTreeMap<String, String>,
+        properties: BTreeMap<String, PropertyValue>,
+    },
+    Text(String),
+    Comment(String),
+}
+
+impl MockTree {
+    fn get(&self, id: NodeId) -> Result<&MockNode, RenderError> {
+        self.nodes.get(&id).ok_or(RenderError::UnknownNode(id))
+    }
+
+    fn get_mut(&mut self, id: NodeId) -> Result<&mut MockNode, RenderError> {
+        self.nodes.get_mut(&id).ok_or(RenderError::UnknownNode(id))
+    }
+
+    fn attributes_mut(&mut self, id: NodeId) -> Result<&mut BTreeMap<String, String>, RenderError> {
+        match &mut self.get_mut(id)?.data {
+            MockNodeData::Element { attributes, .. } => Ok(attributes),
+            MockNodeData::Text(_) | MockNodeData::Comment(_) => Err(not_an_element(id)),
+        }
+    }
+
+    /// Rewrite the `style` attribute through its parsed declarations, like
+    /// `element.style` does in a browser
+    fn update_style(
+        &mut self,
+        id: NodeId,
+        update: impl FnOnce(&mut Vec<(String, String)>),
+    ) -> Result<(), RenderError> {
+        let attributes = self.attributes_mut(id)?;
+        let mut declarations: Vec<(String, String)> = attributes
+            .get("style")
+            .map(|style| {
+                style
+                    .split(';')
+                    .filter_map(|declaration| declaration.split_once(':'))
+                    .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        update(&mut declarations);
+        if declarations.is_empty() {
+            attributes.remove("style");
+        } else {
+            let style = declarations
+                .iter()
+                .map(|(name, value)| format!("{}: {};", name, value))
+                .collect::<Vec<_>>()
+                .join(" ");
+            attributes.insert("style".to_string(), style);
+        }
+        Ok(())
+    }
+
+    /// Rewrite the `class` attribute through its class list, like
+    /// `element.classList` does in a browser
+    fn update_classes(
+        &mut self,
+        id: NodeId,
+        update: impl FnOnce(&mut Vec<String>),
+    ) -> Result<(), RenderError> {
+        let attributes = self.attributes_mut(id)?;
+        let mut classes: Vec<String> = attributes
+            .get("class")
+            .map(|class| class.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default();
+        update(&mut classes);
+        attributes.insert("class".to_string(), classes.join(" "));
+        Ok(())
+    }
+
+    fn insert(&mut self, id: NodeId, data: MockNodeData) {
+        self.nodes.insert(
+            id,
+            MockNode {
+                data,
+                parent: None,
+                children: Vec::new(),
+                listeners: Vec::new(),
+            },
+        );
+    }
+
+    fn detach(&mut self, id: NodeId) {
+        let parent = self.nodes.get_mut(&id).and_then(|node| node.parent.take());
+        if let Some(parent) = parent.and_then(|parent| self.nodes.get_mut(&parent)) {
+            parent.children.retain(|child| *child != id);
+        }
+    }
+
+    fn drop_subtree(&mut self, id: NodeId) {
+        if let Some(node) = self.nodes.remove(&id) {
+            for child in node.children {
+                self.drop_subtree(child);
+            }
+        }
+    }
+
+    /// Insert parsed nodes under temporary ids and append them to `parent`
+    fn append_parsed(&mut self, parent: NodeId, nodes: Vec<ExistingNode>) {
+        for node in nodes {
+            self.parsed += 1;
+            let id = NodeId(u32::MAX - self.parsed);
+            let (data, children) = match node {
+                ExistingNode::Element {
+                    tag,
+                    attributes,
+                    children,
+                } => (
+                    MockNodeData::Element {
+                        tag,
+                        namespace: None,
+                        attributes,
+                        properties: BTreeMap::new(),
+                    },
+                    children,
+                ),
+                ExistingNode::Text(text) => (MockNodeData::Text(text), Vec::new()),
+                ExistingNode::Comment(text) => (MockNodeData::Comment(text), Vec::new()),
+            };
+            self.insert(id, data);
+            if let Some(node) = self.nodes.get_mut(&id) {
+                node.parent = Some(parent);
+            }
+            if let Some(parent) = self.nodes.get_mut(&parent) {
+                parent.children.push(id);
+            }
+            self.append_parsed(id, children);
+        }
+    }
+
+    fn snapshot(&self, id: NodeId) -> Option<ExistingNode> {
+        let node = self.nodes.get(&id)?;
+        Some(match &node.data {
+            MockNodeData::Element {
+                tag, attributes, ..
+            } => ExistingNode::Element {
+                tag: tag.clone(),
+                attributes: attributes.clone(),
+                children: node
+                    .children
+                    .iter()
+                    .filter_map(|child| self.snapshot(*child))
+                    .collect(),
+            },
+            MockNodeData::Text(text) => ExistingNode::Text(text.clone()),
+            MockNodeData::Comment(text) => ExistingNode::Comment(text.clone()),
+        })
+    }
+
+    fn write_html(&self, id: NodeId, out: &mut String) {
+        let Some(node) = self.nodes.get(&id) else {
+            return;
+        };
+        match &node.data {
+            MockNodeData::Text(text) => out.push_str(&escape_text(text)),
+            MockNodeData::Comment(text) => {
+                out.push_str("<!--");
+                out.push_str(text);
+                out.push_str("-->");
+            }
+            MockNodeData::Element {
+                tag, attributes, ..
+            } => {
+                out.push('<');
+                out.push_str(tag);
+                for (name, value) in attributes {
+                    out.push(' ');
+                    out.push_str(name);
+                    out.push_str("=\"");
+                    out.push_str(&escape_attribute(value));
+                    out.push('"');
+                }
+                out.push('>');
+                if is_void_element(tag) {
+                    return;
+                }
+                for child in &node.children {
+                    self.write_html(*child, out);
+                }
+                out.push_str("</");
+                out.push_str(tag);
+                out.push('>');
+            }
+        }
+    }
+
+    fn write_text(&self, id: NodeId, out: &mut String) {
+        let Some(node) = self.nodes.get(&id) else {
+            return;
+        };
+        match &node.data {
+            MockNodeData::Text(text) => out.push_str(text),
+            MockNodeData::Comment(_) => {}
+            MockNodeData::Element { .. } => {
+                for child in &node.children {
+                    self.write_text(*child, out);
+                }
+            }
+        }
+    }
+
+    fn collect_matches(
+        &self,
+        id: NodeId,
+        selector: &[Compound],
+        first_only: bool,
+        out: &mut Vec<NodeId>,
+    ) {
+        let Some(node) = self.nodes.get(&id) else {
+            return;
+        };
+        for child in &node.children {
+            if first_only && !out.is_empty() {
+                return;
+            }
+            if self.matches(*child, selector) {
+                out.push(*child);
+            }
+            self.collect_matches(*child, selector, first_only, out);
+        }
+    }
+
+    /// Whether `id` matches the last compound and its ancestors the rest, in order
+    fn matches(&self, id: NodeId, selector: &[Compound]) -> bool {
+        let Some((last, rest)) = selector.split_last() else {
+            return false;
+        };
+        if !self.matches_compound(id, last) {
+            return false;
+        }
+
+        let mut remaining = rest;
+        let mut ancestor = self.nodes.get(&id).and_then(|node| node.parent);
+        while let Some((compound, before)) = remaining.split_last() {
+            let Some(current) = ancestor else {
+                return false;
+            };
+            if self.matches_compound(current, compound) {
+                remaining = before;
+            }
+            ancestor = self.nodes.get(&current).and_then(|node| node.parent);
+        }
+        true
+    }
+
+    fn matches_compound(&self, id: NodeId, compound: &Compound) -> bool {
+        let Some(MockNodeData::Element {
+            tag, attributes, ..
+        }) = self.nodes.get(&id).map(|node| &node.data)
+        else {
+            return false;
+        };
+
+        if compound
+            .tag
+            .as_deref()
+            .is_some_and(|expected| expected != tag)
+        {
+            return false;
+        }
+        compound.conditions.iter().all(|condition| match condition {
+            Condition::Id(expected) => attributes.get("id") == Some(expected),
+            Condition::Class(expected) => attributes
+                .get("class")
+                .is_some_and(|class| class.split_whitespace().any(|c| c == expected)),
+            Condition::Attribute(name, None) => attributes.contains_key(name),
+            Condition::Attribute(name, Some(expected)) => attributes.get(name) == Some(expected),
+        })
+    }
+}
+
+impl MockDom {
+    /// Create an empty document
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `id` refers to a live node
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.tree.borrow().nodes.contains_key(&id)
+    }
+
+    /// Namespace URI of an element, `None` for HTML elements and other nodes
+    pub fn namespace(&self, id: NodeId) -> Option<String> {
+        match &self.tree.borrow().nodes.get(&id)?.data {
+            MockNodeData::Element { namespace, .. } => namespace.clone(),
+            MockNodeData::Text(_) | MockNodeData::Comment(_) => None,
+        }
+    }
+
+    /// Tag name of an element, `None` for text and comment nodes and unknown ids
+    pub fn tag(&self, id: NodeId) -> Option<String> {
+        match &self.tree.borrow().nodes.get(&id)?.data {
+            MockNodeData::Element { tag, .. } => Some(tag.clone()),
+            MockNodeData::Text(_) | MockNodeData::Comment(_) => None,
+        }
+    }
+
+    pub fn attribute(&self, id: NodeId, name: &str) -> Option<String> {
+        match &self.tree.borrow().nodes.get(&id)?.data {
+            MockNodeData::Element { attributes, .. } => attributes.get(name).cloned(),
+            MockNodeData::Text(_) | MockNodeData::Comment(_) => None,
+        }
+    }
+
+    /// DOM property assigned with `set_property`; not reflected in the HTML
+    pub fn property(&self, id: NodeId, name: &str) -> Option<PropertyValue> {
+        match &self.tree.borrow().nodes.get(&id)?.data {
+            MockNodeData::Element { properties, .. } => properties.get(name).cloned(),
+            MockNodeData::Text(_) | MockNodeData::Comment(_) => None,
+        }
+    }
+
+    /// Value of one inline style declaration
+    pub fn style(&self, id: NodeId, name: &str) -> Option<String> {
+        let style = self.attribute(id, "style")?;
+        style
+            .split(';')
+            .filter_map(|declaration| declaration.split_once(':'))
+            .find(|(property, _)| property.trim() == name)
+            .map(|(_, value)| value.trim().to_string())
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.tree.borrow().nodes.get(&id)?.parent
+    }
+
+    pub fn children(&self, id: NodeId) -> Vec<NodeId> {
+        self.tree
+            .borrow()
+            .nodes
+            .get(&id)
+            .map(|node| node.children.clone())
+            .unwrap_or_default()
+    }
+
+    /// Concatenated text of a node and its descendants
+    pub fn text_content(&self, id: NodeId) -> String {
+        let mut out = String::new();
+        self.tree.borrow().write_text(id, &mut out);
+        out
+    }
+
+    /// HTML of a node, including its own tag
+    pub fn outer_html(&self, id: NodeId) -> String {
+        let mut out = String::new();
+        self.tree.borrow().write_html(id, &mut out);
+        out
+    }
+
+    /// HTML of a node's children
+    pub fn inner_html(&self, id: NodeId) -> String {
+        let tree = self.tree.borrow();
+        let mut out = String::new();
+        if let Some(node) = tree.nodes.get(&id) {
+            for child in &node.children {
+                tree.write_html(*child, &mut out);
+            }
+        }
+        out
+    }
+
+    /// Replace the children of `id` with nodes parsed from `html`, e.g.
+    /// server-rendered markup to hydrate
+    ///
+    /// The parsed nodes only get usable ids once a `Renderer` hydrates them.
+    pub fn set_inner_html(&self, id: NodeId, html: &str) -> Result<(), RenderError> {
+        let mut tree = self.tree.borrow_mut();
+        let children = std::mem::take(&mut tree.get_mut(id)?.children);
+        for child in children {
+            tree.drop_subtree(child);
+        }
+        tree.append_parsed(id, parse_html(html));
+        Ok(())
+    }
+
+    /// First descendant of `scope`, in document order, matching `selector`
+    ///
+    /// Supports tag names, `#id`, `.class`, `[attr]` and `[attr=value]`,
+    /// compounded (`li.done`) and joined by the descendant combinator.
+    pub fn query_selector(&self, scope: NodeId, selector: &str) -> Option<NodeId> {
+        let selector = parse_selector(selector);
+        let mut out = Vec::new();
+        self.tree
+            .borrow()
+            .collect_matches(scope, &selector, true, &mut out);
+        out.into_iter().next()
+    }
+
+    /// Every descendant of `scope`, in document order, matching `selector`
+    pub fn query_selector_all(&self, scope: NodeId, selector: &str) -> Vec<NodeId> {
+        let selector = parse_selector(selector);
+        let mut out = Vec::new();
+        self.tree
+            .borrow()
+            .collect_matches(scope, &selector, false, &mut out);
+        out
+    }
+
+    /// Dispatch a synthetic event on `target`
+    ///
+    /// Listeners run on the target, then on each ancestor for bubbling event
+    /// types, until one stops propagation. Non-bubbling events still reach
+    /// delegated listeners on ancestors first, as a capture listener would.
+    /// Listeners may freely re-render.
+    pub fn dispatch(&self, target: NodeId, event_type: &str) -> Event {
+        let mut path = vec![target];
+        {
+            let tree = self.tree.borrow();
+            let mut current = tree.nodes.get(&target).and_then(|node| node.parent);
+            while let Some(id) = current {
+                path.push(id);
+                current = tree.nodes.get(&id).and_then(|node| node.parent);
+            }
+        }
+        let event = Event::new(event_type, Some(target)).with_path(path.clone());
+
+        let mut calls: Vec<(NodeId, bool)> = Vec::new();
+        if event_bubbles(event_type) {
+            calls.extend(path.iter().map(|&id| (id, false)));
+        } else {
+            calls.extend(path[1..].iter().rev().map(|&id| (id, true)));
+            calls.push((target, false));
+        }
+
+        for (id, delegated_only) in calls {
+            let listener = self.tree.borrow().nodes.get(&id).and_then(|node| {
+                node.listeners
+                    .iter()
+                    .find(|(name, _, delegated)| {
+                        name == event_type && (*delegated || !delegated_only)
+                    })
+                    .map(|(_, listener, _)| Rc::clone(listener))
+            });
+            if let Some(listener) = listener {
+                event.set_current_target(Some(id));
+                listener(&event);
+            }
+            if event.is_propagation_stopped() {
+                break;
+            }
+        }
+
+        event.set_current_target(None);
+        event
+    }
+
+    /// Event types with a listener on `id`
+    pub fn listeners(&self, id: NodeId) -> Vec<String> {
+        self.tree
+            .borrow()
+            .nodes
+            .get(&id)
+            .map(|node| {
+                node.listeners
+                    .iter()
+                    .map(|(name, _, _)| name.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl MockDom {
+    fn create(
+        &mut self,
+        id: NodeId,
+        tag: &str,
+        namespace: Option<&str>,
+    ) -> Result<(), RenderError> {
+        if tag.is_empty()
+            || !tag
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == ':')
+        {
+            return Err(RenderError::Backend(format!("invalid tag name {:?}", tag)));
+        }
+        self.tree.borrow_mut().insert(
+            id,
+            MockNodeData::Element {
+                tag: tag.to_string(),
+                namespace: namespace.map(str::to_string),
+                attributes: BTreeMap::new(),
+                properties: BTreeMap::new(),
+            },
+        );
+        Ok(())
+    }
+
+    fn listen(
+        &mut self,
+        id: NodeId,
+        event: &str,
+        listener: Listener,
+        delegated: bool,
+    ) -> Result<(), RenderError> {
+        let mut tree = self.tree.borrow_mut();
+        let listeners = &mut tree.get_mut(id)?.listeners;
+        listeners.retain(|(name, _, _)| name != event);
+        listeners.push((event.to_string(), listener, delegated));
+        Ok(())
+    }
+}
+
+impl DomBackend for MockDom {
+    fn existing_children(&self, parent: NodeId) -> Result<Vec<ExistingNode>, RenderError> {
+        let tree = self.tree.borrow();
+        Ok(tree
+            .get(parent)?
+            .children
+            .iter()
+            .filter_map(|child| tree.snapshot(*child))
+            .collect())
+    }
+
+    fn claim(&mut self, parent: NodeId, index: usize, id: NodeId) -> Result<(), RenderError> {
+        let mut tree = self.tree.borrow_mut();
+        let current = *tree.get(parent)?.children.get(index).ok_or_else(|| {
+            RenderError::Backend(format!("node {} has no child {} to claim", parent.0, index))
+        })?;
+        if current == id {
+            return Ok(());
+        }
+        tree.drop_subtree(id);
+        let node = tree
+            .nodes
+            .remove(&current)
+            .ok_or(RenderError::UnknownNode(current))?;
+        for child in &node.children {
+            if let Some(child) = tree.nodes.get_mut(child) {
+                child.parent = Some(id);
+            }
+        }
+        tree.nodes.insert(id, node);
+        tree.get_mut(parent)?.children[index] = id;
+        Ok(())
+    }
+
+    fn create_element(&mut self, id: NodeId, tag: &str) -> Result<(), RenderError> {
+        self.create(id, tag, None)
+    }
+
+    fn create_element_ns(
+        &mut self,
+        id: NodeId,
+        namespace: &str,
+        tag: &str,
+    ) -> Result<(), RenderError> {
+        self.create(id, tag, Some(namespace))
+    }
+
+    fn create_text(&mut self, id: NodeId, text: &str) -> Result<(), RenderError> {
+        self.tree
+            .borrow_mut()
+            .insert(id, MockNodeData::Text(text.to_string()));
+        Ok(())
+    }
+
+    fn create_comment(&mut self, id: NodeId, text: &str) -> Result<(), RenderError> {
+        self.tree
+            .borrow_mut()
+            .insert(id, MockNodeData::Comment(text.to_string()));
+        Ok(())
+    }
+
+    fn set_attribute(&mut self, id: NodeId, name: &str, value: &str) -> Result<(), RenderError> {
+        self.tree
+            .borrow_mut()
+            .attributes_mut(id)?
+            .insert(name.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn remove_attribute(&mut self, id: NodeId, name: &str) -> Result<(), RenderError> {
+        self.tree.borrow_mut().attributes_mut(id)?.remove(name);
+        Ok(())
+    }
+
+    fn set_property(
+        &mut self,
+        id: NodeId,
+        name: &str,
+        value: &PropertyValue,
+    ) -> Result<(), RenderError> {
+        let mut tree = self.tree.borrow_mut();
+        match &mut tree.get_mut(id)?.data {
+            MockNodeData::Element { properties, .. } => {
+                properties.insert(name.to_string(), value.clone());
+                Ok(())
+            }
+            MockNodeData::Text(_) | MockNodeData::Comment(_) => Err(not_an_element(id)),
+        }
+    }
+
+    fn set_style(&mut self, id: NodeId, name: &str, value: &str) -> Result<(), RenderError> {
+        self.tree.borrow_mut().update_style(id, |declarations| {
+            match declarations
+                .iter_mut()
+                .find(|(existing, _)| existing == name)
+            {
+                Some((_, existing)) => *existing = value.to_string(),
+                None => declarations.push((name.to_string(), value.to_string())),
+            }
+        })
+    }
+
+    fn remove_style(&mut self, id: NodeId, name: &str) -> Result<(), RenderError> {
+        self.tree.borrow_mut().update_style(id, |declarations| {
+            declarations.retain(|(existing, _)| existing != name)
+        })
+    }
+
+    fn add_class(&mut self, id: NodeId, name: &str) -> Result<(), RenderError> {
+        self.tree.borrow_mut().update_classes(id, |classes| {
+            if !classes.iter().any(|class| class == name) {
+                classes.push(name.to_string());
+            }
+        })
+    }
+
+    fn remove_class(&mut self, id: NodeId, name: &str) -> Result<(), RenderError> {
+        self.tree
+            .borrow_mut()
+            .update_classes(id, |classes| classes.retain(|class| class != name))
+    }
+
+    fn set_text(&mut self, id: NodeId, text: &str) -> Result<(), RenderError> {
+        let mut tree = self.tree.borrow_mut();
+        let node = tree.get_mut(id)?;
+        match &mut node.data {
+            MockNodeData::Text(content) | MockNodeData::Comment(content) => {
+                *content = text.to_string()
+            }
+            MockNodeData::Element { .. } => {
+                return Err(RenderError::Backend(format!(
+                    "node {} is not a text or comment node",
+                    id.0
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_before(
+        &mut self,
+        parent: NodeId,
+        id: NodeId,
+        before: Option<NodeId>,
+    ) -> Result<(), RenderError> {
+        let mut tree = self.tree.borrow_mut();
+        tree.get(id)?;
+        if let MockNodeData::Text(_) | MockNodeData::Comment(_) = tree.get(parent)?.data {
+            return Err(not_an_element(parent));
+        }
+
+        tree.detach(id);
+
+        let siblings = &tree.get(parent)?.children;
+        let index = match before {
+            Some(before) => siblings
+                .iter()
+                .position(|child| *child == before)
+                .ok_or_else(|| {
+                    RenderError::Backend(format!(
+                        "node {} is not a child of node {}",
+                        before.0, parent.0
+                    ))
+                })?,
+            None => siblings.len(),
+        };
+
+        tree.get_mut(parent)?.children.insert(index, id);
+        tree.get_mut(id)?.parent = Some(parent);
+        Ok(())
+    }
+
+    fn remove(&mut self, id: NodeId) -> Result<(), RenderError> {
+        let mut tree = self.tree.borrow_mut();
+        tree.detach(id);
+        tree.drop_subtree(id);
+        Ok(())
+    }
+
+    fn add_event_listener(
+        &mut self,
+        id: NodeId,
+        event: &str,
+        listener: Listener,
+    ) -> Result<(), RenderError> {
+        self.listen(id, event, listener, false)
+    }
+
+    fn add_delegated_listener(
+        &mut self,
+        id: NodeId,
+        event: &str,
+        listener: Listener,
+    ) -> Result<(), RenderError> {
+        self.listen(id, event, listener, true)
+    }
+
+    fn remove_event_listener(&mut self, id: NodeId, event: &str) -> Result<(), RenderError> {
+        if let Some(node) = self.tree.borrow_mut().nodes.get_mut(&id) {
+            node.listeners.retain(|(name, _, _)| name != event);
+        }
+        Ok(())
+    }
+}
+
+fn not_an_element(id: NodeId) -> RenderError {
+    RenderError::Backend(format!("node {} is not an element", id.0))
+}
+
+/// One compound selector, e.g. `li.done[data-id=3]`
+struct Compound {
+    tag: Option<String>,
+    conditions: Vec<Condition>,
+}
+
+enum Condition {
+    Id(String),
+    Class(String),
+    Attribute(String, Option<String>),
+}
+
+fn parse_selector(selector: &str) -> Vec<Compound> {
+    selector.split_whitespace().map(parse_compound).collect()
+}
+
+fn parse_compound(input: &str) -> Compound {
+    fn is_name_char(c: char) -> bool {
+        c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == ':'
+    }
+
+    let mut chars = input.chars().peekable();
+    let mut tag = String::new();
+    while let Some(&c) = chars.peek() {
+        if !is_name_char(c) && c != '*' {
+            break;
+        }
+        tag.push(c);
+        chars.next();
+    }
+
+    let mut conditions = Vec::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '#' | '.' => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !is_name_char(c) {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                conditions.push(if c == '#' {
+                    Condition::Id(name)
+                } else {
+                    Condition::Class(name)
+                });
+            }
+            '[' => {
+                let body: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                match body.split_once('=') {
+                    Some((name, value)) => {
+                        let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+                        conditions.push(Condition::Attribute(
+                            name.trim().to_string(),
+                            Some(value.to_string()),
+                        ));
+                    }
+                    None => conditions.push(Condition::Attribute(body.trim().to_string(), None)),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Compound {
+        tag: (!tag.is_empty() && tag != "*").then_some(tag),
+        conditions,
+    }
+}
+
+/// Parse an HTML fragment the way a browser would for the markup
+/// `render_to_string` produces: elements, void and self-closing tags,
+/// quoted and bare attributes, comments, raw text in `script`, `style`,
+/// `textarea` and `title`, and character references
+fn parse_html(html: &str) -> Vec<ExistingNode> {
+    // Open elements, with a bottom entry collecting the top-level nodes
+    let mut open: Vec<(String, BTreeMap<String, String>, Vec<ExistingNode>)> =
+        vec![(String::new(), BTreeMap::new(), Vec::new())];
+
+    fn push(
+        open: &mut [(String, BTreeMap<String, String>, Vec<ExistingNode>)],
+        node: ExistingNode,
+    ) {
+        let children = &mut open.last_mut().expect("bottom entry is never closed").2;
+        match (children.last_mut(), node) {
+            (Some(ExistingNode::Text(last)), ExistingNode::Text(text)) => last.push_str(&text),
+            (_, node) => children.push(node),
+        }
+    }
+
+    fn close(open: &mut Vec<(String, BTreeMap<String, String>, Vec<ExistingNode>)>) {
+        if let Some((tag, attributes, children)) = open.pop() {
+            push(
+                open,
+                ExistingNode::Element {
+                    tag,
+                    attributes,
+                    children,
+                },
+            );
+        }
+    }
+
+    let mut rest = html;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("<!--") {
+            let (text, next) = after.split_once("-->").unwrap_or((after, ""));
+            push(&mut open, ExistingNode::Comment(text.to_string()));
+            rest = next;
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let (name, next) = after.split_once('>').unwrap_or((after, ""));
+            let name = name.trim().to_ascii_lowercase();
+            // Unmatched end tags are ignored; matched ones close what is inside
+            if let Some(position) = open.iter().skip(1).rposition(|(tag, ..)| *tag == name) {
+                while open.len() > position + 1 {
+                    close(&mut open);
+                }
+            }
+            rest = next;
+        } else if rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+        {
+            let (tag, attributes, self_closing, next) = parse_start_tag(&rest[1..]);
+            rest = next;
+            if self_closing || is_void_element(&tag) {
+                push(
+                    &mut open,
+                    ExistingNode::Element {
+                        tag,
+                        attributes,
+                        children: Vec::new(),
+                    },
+                );
+            } else if matches!(tag.as_str(), "script" | "style" | "textarea" | "title") {
+                let end = find_ignore_case(rest, &format!("</{}", tag)).unwrap_or(rest.len());
+                let text = if matches!(tag.as_str(), "textarea" | "title") {
+                    decode_entities(&rest[..end])
+                } else {
+                    rest[..end].to_string()
+                };
+                let children = if text.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![ExistingNode::Text(text)]
+                };
+                push(
+                    &mut open,
+                    ExistingNode::Element {
+                        tag,
+                        attributes,
+                        children,
+                    },
+                );
+                rest = rest[end..].split_once('>').map_or("", |(_, next)| next);
+            } else {
+                open.push((tag, attributes, Vec::new()));
+            }
+        } else {
+            let end = rest[1..].find('<').map_or(rest.len(), |index| index + 1);
+            push(&mut open, ExistingNode::Text(decode_entities(&rest[..end])));
+            rest = &rest[end..];
+        }
+    }
+
+    while open.len() > 1 {
+        close(&mut open);
+    }
+    open.pop().map(|(_, _, nodes)| nodes).unwrap_or_default()
+}
+
+/// Parse what follows the `<` of a start tag up to its `>`
+fn parse_start_tag(input: &str) -> (String, BTreeMap<String, String>, bool, &str) {
+    let is_name_end = |c: char| c.is_whitespace() || c == '/' || c == '>';
+    let name_end = input.find(is_name_end).unwrap_or(input.len());
+    let tag = input[..name_end].to_ascii_lowercase();
+    let mut rest = &input[name_end..];
+    let mut attributes = BTreeMap::new();
+
+    loop {
+        rest = rest.trim_start();
+        if let Some(next) = rest.strip_prefix("/>") {
+            return (tag, attributes, true, next);
+        }
+        if let Some(next) = rest.strip_prefix('>') {
+            return (tag, attributes, false, next);
+        }
+        if rest.is_empty() {
+            return (tag, attributes, false, rest);
+        }
+
+        let name_end = rest
+            .find(|c: char| is_name_end(c) || c == '=')
+            .unwrap_or(rest.len());
+        if name_end == 0 {
+            // A stray `/` or `=`
+            rest = &rest[1..];
+            continue;
+        }
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let (raw, next) = after[1..].split_once(quote).unwrap_or((&after[1..], ""));
+                    value = decode_entities(raw);
+                    rest = next;
+                }
+                _ => {
+                    let end = after
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(after.len());
+                    value = decode_entities(&after[..end]);
+                    rest = &after[end..];
+                }
+            }
+        }
+        attributes.entry(name).or_insert(value);
+    }
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .char_indices()
+        .map(|(index, _)| index)
+        .find(|&index| {
+            haystack
+                .get(index..index + needle.len())
+                .is_some_and(|candidate| candidate.eq_ignore_ascii_case(needle))
+        })
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[1..end + 1])?, end + 2)));
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        _ => {
+            let code = match name.strip_prefix('#')? {
+                hex if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16).ok()?,
+                decimal => decimal.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `<ul id="list"><li class="done item">a</li><li data-id="2">b &amp; c</li></ul>`
+    fn list() -> (MockDom, NodeId) {
+        let mut dom = MockDom::new();
+        let root = NodeId(0);
+        dom.create_element(root, "div").unwrap();
+        dom.create_element(NodeId(1), "ul").unwrap();
+        dom.set_attribute(NodeId(1), "id", "list").unwrap();
+        dom.create_element(NodeId(2), "li").unwrap();
+        dom.set_attribute(NodeId(2), "class", "done item").unwrap();
+        dom.create_text(NodeId(3), "a").unwrap();
+        dom.create_element(NodeId(4), "li").unwrap();
+        dom.set_attribute(NodeId(4), "data-id", "2").unwrap();
+        dom.create_text(NodeId(5), "b & c").unwrap();
+        dom.insert_before(NodeId(2), NodeId(3), None).unwrap();
+        dom.insert_before(NodeId(4), NodeId(5), None).unwrap();
+        dom.insert_before(NodeId(1), NodeId(2), None).unwrap();
+        dom.insert_before(NodeId(1), NodeId(4), None).unwrap();
+        dom.insert_before(root, NodeId(1), None).unwrap();
+        (dom, root)
+    }
+
+    #[test]
+    fn query_selector_matches_tags_ids_classes_and_attributes() {
+        let (dom, root) = list();
+        assert_eq!(dom.query_selector(root, "ul"), Some(NodeId(1)));
+        assert_eq!(dom.query_selector(root, "#list"), Some(NodeId(1)));
+        assert_eq!(dom.query_selector(root, ".done"), Some(NodeId(2)));
+        assert_eq!(dom.query_selector(root, "li.item"), Some(NodeId(2)));
+        assert_eq!(dom.query_selector(root, "[data-id]"), Some(NodeId(4)));
+        assert_eq!(dom.query_selector(root, "[data-id=2]"), Some(NodeId(4)));
+        assert_eq!(dom.query_selector(root, "[data-id=3]"), None);
+        assert_eq!(dom.query_selector(root, "p"), None);
+    }
+
+    #[test]
+    fn query_selector_all_walks_descendants_in_document_order() {
+        let (dom, root) = list();
+        assert_eq!(
+            dom.query_selector_all(root, "li"),
+            vec![NodeId(2), NodeId(4)]
+        );
+        assert_eq!(
+            dom.query_selector_all(root, "#list li"),
+            vec![NodeId(2), NodeId(4)]
+        );
+        assert_eq!(dom.query_selector_all(root, "li ul"), Vec::<NodeId>::new());
+        // The scope itself is not a candidate
+        assert_eq!(
+            dom.query_selector_all(NodeId(1), "ul"),
+            Vec::<NodeId>::new()
+        );
+    }
+
+    #[test]
+    fn html_serialization_escapes_and_nests() {
+        let (dom, root) = list();
+        let ul = r#"<ul id="list"><li class="done item">a</li><li data-id="2">b &amp; c</li></ul>"#;
+        assert_eq!(dom.inner_html(root), ul);
+        assert_eq!(dom.outer_html(root), format!("<div>{}</div>", ul));
+        assert_eq!(dom.outer_html(NodeId(5)), "b &amp; c");
+        assert_eq!(dom.text_content(root), "ab & c");
+    }
+
+    #[test]
+    fn html_serialization_of_void_elements_comments_and_quotes() {
+        let mut dom = MockDom::new();
+        dom.create_element(NodeId(0), "p").unwrap();
+        dom.create_element(NodeId(1), "input").unwrap();
+        dom.set_attribute(NodeId(1), "value", "say \"hi\"").unwrap();
+        dom.create_comment(NodeId(2), "slot").unwrap();
+        dom.insert_before(NodeId(0), NodeId(1), None).unwrap();
+        dom.insert_before(NodeId(0), NodeId(2), None).unwrap();
+        assert_eq!(
+            dom.outer_html(NodeId(0)),
+            r#"<p><input value="say &quot;hi&quot;"><!--slot--></p>"#
+        );
+    }
+
+    #[test]
+    fn set_inner_html_round_trips() {
+        let (dom, root) = list();
+        let html = r#"<section class="a"><h1>Title</h1><br><!--x-->text &lt;b&gt;</section>"#;
+        dom.set_inner_html(root, html).unwrap();
+        assert_eq!(dom.inner_html(root), html);
+        // The replaced nodes are gone
+        assert!(!dom.contains(NodeId(1)));
+        assert!(!dom.contains(NodeId(5)));
+    }
+
+    #[test]
+    fn remove_forgets_the_subtree() {
+        let (mut dom, root) = list();
+        dom.remove(NodeId(2)).unwrap();
+        assert!(!dom.contains(NodeId(2)));
+        assert!(!dom.contains(NodeId(3)));
+        assert_eq!(dom.children(NodeId(1)), vec![NodeId(4)]);
+        assert_eq!(
+            dom.inner_html(root),
+            r#"<ul id="list"><li data-id="2">b &amp; c</li></ul>"#
+        );
+    }
+}
@@ -0,0 +1,67 @@
+pub const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+pub const MATHML_NAMESPACE: &str = "http://www.w3.org/1998/Math/MathML";
+pub const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+pub const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+pub const XMLNS_NAMESPACE: &str = "http://www.w3.org/2000/xmlns/";
+
+/// Namespace an element is created in
+///
+/// `svg` and `math` elements start their namespace, which their
+/// descendants inherit; the children of an SVG `foreignObject` are HTML
+/// again.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Namespace {
+    #[default]
+    Html,
+    Svg,
+    MathMl,
+}
+
+impl Namespace {
+    /// Namespace URI to create elements with, `None` for plain HTML
+    pub fn uri(self) -> Option<&'static str> {
+        match self {
+            Namespace::Html => None,
+            Namespace::Svg => Some(SVG_NAMESPACE),
+            Namespace::MathMl => Some(MATHML_NAMESPACE),
+        }
+    }
+
+    /// Namespace of an element with namespace URI `uri`, as the DOM reports
+    /// it; unknown namespaces count as HTML
+    pub fn from_uri(uri: Option<&str>) -> Namespace {
+        match uri {
+            Some(SVG_NAMESPACE) => Namespace::Svg,
+            Some(MATHML_NAMESPACE) => Namespace::MathMl,
+            _ => Namespace::Html,
+        }
+    }
+
+    /// Namespace of a `tag` element whose parent's children are in `self`
+    pub fn of_element(self, tag: &str) -> Namespace {
+        match tag {
+            "svg" => Namespace::Svg,
+            "math" => Namespace::MathMl,
+            _ => self,
+        }
+    }
+
+    /// Namespace the children of a `tag` element in `self` are in
+    pub fn of_children(self, tag: &str) -> Namespace {
+        match (self, tag) {
+            (Namespace::Svg, "foreignObject") => Namespace::Html,
+            (namespace, _) => namespace,
+        }
+    }
+}
+
+/// Namespace URI of an attribute with a reserved prefix, e.g. `xlink:href`
+pub fn attribute_namespace(name: &str) -> Option<&'static str> {
+    match name.split_once(':') {
+        Some(("xlink", _)) => Some(XLINK_NAMESPACE),
+        Some(("xml", _)) => Some(XML_NAMESPACE),
+        Some(("xmlns", _)) => Some(XMLNS_NAMESPACE),
+        None if name == "xmlns" => Some(XMLNS_NAMESPACE),
+        _ => None,
+    }
+}
@@ -0,0 +1,109 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::JsCast;
+
+use crate::patch::NodeId;
+
+/// Handle to the node a vnode is rendered to
+///
+/// Attach it with `VNode::node_ref`. It is set once the node is mounted,
+/// follows the vnode across re-renders and is cleared when the node is
+/// unmounted. Clones share the same target. Refs on fragments, portals
+/// and components are ignored.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Default)]
+pub struct NodeRef {
+    target: Rc<RefCell<RefTarget>>,
+}
+
+#[derive(Default)]
+struct RefTarget {
+    id: Option<NodeId>,
+    #[cfg(target_arch = "wasm32")]
+    node: Option<web_sys::Node>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl NodeRef {
+    /// Create an empty ref
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new() -> NodeRef {
+        NodeRef::default()
+    }
+}
+
+impl NodeRef {
+    /// Backend id of the node, while it is mounted
+    pub fn id(&self) -> Option<NodeId> {
+        self.target.borrow().id
+    }
+
+    /// Whether both refs share the same target
+    pub fn ptr_eq(&self, other: &NodeRef) -> bool {
+        Rc::ptr_eq(&self.target, &other.target)
+    }
+
+    /// Apply a change recorded by the reconciler
+    ///
+    /// Clearing only takes effect if the ref still points at that node,
+    /// and not at one it was moved to since.
+    pub(crate) fn update(&self, update: RefUpdate) {
+        let mut target = self.target.borrow_mut();
+        match update {
+            RefUpdate::Set(id) => target.id = Some(id),
+            RefUpdate::Clear(id) if target.id == Some(id) => *target = RefTarget::default(),
+            RefUpdate::Clear(_) => {}
+        }
+    }
+}
+
+/// Change to a ref, applied once the patches of a pass are
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum RefUpdate {
+    Set(NodeId),
+    Clear(NodeId),
+}
+
+#[cfg(target_arch = "wasm32")]
+impl NodeRef {
+    /// The DOM node, while it is mounted
+    pub fn get(&self) -> Option<web_sys::Node> {
+        self.target.borrow().node.clone()
+    }
+
+    /// The DOM node as a specific type, e.g. `web_sys::HtmlInputElement`
+    pub fn cast<T: JsCast>(&self) -> Option<T> {
+        self.get()?.dyn_into().ok()
+    }
+
+    pub(crate) fn set_node(&self, node: Option<web_sys::Node>) {
+        self.target.borrow_mut().node = node;
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl NodeRef {
+    /// The DOM node, or `null` when not mounted
+    #[wasm_bindgen(getter)]
+    pub fn current(&self) -> Option<web_sys::Node> {
+        self.get()
+    }
+}
+
+impl PartialEq for NodeRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other)
+    }
+}
+
+impl fmt::Debug for NodeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NodeRef").field("id", &self.id()).finish()
+    }
+}
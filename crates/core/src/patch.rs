@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+use crate::prop::PropertyValue;
+
+/// Abstract identifier of a node owned by a rendering backend
+///
+/// Ids are handed out by the `Reconciler`. Once a node is removed, the ids
+/// of its whole subtree are reused by later create patches, so a backend
+/// may simply overwrite whatever it stored under a reused id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NodeId(pub u32);
+
+/// A single platform-independent DOM operation produced by diffing
+///
+/// Patches are applied strictly in order. Created nodes start detached and
+/// are placed with `InsertBefore`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Patch {
+    /// Create a detached element
+    CreateElement { id: NodeId, tag: String },
+    /// Create a detached element in a namespace other than HTML, e.g. SVG
+    CreateElementNs {
+        id: NodeId,
+        namespace: String,
+        tag: String,
+    },
+    /// Create a detached text node
+    CreateText { id: NodeId, text: String },
+    /// Create a detached comment node, marking an empty slot or the end of
+    /// a fragment
+    CreateComment { id: NodeId, text: String },
+    /// Adopt the `index`th child `parent` had before hydration started as
+    /// `id`; all children of a parent are claimed before any of them changes
+    Claim {
+        parent: NodeId,
+        index: usize,
+        id: NodeId,
+    },
+    /// Set (or overwrite) an attribute
+    SetAttribute {
+        id: NodeId,
+        name: String,
+        value: String,
+    },
+    /// Remove an attribute
+    RemoveAttribute { id: NodeId, name: String },
+    /// Set (or overwrite) an attribute in a namespace, e.g. `xlink:href`;
+    /// `name` is the qualified name
+    SetAttributeNs {
+        id: NodeId,
+        namespace: String,
+        name: String,
+        value: String,
+    },
+    /// Remove a namespaced attribute, given by its qualified name
+    RemoveAttributeNs {
+        id: NodeId,
+        namespace: String,
+        name: String,
+    },
+    /// Assign a DOM property, e.g. `value` or `checked`
+    SetProperty {
+        id: NodeId,
+        name: String,
+        value: PropertyValue,
+    },
+    /// Set one inline style declaration
+    SetStyle {
+        id: NodeId,
+        name: String,
+        value: String,
+    },
+    /// Remove one inline style declaration
+    RemoveStyle { id: NodeId, name: String },
+    /// Add a class to an element's class list, e.g. for a transition
+    AddClass { id: NodeId, name: String },
+    /// Remove a class from an element's class list
+    RemoveClass { id: NodeId, name: String },
+    /// Replace the content of a text or comment node
+    SetText { id: NodeId, text: String },
+    /// Insert `id` into `parent` before `before`, or append it when `before`
+    /// is `None`; inserting an attached node moves it
+    InsertBefore {
+        parent: NodeId,
+        id: NodeId,
+        before: Option<NodeId>,
+    },
+    /// Detach a node, together with its subtree, from its parent
+    Remove { id: NodeId },
+    /// Start listening for `event` on `id`; the handler itself is looked up
+    /// in the reconciler's `HandlerTable` when the event fires
+    AddEventListener { id: NodeId, event: String },
+    /// Listen for `event` on `id` and all its descendants, dispatching to
+    /// their handlers; used by roots in event delegation mode, and by the
+    /// containers of portals in such roots
+    AddDelegatedListener { id: NodeId, event: String },
+    /// Listen for `event` bubbling up to the container `id` of a portal and
+    /// pass it on to the handlers of the portal's ancestors; used by roots
+    /// that do not delegate
+    AddPortalListener { id: NodeId, event: String },
+    /// Stop listening for `event` on `id`
+    RemoveEventListener { id: NodeId, event: String },
+}
@@ -0,0 +1,33 @@
+use crate::patch::NodeId;
+
+/// Node a portal mounts its children into
+#[derive(Clone, Debug, PartialEq)]
+pub enum PortalTarget {
+    /// A node the backend already holds, e.g. one created with
+    /// `Renderer::create_portal_target`
+    Node(NodeId),
+    /// A DOM element, e.g. `document.body`
+    #[cfg(target_arch = "wasm32")]
+    Element(web_sys::Element),
+}
+
+impl From<NodeId> for PortalTarget {
+    fn from(id: NodeId) -> Self {
+        PortalTarget::Node(id)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl From<web_sys::Element> for PortalTarget {
+    fn from(element: web_sys::Element) -> Self {
+        PortalTarget::Element(element)
+    }
+}
+
+/// Where a `VNode::portal` renders its children
+#[derive(Clone, Debug)]
+pub(crate) struct Portal {
+    pub(crate) target: PortalTarget,
+    /// Element holding the children inside the target, set once mounted
+    pub(crate) container: Option<NodeId>,
+}
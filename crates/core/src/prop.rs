@@ -0,0 +1,296 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{JsCast, JsValue};
+
+/// Value of a `VNode` prop
+///
+/// Each kind is diffed and applied differently: attributes are set as
+/// strings, boolean attributes are added or removed, class sets become the
+/// `class` attribute, style maps are patched per declaration and DOM
+/// properties are assigned on the node object. Numbers compare by value,
+/// except that NaN equals itself, so props can be hashed.
+#[derive(Clone, Debug)]
+pub enum PropValue {
+    /// Plain string attribute
+    Str(String),
+    /// Boolean attribute, present (and empty) when true, removed when false
+    Bool(bool),
+    /// Numeric attribute
+    Number(f64),
+    /// Class names, rendered as a space-separated `class` attribute
+    Classes(BTreeSet<String>),
+    /// Inline style declarations, e.g. `"background-color" => "red"`
+    Style(BTreeMap<String, String>),
+    /// DOM property assigned on the node, e.g. `value` or `checked`
+    Property(PropertyValue),
+}
+
+/// Primitive value of a DOM property
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PropertyValue {
+    Str(String),
+    Bool(bool),
+    Number(f64),
+}
+
+impl PartialEq for PropValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (PropValue::Str(a), PropValue::Str(b)) => a == b,
+            (PropValue::Bool(a), PropValue::Bool(b)) => a == b,
+            (PropValue::Number(a), PropValue::Number(b)) => number_bits(*a) == number_bits(*b),
+            (PropValue::Classes(a), PropValue::Classes(b)) => a == b,
+            (PropValue::Style(a), PropValue::Style(b)) => a == b,
+            (PropValue::Property(a), PropValue::Property(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for PropValue {}
+
+impl Hash for PropValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            PropValue::Str(value) => value.hash(state),
+            PropValue::Bool(value) => value.hash(state),
+            PropValue::Number(value) => number_bits(*value).hash(state),
+            PropValue::Classes(classes) => classes.hash(state),
+            PropValue::Style(style) => style.hash(state),
+            PropValue::Property(value) => value.hash(state),
+        }
+    }
+}
+
+impl PartialEq for PropertyValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (PropertyValue::Str(a), PropertyValue::Str(b)) => a == b,
+            (PropertyValue::Bool(a), PropertyValue::Bool(b)) => a == b,
+            (PropertyValue::Number(a), PropertyValue::Number(b)) => {
+                number_bits(*a) == number_bits(*b)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for PropertyValue {}
+
+impl Hash for PropertyValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            PropertyValue::Str(value) => value.hash(state),
+            PropertyValue::Bool(value) => value.hash(state),
+            PropertyValue::Number(value) => number_bits(*value).hash(state),
+        }
+    }
+}
+
+/// Bits a number compares and hashes by, the same for every NaN
+fn number_bits(value: f64) -> u64 {
+    if value.is_nan() {
+        f64::NAN.to_bits()
+    } else {
+        value.to_bits()
+    }
+}
+
+impl PropValue {
+    /// Class set from whitespace-separated class names
+    pub fn classes<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        PropValue::Classes(
+            names
+                .into_iter()
+                .flat_map(str::split_whitespace)
+                .map(str::to_string)
+                .collect(),
+        )
+    }
+
+    /// Style map from `(property, value)` pairs
+    pub fn style<'a>(declarations: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        PropValue::Style(
+            declarations
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
+
+    /// Value stored for the prop `name`
+    ///
+    /// Primitive values of props that only work as DOM properties, such as
+    /// `value` or `checked`, are turned into `Property`.
+    pub(crate) fn normalize(name: &str, value: PropValue) -> PropValue {
+        if !is_dom_property(name) {
+            return value;
+        }
+        match value {
+            PropValue::Str(value) => PropValue::Property(PropertyValue::Str(value)),
+            PropValue::Bool(value) => PropValue::Property(PropertyValue::Bool(value)),
+            PropValue::Number(value) => PropValue::Property(PropertyValue::Number(value)),
+            other => other,
+        }
+    }
+
+    /// Attribute value this prop renders to, or `None` if it sets no attribute
+    pub fn attribute_value(&self) -> Option<String> {
+        match self {
+            PropValue::Str(value) => Some(value.clone()),
+            PropValue::Bool(true) => Some(String::new()),
+            PropValue::Bool(false) => None,
+            PropValue::Number(value) => Some(value.to_string()),
+            PropValue::Classes(classes) if classes.is_empty() => None,
+            PropValue::Classes(classes) => Some(
+                classes
+                    .iter()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            PropValue::Style(_) | PropValue::Property(_) => None,
+        }
+    }
+
+    /// Declarations of a `Style` prop
+    pub(crate) fn style_map(&self) -> Option<&BTreeMap<String, String>> {
+        match self {
+            PropValue::Style(style) => Some(style),
+            _ => None,
+        }
+    }
+
+    /// Convert a JS value passed as the prop `name`
+    ///
+    /// Strings, booleans and numbers map to the matching variant. For
+    /// `class`, an array of names or an object whose truthy keys are the
+    /// names gives a class set; for `style`, an object gives a style map.
+    /// `null` and `undefined` mean "no prop" and return `None`.
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_js(name: &str, value: &JsValue) -> Result<Option<PropValue>, String> {
+        if value.is_null() || value.is_undefined() {
+            return Ok(None);
+        }
+        if let Some(value) = value.as_string() {
+            return Ok(Some(PropValue::Str(value)));
+        }
+        if let Some(value) = value.as_bool() {
+            return Ok(Some(PropValue::Bool(value)));
+        }
+        if let Some(value) = value.as_f64() {
+            return Ok(Some(PropValue::Number(value)));
+        }
+
+        match name {
+            "class" | "className" if js_sys::Array::is_array(value) => {
+                let names: Vec<String> = js_sys::Array::from(value)
+                    .iter()
+                    .filter_map(|name| name.as_string())
+                    .collect();
+                Ok(Some(PropValue::classes(names.iter().map(String::as_str))))
+            }
+            "class" | "className" if value.is_object() => {
+                let entries = js_sys::Object::entries(value.unchecked_ref());
+                let names: Vec<String> = entries
+                    .iter()
+                    .filter_map(|entry| {
+                        let entry = js_sys::Array::from(&entry);
+                        entry.get(1).is_truthy().then(|| entry.get(0).as_string())?
+                    })
+                    .collect();
+                Ok(Some(PropValue::classes(names.iter().map(String::as_str))))
+            }
+            "style" if value.is_object() => {
+                let entries = js_sys::Object::entries(value.unchecked_ref());
+                let mut style = BTreeMap::new();
+                for entry in entries.iter() {
+                    let entry = js_sys::Array::from(&entry);
+                    let (Some(name), declaration) = (entry.get(0).as_string(), entry.get(1)) else {
+                        continue;
+                    };
+                    if let Some(declaration) = declaration
+                        .as_string()
+                        .or_else(|| declaration.as_f64().map(|value| value.to_string()))
+                    {
+                        style.insert(name, declaration);
+                    }
+                }
+                Ok(Some(PropValue::Style(style)))
+            }
+            _ => Err(format!("unsupported value for prop `{}`", name)),
+        }
+    }
+}
+
+impl PropertyValue {
+    /// Value restoring the property once the prop is gone
+    pub(crate) fn reset(&self) -> PropertyValue {
+        match self {
+            PropertyValue::Str(_) => PropertyValue::Str(String::new()),
+            PropertyValue::Bool(_) => PropertyValue::Bool(false),
+            PropertyValue::Number(_) => PropertyValue::Number(0.0),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl From<&PropertyValue> for JsValue {
+    fn from(value: &PropertyValue) -> Self {
+        match value {
+            PropertyValue::Str(value) => JsValue::from_str(value),
+            PropertyValue::Bool(value) => JsValue::from_bool(*value),
+            PropertyValue::Number(value) => JsValue::from_f64(*value),
+        }
+    }
+}
+
+impl From<&str> for PropValue {
+    fn from(value: &str) -> Self {
+        PropValue::Str(value.to_string())
+    }
+}
+
+impl From<String> for PropValue {
+    fn from(value: String) -> Self {
+        PropValue::Str(value)
+    }
+}
+
+impl From<bool> for PropValue {
+    fn from(value: bool) -> Self {
+        PropValue::Bool(value)
+    }
+}
+
+impl From<f64> for PropValue {
+    fn from(value: f64) -> Self {
+        PropValue::Number(value)
+    }
+}
+
+impl From<i32> for PropValue {
+    fn from(value: i32) -> Self {
+        PropValue::Number(value.into())
+    }
+}
+
+impl From<PropertyValue> for PropValue {
+    fn from(value: PropertyValue) -> Self {
+        PropValue::Property(value)
+    }
+}
+
+/// Props whose current state lives in a DOM property rather than the
+/// attribute, which only sets the initial value
+pub fn is_dom_property(name: &str) -> bool {
+    matches!(
+        name,
+        "value" | "checked" | "selected" | "muted" | "indeterminate"
+    )
+}
@@ -1,19 +1,187 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashSet, VecDeque};
 use std::rc::Rc;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-// Thread-local storage for active effect
+// Thread-local reactive runtime: the effect currently tracking reads,
+// the batch nesting depth and the queue of effects waiting to re-run.
 thread_local! {
-    static ACTIVE_EFFECT: RefCell<Option<Rc<RefCell<Vec<Rc<dyn Fn()>>>>>> = RefCell::new(None);
+    static ACTIVE_EFFECT: RefCell<Option<Rc<EffectState>>> = const { RefCell::new(None) };
+    static BATCH_DEPTH: Cell<usize> = const { Cell::new(0) };
+    static FLUSHING: Cell<bool> = const { Cell::new(false) };
+    static PENDING: RefCell<VecDeque<Rc<EffectState>>> = const { RefCell::new(VecDeque::new()) };
+    static ACTIVE_SCOPE: RefCell<Option<Scope>> = const { RefCell::new(None) };
+    static NEXT_SIGNAL_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Effects subscribed to a signal, notified in subscription order
+///
+/// An entry only counts while its effect is still in the run that read the
+/// signal; entries from earlier runs are stale and dropped lazily, so
+/// unsubscribing never searches the list.
+struct Subscribers {
+    /// Identifies the signal in the sources of an effect run
+    id: u64,
+    /// Effect and the run it subscribed in
+    entries: RefCell<Vec<(Rc<EffectState>, u64)>>,
+    /// Entry count at which stale entries are dropped on the next subscribe
+    compact_at: Cell<usize>,
+}
+
+impl Subscribers {
+    fn new() -> Self {
+        Subscribers {
+            id: NEXT_SIGNAL_ID.with(|next| {
+                let id = next.get();
+                next.set(id + 1);
+                id
+            }),
+            entries: RefCell::new(Vec::new()),
+            compact_at: Cell::new(8),
+        }
+    }
+
+    fn subscribe(&self, effect: &Rc<EffectState>) {
+        let mut entries = self.entries.borrow_mut();
+        if entries.len() >= self.compact_at.get() {
+            entries.retain(|(effect, run)| effect.runs.get() == *run);
+            self.compact_at.set((entries.len() * 2).max(8));
+        }
+        entries.push((Rc::clone(effect), effect.runs.get()));
+    }
+
+    /// Queue every subscribed effect, dropping stale entries on the way
+    fn notify(&self) {
+        self.entries.borrow_mut().retain(|(effect, run)| {
+            let current = effect.runs.get() == *run;
+            if current {
+                schedule(effect);
+            }
+            current
+        });
+    }
+}
+
+/// Shared state behind an effect handle
+struct EffectState {
+    run: RefCell<Option<Rc<dyn Fn()>>>,
+    /// Bumped whenever the effect starts over, making the subscriptions of
+    /// the previous run stale
+    runs: Cell<u64>,
+    /// Ids of the signals read during the current run
+    sources: RefCell<HashSet<u64>>,
+    queued: Cell<bool>,
+}
+
+impl EffectState {
+    fn execute(self: &Rc<Self>) {
+        let Some(run) = self.run.borrow().clone() else {
+            return;
+        };
+
+        // Dependencies are collected from scratch on every run
+        self.unsubscribe();
+
+        let _active = ActiveEffectGuard::enter(Some(Rc::clone(self)));
+        run();
+    }
+
+    fn unsubscribe(&self) {
+        self.runs.set(self.runs.get() + 1);
+        self.sources.borrow_mut().clear();
+    }
+}
+
+/// Restores the previously active effect when dropped, so nested effects
+/// and panicking effect bodies leave the tracking context intact.
+struct ActiveEffectGuard {
+    previous: Option<Rc<EffectState>>,
+}
+
+impl ActiveEffectGuard {
+    fn enter(effect: Option<Rc<EffectState>>) -> Self {
+        let previous = ACTIVE_EFFECT.with(|ae| ae.replace(effect));
+        ActiveEffectGuard { previous }
+    }
+}
+
+impl Drop for ActiveEffectGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        ACTIVE_EFFECT.with(|ae| *ae.borrow_mut() = previous);
+    }
+}
+
+/// Leaves the current batch when dropped, flushing queued effects once the
+/// outermost batch completes.
+struct BatchGuard;
+
+impl BatchGuard {
+    fn enter() -> Self {
+        BATCH_DEPTH.with(|depth| depth.set(depth.get() + 1));
+        BatchGuard
+    }
+}
+
+impl Drop for BatchGuard {
+    fn drop(&mut self) {
+        let depth = BATCH_DEPTH.with(|depth| {
+            depth.set(depth.get() - 1);
+            depth.get()
+        });
+        if depth == 0 && !std::thread::panicking() {
+            flush();
+        }
+    }
+}
+
+fn track(subscribers: &Subscribers) {
+    ACTIVE_EFFECT.with(|ae| {
+        let active = ae.borrow();
+        let Some(effect) = active.as_ref() else {
+            return;
+        };
+
+        if effect.sources.borrow_mut().insert(subscribers.id) {
+            subscribers.subscribe(effect);
+        }
+    });
+}
+
+fn schedule(effect: &Rc<EffectState>) {
+    if effect.queued.replace(true) {
+        return;
+    }
+    PENDING.with(|pending| pending.borrow_mut().push_back(Rc::clone(effect)));
+}
+
+fn flush() {
+    if FLUSHING.with(|flushing| flushing.replace(true)) {
+        // The running flush loop picks up anything queued meanwhile
+        return;
+    }
+
+    struct FlushGuard;
+    impl Drop for FlushGuard {
+        fn drop(&mut self) {
+            FLUSHING.with(|flushing| flushing.set(false));
+        }
+    }
+    let _flushing = FlushGuard;
+
+    while let Some(effect) = PENDING.with(|pending| pending.borrow_mut().pop_front()) {
+        effect.queued.set(false);
+        effect.execute();
+    }
 }
 
 /// Signal implementation in Rust for better performance
 #[derive(Clone)]
 pub struct Signal<T: Clone + PartialEq + 'static> {
     value: Rc<RefCell<T>>,
-    subscribers: Rc<RefCell<Vec<Rc<dyn Fn()>>>>,
+    subscribers: Rc<Subscribers>,
 }
 
 impl<T: Clone + PartialEq + 'static> Signal<T> {
@@ -21,20 +189,13 @@ impl<T: Clone + PartialEq + 'static> Signal<T> {
     pub fn new(initial_value: T) -> Self {
         Signal {
             value: Rc::new(RefCell::new(initial_value)),
-            subscribers: Rc::new(RefCell::new(Vec::new())),
+            subscribers: Rc::new(Subscribers::new()),
         }
     }
 
     /// Get the current value and track dependencies
     pub fn get(&self) -> T {
-        // Track dependency if we're in an effect
-        if let Some(active_effect) = ACTIVE_EFFECT.with(|ae| ae.borrow().clone()) {
-            let effect_fn = Rc::new(|| {
-                // This would trigger re-evaluation of the effect
-                // For now, we'll just store a no-op
-            });
-            active_effect.borrow_mut().push(effect_fn);
-        }
+        track(&self.subscribers);
         self.value.borrow().clone()
     }
 
@@ -46,11 +207,9 @@ impl<T: Clone + PartialEq + 'static> Signal<T> {
 
         *self.value.borrow_mut() = new_value;
 
-        // Notify all subscribers
-        let subscribers = self.subscribers.borrow().clone();
-        for effect in subscribers {
-            effect();
-        }
+        // Notify all subscribers; inside a batch they run when it ends
+        let _batch = BatchGuard::enter();
+        self.subscribers.notify();
     }
 
     /// Get current value without tracking dependencies
@@ -64,29 +223,97 @@ pub fn signal<T: Clone + PartialEq + 'static>(initial_value: T) -> Signal<T> {
     Signal::new(initial_value)
 }
 
+/// Handle to a running effect
+#[derive(Clone)]
+pub struct Effect {
+    state: Rc<EffectState>,
+}
+
+impl Effect {
+    /// Stop the effect: it unsubscribes from every signal and never runs again
+    pub fn dispose(&self) {
+        self.state.run.borrow_mut().take();
+        self.state.unsubscribe();
+    }
+
+    /// Whether `dispose` has been called
+    pub fn is_disposed(&self) -> bool {
+        self.state.run.borrow().is_none()
+    }
+
+    /// Re-run the effect right away, outside of the update queue
+    pub(crate) fn run(&self) {
+        self.state.execute();
+    }
+}
+
 /// Run an effect function and track its dependencies
-pub fn effect<F>(f: F)
+///
+/// The effect re-runs whenever a signal read during its last run changes,
+/// until the returned handle is disposed. Dropping the handle does not stop it.
+pub fn effect<F>(f: F) -> Effect
 where
     F: Fn() + 'static,
 {
-    let effect_deps = Rc::new(RefCell::new(Vec::new()));
+    let state = Rc::new(EffectState {
+        run: RefCell::new(Some(Rc::new(f))),
+        runs: Cell::new(0),
+        sources: RefCell::new(HashSet::new()),
+        queued: Cell::new(false),
+    });
 
-    let effect_fn = Rc::new(move || {
-        // Clear previous dependencies
-        effect_deps.borrow_mut().clear();
+    let handle = Effect { state };
+    ACTIVE_SCOPE.with(|scope| {
+        if let Some(scope) = scope.borrow().as_ref() {
+            scope.effects.borrow_mut().push(handle.clone());
+        }
+    });
 
-        // Set this effect as active
-        ACTIVE_EFFECT.with(|ae| *ae.borrow_mut() = Some(Rc::clone(&effect_deps)));
+    // Run effect initially
+    handle.state.execute();
 
-        // Run the effect
-        f();
+    handle
+}
 
-        // Clear active effect
-        ACTIVE_EFFECT.with(|ae| *ae.borrow_mut() = None);
-    });
+/// Owner of the effects created while it runs code, disposing them together
+///
+/// Effects created inside `Scope::run`, directly or from nested code, are
+/// registered with the scope; `dispose` stops all of them.
+#[derive(Clone, Default)]
+pub struct Scope {
+    effects: Rc<RefCell<Vec<Effect>>>,
+}
 
-    // Run effect initially
-    effect_fn();
+impl Scope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `f` with this scope owning the effects it creates
+    pub fn run<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        struct ScopeGuard(Option<Scope>);
+        impl Drop for ScopeGuard {
+            fn drop(&mut self) {
+                let previous = self.0.take();
+                ACTIVE_SCOPE.with(|scope| *scope.borrow_mut() = previous);
+            }
+        }
+
+        let previous = ACTIVE_SCOPE.with(|scope| scope.replace(Some(self.clone())));
+        let _scope = ScopeGuard(previous);
+        f()
+    }
+
+    /// Dispose every effect created in this scope so far
+    pub fn dispose(&self) {
+        let effects = std::mem::take(&mut *self.effects.borrow_mut());
+        for effect in effects {
+            effect.dispose();
+        }
+    }
 }
 
 /// Create a computed signal that derives its value from other signals
@@ -94,23 +321,44 @@ pub fn computed<T: Clone + PartialEq + 'static, F>(f: F) -> Signal<T>
 where
     F: Fn() -> T + 'static,
 {
-    let signal = Signal::new(f());
+    let slot: Rc<RefCell<Option<Signal<T>>>> = Rc::new(RefCell::new(None));
 
     effect({
-        let signal = signal.clone();
+        let slot = Rc::clone(&slot);
         move || {
-            signal.set(f());
+            let value = f();
+            let existing = slot.borrow().clone();
+            match existing {
+                Some(signal) => signal.set(value),
+                None => *slot.borrow_mut() = Some(Signal::new(value)),
+            }
         }
     });
 
-    signal
+    let signal = slot.borrow().clone();
+    signal.expect("computed effect runs synchronously on creation")
+}
+
+/// Group signal writes so dependent effects run once, after `f` returns
+pub fn batch<R, F>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let _batch = BatchGuard::enter();
+    f()
+}
+
+/// Run `f` without tracking the signals it reads
+pub fn untrack<R, F>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let _active = ActiveEffectGuard::enter(None);
+    f()
 }
 
 // wasm-bindgen doesn't support generic impls. Provide a concrete JS-facing wrapper
 // for numeric signals (f64) used by the JS side.
-#[cfg(target_arch = "wasm32")]
-use wasm_bindgen::prelude::*;
-
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub struct JsSignal {
@@ -132,8 +380,222 @@ impl JsSignal {
         self.inner.get()
     }
 
+    /// Throws the first exception of a JS effect or computed this write
+    /// re-ran
     #[wasm_bindgen(setter)]
-    pub fn set_value(&mut self, value: f64) {
-        self.inner.set(value);
+    pub fn set_value(&mut self, value: f64) -> Result<(), JsValue> {
+        catch_js_errors(|| self.inner.set(value))
+    }
+}
+
+/// Read-only signal produced by `computed` on the JS side
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub struct JsComputed {
+    inner: Signal<JsValue>,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl JsComputed {
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> JsValue {
+        self.inner.get()
+    }
+}
+
+// Exception thrown by a JS effect or computed, until the JS call that ran
+// it rethrows it. Exceptions cannot unwind through the runtime, which would
+// leave its batch and flush state behind.
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    static JS_ERROR: RefCell<Option<JsValue>> = const { RefCell::new(None) };
+}
+
+/// Keep the first exception thrown while the current JS call runs
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn report_js_error(err: JsValue) {
+    JS_ERROR.with(|error| {
+        error.borrow_mut().get_or_insert(err);
+    });
+}
+
+/// Run `f` for a JS call, failing with the first exception a JS effect or
+/// computed threw meanwhile
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn catch_js_errors<R>(f: impl FnOnce() -> R) -> Result<R, JsValue> {
+    let outer = JS_ERROR.with(|error| error.borrow_mut().take());
+    let result = f();
+    match JS_ERROR.with(|error| error.replace(outer)) {
+        Some(err) => Err(err),
+        None => Ok(result),
+    }
+}
+
+/// Run a JS function as an effect; returns a function that disposes it
+///
+/// Throws, without keeping the effect, if the first run throws. Exceptions
+/// of later runs are thrown from the signal write or batch that caused them.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = effect)]
+pub fn js_effect(f: js_sys::Function) -> Result<js_sys::Function, JsValue> {
+    let mut handle = None;
+    let first_run = catch_js_errors(|| {
+        handle = Some(effect(move || {
+            if let Err(err) = f.call0(&JsValue::NULL) {
+                report_js_error(err);
+            }
+        }));
+    });
+    let handle = handle.expect("effects run synchronously on creation");
+    if let Err(err) = first_run {
+        handle.dispose();
+        return Err(err);
+    }
+
+    Ok(Closure::<dyn Fn()>::new(move || handle.dispose())
+        .into_js_value()
+        .unchecked_into())
+}
+
+/// Derive a read-only signal from a JS function
+///
+/// Throws if the first run throws; when a later run throws, the value stays
+/// as it was and the exception is thrown like an effect's.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = computed)]
+pub fn js_computed(f: js_sys::Function) -> Result<JsComputed, JsValue> {
+    let inner = Signal::new(JsValue::UNDEFINED);
+    let mut handle = None;
+    let first_run = catch_js_errors(|| {
+        let inner = inner.clone();
+        handle = Some(effect(move || match f.call0(&JsValue::NULL) {
+            Ok(value) => inner.set(value),
+            Err(err) => report_js_error(err),
+        }));
+    });
+    if let Err(err) = first_run {
+        handle
+            .expect("effects run synchronously on creation")
+            .dispose();
+        return Err(err);
+    }
+    Ok(JsComputed { inner })
+}
+
+/// Run a JS function as a batch and return its result
+///
+/// Throws what the function threw, or else the first exception of a JS
+/// effect or computed the batch re-ran.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = batch)]
+pub fn js_batch(f: &js_sys::Function) -> Result<JsValue, JsValue> {
+    catch_js_errors(|| batch(|| f.call0(&JsValue::NULL)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effect_reading_a_signal_twice_runs_once_per_write() {
+        let source = signal(0);
+        let runs = Rc::new(Cell::new(0));
+        let _effect = effect({
+            let (source, runs) = (source.clone(), Rc::clone(&runs));
+            move || {
+                source.get();
+                source.get();
+                runs.set(runs.get() + 1);
+            }
+        });
+
+        source.set(1);
+        source.set(2);
+
+        assert_eq!(runs.get(), 3);
+    }
+
+    #[test]
+    fn dependencies_of_earlier_runs_are_dropped() {
+        let toggle = signal(true);
+        let (a, b) = (signal(0), signal(0));
+        let runs = Rc::new(Cell::new(0));
+        let _effect = effect({
+            let (toggle, a, b, runs) = (toggle.clone(), a.clone(), b.clone(), Rc::clone(&runs));
+            move || {
+                runs.set(runs.get() + 1);
+                if toggle.get() {
+                    a.get();
+                } else {
+                    b.get();
+                }
+            }
+        });
+
+        toggle.set(false);
+        a.set(1);
+        assert_eq!(runs.get(), 2);
+        b.set(1);
+        assert_eq!(runs.get(), 3);
+    }
+
+    #[test]
+    fn disposed_effects_stop_running() {
+        let source = signal(0);
+        let runs = Rc::new(Cell::new(0));
+        let effects: Vec<Effect> = (0..3)
+            .map(|_| {
+                let (source, runs) = (source.clone(), Rc::clone(&runs));
+                effect(move || {
+                    source.get();
+                    runs.set(runs.get() + 1);
+                })
+            })
+            .collect();
+
+        effects[1].dispose();
+        source.set(1);
+
+        assert!(effects[1].is_disposed());
+        assert_eq!(runs.get(), 3 + 2);
+    }
+
+    #[test]
+    fn subscribers_run_in_subscription_order_across_re_runs() {
+        let source = signal(0);
+        let order = Rc::new(RefCell::new(Vec::new()));
+        let _effects: Vec<Effect> = (0..3)
+            .map(|index| {
+                let (source, order) = (source.clone(), Rc::clone(&order));
+                effect(move || {
+                    source.get();
+                    order.borrow_mut().push(index);
+                })
+            })
+            .collect();
+
+        source.set(1);
+        source.set(2);
+
+        assert_eq!(*order.borrow(), [0, 1, 2, 0, 1, 2, 0, 1, 2]);
+    }
+
+    #[test]
+    fn stale_subscriptions_do_not_pile_up() {
+        let (trigger, read) = (signal(0), signal(0));
+        let _effect = effect({
+            let (trigger, read) = (trigger.clone(), read.clone());
+            move || {
+                trigger.get();
+                read.get();
+            }
+        });
+
+        for value in 1..100 {
+            trigger.set(value);
+        }
+
+        assert!(read.subscribers.entries.borrow().len() <= 8);
     }
 }
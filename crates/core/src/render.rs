@@ -1,22 +1,136 @@
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::ops::Range;
+use std::rc::Rc;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 #[cfg(target_arch = "wasm32")]
-use web_sys::{Document, Element, Node, window};
-#[cfg(not(target_arch = "wasm32"))]
-use std::marker::PhantomData;
+use web_sys::{window, Element};
 
+#[cfg(target_arch = "wasm32")]
+use crate::backend::WebDom;
+use crate::backend::{DomBackend, RenderError};
+use crate::boundary::AsyncBoundary;
+use crate::component::{Component, ComponentInstance, ComponentNode, ComponentSpec, Lifecycle};
 use crate::console_log;
+use crate::diff::Reconciler;
+use crate::error_boundary::{
+    BoundaryFailure, ErrorBoundaryInstance, ErrorBoundaryNode, ErrorReset,
+};
+use crate::event::{Event, EventHandler, HandlerTable};
+use crate::hydrate::ExistingNode;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::mock_dom::MockDom;
+#[cfg(target_arch = "wasm32")]
+use crate::namespace::Namespace;
+use crate::node_ref::{NodeRef, RefUpdate};
+use crate::patch::{NodeId, Patch};
+use crate::portal::{Portal, PortalTarget};
+use crate::prop::PropValue;
+#[cfg(target_arch = "wasm32")]
+use crate::reactivity::report_js_error;
+use crate::reactivity::{batch, effect, untrack, Effect};
+use crate::suspense::SuspenseNode;
+use crate::transition::{self, SharedBackend, Transition, TransitionNode, TransitionStart};
 
 #[cfg(not(target_arch = "wasm32"))]
 impl Renderer {
+    /// Create a renderer drawing into an in-memory `MockDom`
     pub fn new() -> Self {
-        Renderer { _phantom: PhantomData }
+        Self::with_backend(MockDom::new()).expect("mock DOM accepts a div container")
+    }
+
+    /// Create a renderer drawing into `backend`
+    ///
+    /// A detached `div` is created as the container every render targets.
+    pub fn with_backend(backend: impl DomBackend + 'static) -> Result<Self, RenderError> {
+        let mut reconciler = Reconciler::new();
+        let root = reconciler.create_root();
+        let mut backend: Box<dyn DomBackend> = Box::new(backend);
+        backend.create_element(root, "div")?;
+
+        let (reconciler, backend) = connect(reconciler, backend);
+        Ok(Renderer {
+            reconciler,
+            backend,
+            root,
+        })
+    }
+
+    /// Id of the container every render targets
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    /// Create a detached `div` for portals to render into, standing in for
+    /// `document.body` on native builds
+    pub fn create_portal_target(&self) -> Result<NodeId, RenderError> {
+        let id = self.reconciler.borrow_mut().create_root();
+        self.backend.borrow_mut().create_element(id, "div")?;
+        Ok(id)
+    }
+
+    /// Diff `vnode` against the previous render, apply the patches to the
+    /// backend and return them
+    ///
+    /// Components re-render on their own when their signals change; those
+    /// updates are applied right away and not returned from any call.
+    pub fn render(&self, vnode: &VNode) -> Result<Vec<Patch>, RenderError> {
+        // Effects triggered while rendering run once the render is done
+        batch(|| {
+            let mut patches = self.reconciler.borrow_mut().render(self.root, vnode);
+            let fallbacks = self.apply(&patches)?;
+            patches.extend(fallbacks);
+            patches.extend(flush_components(&self.reconciler, &self.backend)?);
+            Ok(patches)
+        })
+    }
+
+    /// Listen for events once at the container instead of on every node
+    ///
+    /// Takes effect on the next render if nothing has been rendered yet, or
+    /// after `unmount`.
+    pub fn set_event_delegation(&self, enabled: bool) {
+        self.reconciler.borrow_mut().set_event_delegation(enabled);
+    }
+
+    /// Re-render the parts of the existing DOM that do not match when
+    /// hydrating, instead of failing with `RenderError::HydrationMismatch`
+    pub fn set_hydration_recovery(&self, enabled: bool) {
+        self.reconciler.borrow_mut().set_hydration_recovery(enabled);
+    }
+
+    /// Adopt the nodes already in the container, e.g. server-rendered HTML
+    /// loaded with `MockDom::set_inner_html`, as the first render of `vnode`
+    ///
+    /// Existing nodes are kept and only get their event listeners; see
+    /// `set_hydration_recovery` for what happens on a mismatch.
+    pub fn hydrate(&self, vnode: &VNode) -> Result<Vec<Patch>, RenderError> {
+        batch(|| {
+            let existing = self.backend.borrow().existing_children(self.root)?;
+            let mut patches = hydrate(&self.reconciler, self.root, vnode, &existing)?;
+            let fallbacks = self.apply(&patches)?;
+            patches.extend(fallbacks);
+            patches.extend(flush_components(&self.reconciler, &self.backend)?);
+            Ok(patches)
+        })
     }
 
-    pub fn render(&self, _vnode: &VNode) {
-        console_log!("Rendering would happen here in WASM");
+    /// Remove everything rendered so far
+    pub fn unmount(&self) -> Result<Vec<Patch>, RenderError> {
+        let mut patches = self.reconciler.borrow_mut().unmount(self.root);
+        let fallbacks = self.apply(&patches)?;
+        patches.extend(fallbacks);
+        Ok(patches)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -25,19 +139,124 @@ pub fn h(node_type: &str) -> VNode {
     VNode::element(node_type)
 }
 
+/// Render the tree returned by `render_fn` with `renderer` and keep it up
+/// to date
+///
+/// `render_fn` runs inside an effect: whenever a signal it read changes, it
+/// runs again and the new tree is diffed against the previous one.
+///
+/// Fails, without keeping the effect, if the first render fails; see
+/// `RenderHandle::last_error` for the re-renders.
 #[cfg(not(target_arch = "wasm32"))]
-pub fn reactive_render(_render_fn: impl Fn() -> VNode) {
-    console_log!("Reactive rendering would happen here in WASM");
+pub fn reactive_render(
+    renderer: Renderer,
+    render_fn: impl Fn() -> VNode + 'static,
+) -> Result<RenderHandle, RenderError> {
+    reactive(renderer, render_fn, false)
+}
+
+/// Like `reactive_render`, but the first render hydrates the nodes already
+/// in the renderer's container
+#[cfg(not(target_arch = "wasm32"))]
+pub fn reactive_hydrate(
+    renderer: Renderer,
+    render_fn: impl Fn() -> VNode + 'static,
+) -> Result<RenderHandle, RenderError> {
+    reactive(renderer, render_fn, true)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn reactive(
+    renderer: Renderer,
+    render_fn: impl Fn() -> VNode + 'static,
+    hydrate: bool,
+) -> Result<RenderHandle, RenderError> {
+    let renderer = Rc::new(renderer);
+    // Only the first render hydrates
+    let hydrating = Cell::new(hydrate);
+    let last_error = Rc::new(RefCell::new(None));
+
+    let effect = effect({
+        let renderer = Rc::clone(&renderer);
+        let last_error = Rc::clone(&last_error);
+        move || {
+            let vnode = render_fn();
+            let result = untrack(|| {
+                if hydrating.replace(false) {
+                    renderer.hydrate(&vnode)
+                } else {
+                    renderer.render(&vnode)
+                }
+            });
+            *last_error.borrow_mut() = result.err();
+        }
+    });
+
+    let first_error = last_error.borrow_mut().take();
+    if let Some(err) = first_error {
+        effect.dispose();
+        return Err(err);
+    }
+    Ok(RenderHandle {
+        effect,
+        renderer,
+        last_error,
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl RenderHandle {
+    /// The renderer drawing the tree
+    pub fn renderer(&self) -> &Renderer {
+        &self.renderer
+    }
+
+    /// Why the latest re-render failed, `None` once one succeeds again
+    pub fn last_error(&self) -> Option<RenderError> {
+        self.last_error.borrow().clone()
+    }
+
+    /// Stop re-rendering and remove what was rendered
+    pub fn unmount(&self) -> Result<Vec<Patch>, RenderError> {
+        self.effect.dispose();
+        self.renderer.unmount()
+    }
 }
 
 /// Virtual DOM Node representation
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 #[derive(Clone, Debug)]
 pub struct VNode {
-    node_type: String,
-    props: HashMap<String, String>,
-    children: Vec<VNode>,
-    text_content: Option<String>,
+    /// Whether this is an element or one of the special nodes
+    pub(crate) kind: NodeKind,
+    /// Tag of an element, type name of a component, empty otherwise
+    pub(crate) node_type: String,
+    pub(crate) key: Option<String>,
+    pub(crate) props: BTreeMap<String, PropValue>,
+    pub(crate) handlers: BTreeMap<String, EventHandler>,
+    pub(crate) children: Vec<VNode>,
+    pub(crate) text_content: Option<String>,
+    /// Component to mount in place of this node, see `VNode::component`
+    pub(crate) component: Option<ComponentNode>,
+    /// Async content replacing the fallback children, see
+    /// `VNode::async_boundary`
+    pub(crate) boundary: Option<AsyncBoundary>,
+    /// Fallback shown while the children suspend, see `VNode::suspense`
+    pub(crate) suspense: Option<SuspenseNode>,
+    /// Fallback replacing the children when they fail, see
+    /// `VNode::error_boundary`
+    pub(crate) error_boundary: Option<ErrorBoundaryNode>,
+    /// Ref pointed at the node once mounted, see `VNode::node_ref`
+    pub(crate) node_ref: Option<NodeRef>,
+    /// Target the children are rendered into, see `VNode::portal`
+    pub(crate) portal: Option<Portal>,
+    /// What re-renders may change, see `VNode::mark_static`
+    pub(crate) patch_flag: PatchFlag,
+    /// Animations played as the element enters and leaves, see
+    /// `VNode::transition`
+    pub(crate) transition: Option<TransitionNode>,
+    /// Backend node this vnode was rendered to, set once mounted
+    pub(crate) id: Option<NodeId>,
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
@@ -45,26 +264,53 @@ impl VNode {
     /// Create a new element node
     pub fn element(node_type: &str) -> VNode {
         VNode {
+            kind: NodeKind::Element,
             node_type: node_type.to_string(),
-            props: HashMap::new(),
+            key: None,
+            props: BTreeMap::new(),
+            handlers: BTreeMap::new(),
             children: Vec::new(),
             text_content: None,
+            component: None,
+            boundary: None,
+            suspense: None,
+            error_boundary: None,
+            node_ref: None,
+            portal: None,
+            patch_flag: PatchFlag::Full,
+            transition: None,
+            id: None,
         }
     }
 
     /// Create a new text node
     pub fn text(content: &str) -> VNode {
         VNode {
-            node_type: "TEXT".to_string(),
-            props: HashMap::new(),
-            children: Vec::new(),
             text_content: Some(content.to_string()),
+            ..VNode::special(NodeKind::Text)
+        }
+    }
+
+    /// Create a fragment: its children are placed directly into the parent
+    pub fn fragment() -> VNode {
+        VNode::special(NodeKind::Fragment)
+    }
+
+    /// Create an empty placeholder, e.g. for a conditional that renders
+    /// nothing; it is rendered as an empty comment
+    pub fn empty() -> VNode {
+        VNode {
+            text_content: Some(String::new()),
+            ..VNode::special(NodeKind::Comment)
         }
     }
 
-    /// Set a property on the node
-    pub fn set_prop(mut self, key: &str, value: &str) -> Self {
-        self.props.insert(key.to_string(), value.to_string());
+    /// Set the key identifying this node among its siblings
+    ///
+    /// Keyed children are matched by key rather than position when a list
+    /// is re-rendered, so reordering moves existing DOM nodes.
+    pub fn set_key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
         self
     }
 
@@ -74,20 +320,312 @@ impl VNode {
         self
     }
 
+    /// Create a portal rendering its children into `target`, e.g.
+    /// `document.body`
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen(js_name = portal)]
+    pub fn portal_js(target: Element) -> VNode {
+        VNode::portal(target)
+    }
+
+    /// Declare that this subtree never changes: once mounted, re-renders
+    /// keep it as is, props, handlers and children included, as long as
+    /// they render a clone of this same vnode
+    ///
+    /// Each call marks a new static instance; a different vnode marked
+    /// static is diffed as usual.
+    pub fn mark_static(mut self) -> Self {
+        self.patch_flag = PatchFlag::Static(next_static_id());
+        self
+    }
+
+    /// Declare that only the props in `names` can change between renders,
+    /// so the others are not compared; children are diffed as usual
+    pub fn dynamic_props(mut self, names: Vec<String>) -> Self {
+        self.patch_flag = PatchFlag::DynamicProps(names);
+        self
+    }
+
+    /// Point `node_ref` at the node this vnode is rendered to
+    pub fn node_ref(mut self, node_ref: &NodeRef) -> Self {
+        self.node_ref = Some(node_ref.clone());
+        self
+    }
+
     /// Set text content (for text nodes)
     pub fn set_text(mut self, content: &str) -> Self {
         self.text_content = Some(content.to_string());
         self
     }
+
+    /// Set a prop from a JS value; see `PropValue::from_js` for how values
+    /// are converted. `null` or `undefined` removes the prop.
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen(js_name = set_prop)]
+    pub fn set_prop_js(mut self, key: &str, value: JsValue) -> Result<VNode, JsValue> {
+        match PropValue::from_js(key, &value).map_err(|err| js_sys::TypeError::new(&err))? {
+            Some(value) => Ok(self.prop(key, value)),
+            None => {
+                self.props.remove(prop_name(key));
+                Ok(self)
+            }
+        }
+    }
+
+    /// Whether `other` describes the same tree; see `PartialEq for VNode`
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen(js_name = equals)]
+    pub fn equals_js(&self, other: &VNode) -> bool {
+        self == other
+    }
+
+    /// The tree pretty-printed as HTML-like markup; see `Display for VNode`
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_string_js(&self) -> String {
+        self.to_string()
+    }
+
+    /// Handle `event` (e.g. `"click"`) with a JS function, called with the
+    /// browser event
+    ///
+    /// An exception it throws is rethrown from the DOM listener once the
+    /// event was dispatched.
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen(js_name = on)]
+    pub fn on_js(self, event: &str, handler: js_sys::Function) -> Self {
+        self.on(event, move |event: &Event| {
+            let raw = event.raw().map(JsValue::from).unwrap_or(JsValue::UNDEFINED);
+            if let Err(err) = handler.call1(&JsValue::NULL, &raw) {
+                report_js_error(err);
+            }
+        })
+    }
+}
+
+impl VNode {
+    /// Create a node of a kind other than element, without a tag
+    fn special(kind: NodeKind) -> VNode {
+        VNode {
+            kind,
+            ..VNode::element("")
+        }
+    }
+
+    /// Create a node mounting component `C` with `props`
+    ///
+    /// Children added with `add_child` are passed to `Component::render`.
+    pub fn component<C: Component>(props: C::Props) -> VNode {
+        VNode {
+            component: Some(ComponentNode {
+                spec: ComponentSpec::new::<C>(props),
+                instance: None,
+            }),
+            ..VNode::element(std::any::type_name::<C>())
+        }
+    }
+
+    /// Create a portal: its children are rendered into `target` while it
+    /// stays in place in the tree
+    ///
+    /// The children are mounted in a `div` appended to `target` and are
+    /// diffed, and unmounted, along with the portal. Components inside
+    /// belong to the tree the portal is in, and events bubbling out of the
+    /// children reach the handlers of the portal's ancestors. Only an empty
+    /// comment marks the portal's position; server renderers write nothing
+    /// else, and the children are mounted when hydrating.
+    pub fn portal(target: impl Into<PortalTarget>) -> VNode {
+        VNode {
+            portal: Some(Portal {
+                target: target.into(),
+                container: None,
+            }),
+            ..VNode::special(NodeKind::Portal)
+        }
+    }
+
+    /// Create a boundary showing `fallback` until `content` resolves
+    ///
+    /// Server renderers wait for nothing: `render_to_string` writes the
+    /// content if it is already ready and the fallback otherwise, while
+    /// `render_to_stream` sends the fallback first and streams the content
+    /// in once the future completes. The client renders the fallback.
+    pub fn async_boundary(
+        fallback: VNode,
+        content: impl Future<Output = VNode> + 'static,
+    ) -> VNode {
+        VNode {
+            boundary: Some(AsyncBoundary::new(content)),
+            ..VNode::fragment().add_child(fallback)
+        }
+    }
+
+    /// Create a boundary showing `fallback` while a `Resource` read by
+    /// `content` is still pending
+    ///
+    /// The content is rendered, components included, into a detached
+    /// element and moved in place of the fallback once every resource it
+    /// read resolved; the mount hooks of its components run then. After
+    /// that the content stays, even if it reads pending resources again.
+    /// `render_to_string` writes the content if nothing is pending and the
+    /// fallback otherwise, while `render_to_stream` streams the content in
+    /// once its resources resolved. Hydrating adopts the server-rendered
+    /// content as is, or the fallback while the content is pending on the
+    /// client too.
+    pub fn suspense(fallback: VNode, content: VNode) -> VNode {
+        VNode {
+            suspense: Some(SuspenseNode {
+                fallback: Box::new(fallback),
+                instance: None,
+            }),
+            ..VNode::special(NodeKind::Suspense).add_child(content)
+        }
+    }
+
+    /// Create a boundary showing the fallback built by `fallback` once
+    /// `content` fails to render
+    ///
+    /// Rendering fails when a component beneath panics, or the backend
+    /// rejects a patch, e.g. an element with an invalid tag name. The
+    /// failed content is removed, its components unmounted, and the
+    /// fallback is rendered in its place from the error and an
+    /// `ErrorReset`, which remounts the content when called. The fallback
+    /// stays until then, rebuilt with each render of the boundary; its own
+    /// failures reach the boundary around it. Without a boundary, failures
+    /// end the render as before. Panics are only caught where they unwind,
+    /// so not on wasm, which aborts. Server renderers write the fallback
+    /// between `<!--eb-->` and `<!--/eb-->` markers when the content
+    /// panics or has an element with an invalid tag name, which is written
+    /// as an empty comment outside of boundaries; hydrating renders the
+    /// content again in its place.
+    pub fn error_boundary(
+        fallback: impl Fn(&RenderError, ErrorReset) -> VNode + 'static,
+        content: VNode,
+    ) -> VNode {
+        VNode {
+            error_boundary: Some(ErrorBoundaryNode {
+                fallback: Rc::new(fallback),
+                instance: None,
+            }),
+            ..VNode::special(NodeKind::ErrorBoundary).add_child(content)
+        }
+    }
+
+    /// Animate the element as it is inserted and removed, see `Transition`
+    ///
+    /// Every insertion by a render enters, the first one included; hydrated
+    /// elements do not. A removed element stays in place, inert, until its
+    /// leave phase ends, while its former siblings are diffed without it.
+    /// Mounting a keyed element where one with the same key is still
+    /// leaving removes that one right away and enters the new one.
+    pub fn transition(mut self, transition: Transition) -> Self {
+        self.transition = Some(TransitionNode {
+            transition: Rc::new(transition),
+            run: None,
+        });
+        self
+    }
+
+    /// Set a string attribute on the node
+    pub fn set_prop(self, key: &str, value: &str) -> Self {
+        self.prop(key, value)
+    }
+
+    /// Set a typed prop, replacing any previous value
+    ///
+    /// `value`, `checked` and similar props are assigned as DOM properties
+    /// rather than attributes.
+    pub fn prop(mut self, key: &str, value: impl Into<PropValue>) -> Self {
+        let key = prop_name(key);
+        self.props
+            .insert(key.to_string(), PropValue::normalize(key, value.into()));
+        self
+    }
+
+    /// Handle `event` (e.g. `"click"`) on this element
+    ///
+    /// Setting a handler again for the same event replaces it.
+    pub fn on(mut self, event: &str, handler: impl Fn(&Event) + 'static) -> Self {
+        self.handlers
+            .insert(event.to_string(), EventHandler::new(handler));
+        self
+    }
+}
+
+/// What a vnode renders as
+///
+/// Kept apart from the tag, so no tag name passed to `VNode::element` makes
+/// an element one of the other kinds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum NodeKind {
+    /// An element, or a component when `VNode::component` is set
+    Element,
+    Text,
+    Comment,
+    /// Children placed directly into the parent, followed by an anchor
+    Fragment,
+    Portal,
+    Suspense,
+    ErrorBoundary,
+}
+
+/// What a re-render may change about a vnode, as declared with
+/// `VNode::mark_static` and `VNode::dynamic_props` or by the template
+/// compiler
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) enum PatchFlag {
+    /// Anything
+    #[default]
+    Full,
+    /// Only the named props, besides handlers and children
+    DynamicProps(Vec<String>),
+    /// Nothing in the whole subtree, for re-renders of the same static
+    /// instance, identified by the id `mark_static` gave it
+    Static(u64),
+}
+
+thread_local! {
+    static NEXT_STATIC_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Id for a new static instance
+pub(crate) fn next_static_id() -> u64 {
+    NEXT_STATIC_ID.with(|next| {
+        let id = next.get();
+        next.set(id + 1);
+        id
+    })
+}
+
+/// `None` renders as an empty placeholder
+impl From<Option<VNode>> for VNode {
+    fn from(vnode: Option<VNode>) -> Self {
+        vnode.unwrap_or_else(VNode::empty)
+    }
+}
+
+/// Canonical name of a prop, mapping JS-style `className` to `class`
+fn prop_name(key: &str) -> &str {
+    match key {
+        "className" => "class",
+        key => key,
+    }
 }
 
 /// Virtual DOM renderer with efficient diffing
+///
+/// Diffing is done by a platform-independent `Reconciler` and the resulting
+/// patches are applied through a `DomBackend`: the browser DOM on wasm, an
+/// in-memory `MockDom` by default on native builds.
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub struct Renderer {
-    #[cfg(target_arch = "wasm32")]
-    document: Document,
+    reconciler: Rc<RefCell<Reconciler>>,
+    backend: Rc<RefCell<Box<dyn DomBackend>>>,
     #[cfg(not(target_arch = "wasm32"))]
-    _phantom: PhantomData<()>,
+    root: NodeId,
+    /// Root id assigned to each container rendered into
+    #[cfg(target_arch = "wasm32")]
+    containers: RefCell<Vec<(Element, NodeId)>>,
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -98,50 +636,324 @@ impl Renderer {
         let window = window().unwrap();
         let document = window.document().unwrap();
 
-        Ok(Renderer { document })
+        let (reconciler, backend) = connect(Reconciler::new(), Box::new(WebDom::new(document)));
+        Ok(Renderer {
+            reconciler,
+            backend,
+            containers: RefCell::new(Vec::new()),
+        })
     }
 
     /// Render a virtual node to a container element
+    ///
+    /// The first render into a container replaces its content. Later renders
+    /// diff against the previous tree and only touch what changed, so focus,
+    /// scroll position and input state survive.
     pub fn render(&self, vnode: &VNode, container: &Element) -> Result<(), JsValue> {
-        // Clear container
-        container.set_inner_html("");
+        let root = self.container_id(container, true)?;
+        // Effects triggered while rendering run once the render is done
+        batch(|| {
+            let patches = self.reconciler.borrow_mut().render(root, vnode);
+            self.apply(&patches)?;
+            flush_components(&self.reconciler, &self.backend)?;
+            Ok(())
+        })
+    }
 
-        // Create and append the element
-        let element = self.create_element(vnode)?;
-        container.append_child(&element)?;
+    /// Listen for events once per container instead of on every node
+    ///
+    /// Applies to containers first rendered into afterwards.
+    pub fn set_event_delegation(&self, enabled: bool) {
+        self.reconciler.borrow_mut().set_event_delegation(enabled);
+    }
+
+    /// Adopt the server-rendered content of a container as the first render
+    /// of `vnode`, attaching event handlers without recreating elements
+    ///
+    /// Mismatches are logged in debug builds. They fail the hydration
+    /// unless recovery is enabled with `set_hydration_recovery`.
+    pub fn hydrate(&self, vnode: &VNode, container: &Element) -> Result<(), JsValue> {
+        let root = self.container_id(container, false)?;
+        batch(|| {
+            let existing = self.backend.borrow().existing_children(root)?;
+            let patches = hydrate(&self.reconciler, root, vnode, &existing)?;
+            self.apply(&patches)?;
+            flush_components(&self.reconciler, &self.backend)?;
+            Ok(())
+        })
+    }
 
+    /// Re-render the parts of a container that do not match when
+    /// hydrating, instead of failing
+    pub fn set_hydration_recovery(&self, enabled: bool) {
+        self.reconciler.borrow_mut().set_hydration_recovery(enabled);
+    }
+
+    /// Remove whatever was rendered into a container
+    pub fn unmount(&self, container: &Element) -> Result<(), JsValue> {
+        let mut containers = self.containers.borrow_mut();
+        if let Some(index) = containers
+            .iter()
+            .position(|(element, _)| element == container)
+        {
+            let (_, root) = containers.remove(index);
+            drop(containers);
+            let patches = self.reconciler.borrow_mut().unmount(root);
+            self.apply(&patches)?;
+        }
         Ok(())
     }
+}
 
-    /// Create a DOM element from a virtual node
-    fn create_element(&self, vnode: &VNode) -> Result<Node, JsValue> {
-        if vnode.node_type == "TEXT" {
-            let text_node = self.document.create_text_node(
-                &vnode.text_content.clone().unwrap_or_default()
-            );
-            return Ok(text_node.into());
+#[cfg(target_arch = "wasm32")]
+impl Renderer {
+    /// Root id for `container`, registering it on first use, when its
+    /// content is cleared unless it is to be hydrated
+    fn container_id(&self, container: &Element, clear: bool) -> Result<NodeId, RenderError> {
+        let mut containers = self.containers.borrow_mut();
+        if let Some((_, root)) = containers.iter().find(|(element, _)| element == container) {
+            return Ok(*root);
         }
 
-        let element = self.document.create_element(&vnode.node_type)?;
+        if clear {
+            container.set_inner_html("");
+        }
+
+        let root = self.reconciler.borrow_mut().create_root();
+        // Rendering into an `<svg>` creates SVG children
+        let namespace = Namespace::from_uri(container.namespace_uri().as_deref());
+        self.reconciler
+            .borrow_mut()
+            .set_namespace(root, namespace.of_children(&container.local_name()));
+        self.backend
+            .borrow_mut()
+            .adopt(root, container.clone().into())?;
+        containers.push((container.clone(), root));
+        Ok(root)
+    }
+}
+
+impl Renderer {
+    fn apply(&self, patches: &[Patch]) -> Result<Vec<Patch>, RenderError> {
+        apply(&self.reconciler, &self.backend, patches)
+    }
+}
 
-        // Set properties
-        for (key, value) in &vnode.props {
-            if key.starts_with("on") {
-                // Event handlers would be set up here
-                // For now, we'll skip this
-            } else {
-                element.set_attribute(key, value)?;
+/// Hydrate `root`, logging mismatches in debug builds
+fn hydrate(
+    reconciler: &RefCell<Reconciler>,
+    root: NodeId,
+    vnode: &VNode,
+    existing: &[ExistingNode],
+) -> Result<Vec<Patch>, RenderError> {
+    let result = reconciler.borrow_mut().hydrate(root, vnode, existing);
+    if cfg!(debug_assertions) {
+        let mismatches = match &result {
+            Ok((_, mismatches)) | Err(RenderError::HydrationMismatch(mismatches)) => {
+                mismatches.as_slice()
             }
+            Err(_) => &[],
+        };
+        for mismatch in mismatches {
+            console_log!("hydration mismatch at {}", mismatch);
         }
+    }
+    result.map(|(patches, _)| patches)
+}
+
+/// Share the reconciler and backend with the callback applying component
+/// updates as soon as they happen
+fn connect(
+    reconciler: Reconciler,
+    backend: Box<dyn DomBackend>,
+) -> (Rc<RefCell<Reconciler>>, SharedBackend) {
+    let reconciler = Rc::new(RefCell::new(reconciler));
+    let backend = Rc::new(RefCell::new(backend));
 
-        // Add children
-        for child in &vnode.children {
-            let child_element = self.create_element(child)?;
-            element.append_child(&child_element)?;
+    let weak_reconciler = Rc::downgrade(&reconciler);
+    let weak_backend = Rc::downgrade(&backend);
+    reconciler.borrow_mut().on_component_update(move || {
+        let (Some(reconciler), Some(backend)) = (weak_reconciler.upgrade(), weak_backend.upgrade())
+        else {
+            return;
+        };
+        if let Err(err) = flush_components(&reconciler, &backend) {
+            console_log!("component update failed: {}", err);
         }
+    });
 
-        Ok(element.into())
+    (reconciler, backend)
+}
+
+/// Apply the patches of components that re-rendered on their own
+///
+/// Does nothing while a render is using the reconciler or backend; the
+/// render flushes once it is done.
+fn flush_components(
+    reconciler_cell: &RefCell<Reconciler>,
+    backend_cell: &SharedBackend,
+) -> Result<Vec<Patch>, RenderError> {
+    let mut applied = Vec::new();
+    loop {
+        let (Ok(mut reconciler), Ok(backend)) = (
+            reconciler_cell.try_borrow_mut(),
+            backend_cell.try_borrow_mut(),
+        ) else {
+            return Ok(applied);
+        };
+        let patches = reconciler.update_components();
+        let effects = PassEffects::take(&mut reconciler);
+        if patches.is_empty() && effects.hooks.is_empty() && effects.transitions.is_empty() {
+            return Ok(applied);
+        }
+        drop(reconciler);
+        drop(backend);
+        let failed = apply_pass(backend_cell, &patches, effects)?;
+        applied.extend(patches);
+        applied.extend(show_fallbacks(reconciler_cell, backend_cell, failed)?);
+    }
+}
+
+/// Apply the patches of a reconciler pass with what else it left to do,
+/// returning the patches applied after them to show the fallback of error
+/// boundaries the backend failed
+fn apply(
+    reconciler: &RefCell<Reconciler>,
+    backend: &SharedBackend,
+    patches: &[Patch],
+) -> Result<Vec<Patch>, RenderError> {
+    let effects = PassEffects::take(&mut reconciler.borrow_mut());
+    let failed = apply_pass(backend, patches, effects)?;
+    show_fallbacks(reconciler, backend, failed)
+}
+
+/// Replace the content of error boundaries the backend failed with their
+/// fallback, until no fallback fails, and return the patches applied
+fn show_fallbacks(
+    reconciler: &RefCell<Reconciler>,
+    backend: &SharedBackend,
+    mut failed: Vec<BoundaryFailure>,
+) -> Result<Vec<Patch>, RenderError> {
+    let mut applied = Vec::new();
+    while !failed.is_empty() {
+        let (patches, effects) = {
+            let mut reconciler = reconciler.borrow_mut();
+            let patches = reconciler.fail_boundaries(failed);
+            (patches, PassEffects::take(&mut reconciler))
+        };
+        failed = apply_pass(backend, &patches, effects)?;
+        applied.extend(patches);
+    }
+    Ok(applied)
+}
+
+/// What a reconciler pass leaves to do around applying its patches
+struct PassEffects {
+    handlers: HandlerTable,
+    refs: Vec<(NodeRef, RefUpdate)>,
+    hooks: Vec<(Rc<ComponentInstance>, Lifecycle)>,
+    /// Patches rendered beneath each error boundary, innermost first
+    boundaries: Vec<(Rc<ErrorBoundaryInstance>, Range<usize>)>,
+    /// Enter and leave phases started, and moves to animate
+    transitions: Vec<TransitionStart>,
+    moves: Vec<TransitionStart>,
+    /// Elements targeted by portals for the first time
+    #[cfg(target_arch = "wasm32")]
+    targets: Vec<(NodeId, Element)>,
+}
+
+impl PassEffects {
+    fn take(reconciler: &mut Reconciler) -> Self {
+        PassEffects {
+            handlers: reconciler.handlers().clone(),
+            refs: reconciler.take_refs(),
+            hooks: reconciler.take_hooks(),
+            boundaries: reconciler.take_boundary_patches(),
+            transitions: reconciler.take_transitions(),
+            moves: reconciler.take_moves(),
+            #[cfg(target_arch = "wasm32")]
+            targets: reconciler.take_targets(),
+        }
+    }
+}
+
+/// Run unmount hooks, apply the patches, then run mount and update hooks
+/// and start transitions
+///
+/// A patch the backend rejects fails the innermost error boundary it was
+/// rendered beneath, which is returned, and the pass goes on; only the
+/// first failure of each boundary is kept. Outside any boundary it ends
+/// the pass with the error. No borrow is held while hooks run, so they can
+/// set signals or render.
+fn apply_pass(
+    backend_cell: &SharedBackend,
+    patches: &[Patch],
+    effects: PassEffects,
+) -> Result<Vec<BoundaryFailure>, RenderError> {
+    let (unmounted, hooks): (Vec<_>, Vec<_>) = effects
+        .hooks
+        .into_iter()
+        .partition(|(_, hook)| *hook == Lifecycle::Unmount);
+    for (instance, hook) in unmounted {
+        instance.run_hook(hook);
+    }
+    let mut failed: Vec<BoundaryFailure> = Vec::new();
+    let moves: Vec<_>;
+    {
+        let mut backend = backend_cell.borrow_mut();
+        #[cfg(target_arch = "wasm32")]
+        for (id, element) in effects.targets {
+            backend.adopt(id, element.into())?;
+        }
+        // Where moved elements were, for FLIP
+        moves = effects
+            .moves
+            .into_iter()
+            .filter_map(|start| {
+                let first = backend.position(start.id)?;
+                Some((start, first))
+            })
+            .collect();
+        for (index, patch) in patches.iter().enumerate() {
+            let Err(error) = backend.apply_patch(patch, &effects.handlers) else {
+                continue;
+            };
+            let boundary = effects
+                .boundaries
+                .iter()
+                .filter(|(_, range)| range.contains(&index))
+                .min_by_key(|(_, range)| range.len());
+            match boundary {
+                Some((boundary, _)) => {
+                    if !failed
+                        .iter()
+                        .any(|(failed, _)| Rc::ptr_eq(failed, boundary))
+                    {
+                        failed.push((Rc::clone(boundary), error));
+                    }
+                }
+                None => return Err(error),
+            }
+        }
+        resolve_refs(effects.refs, backend.as_ref());
+    }
+    for (instance, hook) in hooks {
+        instance.run_hook(hook);
+    }
+    transition::play(effects.transitions, moves, backend_cell);
+    Ok(failed)
+}
+
+/// Update the refs changed by the patches just applied
+fn resolve_refs(refs: Vec<(NodeRef, RefUpdate)>, backend: &dyn DomBackend) {
+    for (node_ref, update) in refs {
+        node_ref.update(update);
+        #[cfg(target_arch = "wasm32")]
+        node_ref.set_node(node_ref.id().and_then(|id| backend.dom_node(id)));
     }
+    // Native refs only hold the node id
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = backend;
 }
 
 /// Create a virtual element node (JavaScript-compatible API)
@@ -151,45 +963,108 @@ pub fn h(node_type: &str) -> VNode {
     VNode::element(node_type)
 }
 
-/// Reactive rendering helper
+/// Render the `VNode` returned by `render_fn` into `container` and keep it
+/// up to date
+///
+/// `render_fn` runs inside an effect: whenever a signal it read changes, it
+/// runs again and the new tree is diffed against the DOM.
+///
+/// Throws, without keeping the effect, if the first render fails; see
+/// `RenderHandle::last_error` for the re-renders.
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub fn reactive_render(
-    render_fn: &js_sys::Function,
-    container: &Element,
-) -> Result<(), JsValue> {
-    let renderer = Renderer::new()?;
-
-    // Create a closure that calls the render function and re-renders
-    let render_callback = Closure::wrap(Box::new(move || {
-        // Call the JavaScript render function
-        let vnode_result = render_fn.call0(&JsValue::NULL);
-
-        match vnode_result {
-            Ok(vnode_js) => {
-                // Convert JS object to VNode (simplified)
-                // In practice, we'd need proper JS interop
-                console_log!("Re-rendering...");
-            }
-            Err(e) => {
-                console_log!("Render error: {:?}", e);
-            }
+    render_fn: js_sys::Function,
+    container: Element,
+) -> Result<RenderHandle, JsValue> {
+    reactive(render_fn, container, false)
+}
+
+/// Like `reactive_render`, but the first render hydrates the
+/// server-rendered content of `container`
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn reactive_hydrate(
+    render_fn: js_sys::Function,
+    container: Element,
+) -> Result<RenderHandle, JsValue> {
+    reactive(render_fn, container, true)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn reactive(
+    render_fn: js_sys::Function,
+    container: Element,
+    hydrate: bool,
+) -> Result<RenderHandle, JsValue> {
+    let renderer = Rc::new(Renderer::new()?);
+    // Only the first render hydrates
+    let hydrating = Cell::new(hydrate);
+    let last_error = Rc::new(RefCell::new(None));
+
+    let effect = effect({
+        let renderer = Rc::clone(&renderer);
+        let container = container.clone();
+        let last_error = Rc::clone(&last_error);
+        move || {
+            let vnode = render_fn
+                .call0(&JsValue::NULL)
+                .and_then(|value| Ok(VNode::from_js(&value)?));
+            let result = vnode.and_then(|vnode| {
+                untrack(|| {
+                    if hydrating.replace(false) {
+                        renderer.hydrate(&vnode, &container)
+                    } else {
+                        renderer.render(&vnode, &container)
+                    }
+                })
+            });
+            *last_error.borrow_mut() = result.err();
         }
-    }) as Box<dyn Fn()>);
+    });
 
-    // For now, just call it once. Convert the Closure reference to a JS Function and call it.
-    let func = render_callback.as_ref().unchecked_ref::<js_sys::Function>();
-    let _ = func.call0(&JsValue::NULL);
+    let first_error = last_error.borrow_mut().take();
+    if let Some(err) = first_error {
+        effect.dispose();
+        return Err(err);
+    }
+    Ok(RenderHandle {
+        effect,
+        renderer,
+        container,
+        last_error,
+    })
+}
 
-    // In a real implementation, this would be called when signals change
-    // We'd need to integrate with the reactivity system
+/// Handle to a render kept up to date by `reactive_render` or
+/// `reactive_hydrate`
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub struct RenderHandle {
+    effect: Effect,
+    renderer: Rc<Renderer>,
+    #[cfg(target_arch = "wasm32")]
+    container: Element,
+    /// Why the latest re-render failed
+    #[cfg(not(target_arch = "wasm32"))]
+    last_error: Rc<RefCell<Option<RenderError>>>,
+    #[cfg(target_arch = "wasm32")]
+    last_error: Rc<RefCell<Option<JsValue>>>,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl RenderHandle {
+    /// Why the latest re-render failed, `undefined` once one succeeds again
+    pub fn last_error(&self) -> Option<JsValue> {
+        self.last_error.borrow().clone()
+    }
 
-    Ok(())
+    /// Stop re-rendering and remove what was rendered
+    pub fn unmount(&self) -> Result<(), JsValue> {
+        self.effect.dispose();
+        self.renderer.unmount(&self.container)
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::JsValue;
-#[cfg(target_arch = "wasm32")]
-use wasm_bindgen::closure::Closure;
-#[cfg(target_arch = "wasm32")]
-use wasm_bindgen::JsCast;
@@ -0,0 +1,633 @@
+use std::collections::BTreeMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::rc::Rc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+use std::task::{Context, Poll};
+#[cfg(not(target_arch = "wasm32"))]
+use std::task::{Wake, Waker};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{io, thread};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::backend::RenderError;
+use crate::boundary::AsyncBoundary;
+use crate::compiler::is_void_element;
+use crate::component::KeptComponents;
+use crate::error_boundary::{panic_message, ErrorReset};
+use crate::prop::{PropValue, PropertyValue};
+use crate::render::{NodeKind, VNode};
+use crate::suspense::{poll_resources, wake_on_progress, with_suspense, SuspenseState};
+
+/// Render a vnode tree to HTML, e.g. on the server
+///
+/// The markup matches what the client renders for the same tree, so it can
+/// be hydrated: fragments end with an empty `<!---->` anchor and components
+/// are created, rendered once and disposed. Props are written in name
+/// order, event handlers are skipped, `true` boolean attributes are written
+/// bare and `value`/`checked`/`selected` properties become the matching
+/// initial attribute (or the content, for a `textarea`). Text and
+/// attribute values are escaped; tags and attribute names that are not
+/// valid HTML names are dropped. Adjacent text nodes are separated by a
+/// `<!-- -->` comment so the browser parses them back apart. Async
+/// boundaries render their content if it is already available and their
+/// fallback otherwise. Suspense boundaries render their content unless a
+/// resource it reads is pending, and otherwise their fallback after a
+/// `<!--sl-->` comment telling the client so. Error boundaries render their
+/// fallback between `<!--eb-->` and `<!--/eb-->` comments if a component
+/// in their content panics.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn render_to_string(vnode: &VNode) -> String {
+    let mut writer = HtmlWriter::new(None);
+    writer.write_node(vnode);
+    writer.out
+}
+
+/// Render a vnode tree to HTML in chunks, without waiting for async or
+/// suspense boundaries
+///
+/// The first chunk is the whole page, with the fallback of every boundary
+/// that is still pending between `<!--sl:N-->` and `<!--/sl:N-->`
+/// markers. Each following chunk carries the content of one boundary, in
+/// the order they resolve, as a `<template>` and an inline script that
+/// swaps it in place of the fallback. Boundaries nested in streamed
+/// content are streamed in turn. Resources are polled whenever the stream
+/// is.
+pub fn render_to_stream(vnode: &VNode) -> HtmlStream {
+    HtmlStream {
+        root: Some(vnode.clone()),
+        pending: PendingBoundaries::default(),
+        swap_sent: false,
+    }
+}
+
+/// Chunks of HTML produced by `render_to_stream`
+///
+/// Poll it with `poll_chunk` from an async runtime, or iterate over it or
+/// use `write_to`, which block the current thread until the next boundary
+/// resolves.
+pub struct HtmlStream {
+    root: Option<VNode>,
+    pending: PendingBoundaries,
+    swap_sent: bool,
+}
+
+/// Boundaries whose fallback was written, waiting for their content
+#[derive(Default)]
+struct PendingBoundaries {
+    boundaries: Vec<(usize, Suspended)>,
+    next_id: usize,
+}
+
+impl PendingBoundaries {
+    /// Wait for `boundary`, returning the id of its markers
+    fn push(&mut self, boundary: Suspended) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.boundaries.push((id, boundary));
+        id
+    }
+}
+
+/// A boundary waiting for its content
+enum Suspended {
+    Async(AsyncBoundary),
+    /// Content of a suspense boundary, with the components rendered so far
+    Suspense {
+        content: Vec<VNode>,
+        state: Rc<SuspenseState>,
+        kept: KeptComponents,
+    },
+}
+
+impl Suspended {
+    fn is_ready(&self, cx: &mut Context<'_>) -> bool {
+        match self {
+            Suspended::Async(boundary) => boundary.poll(cx).is_ready(),
+            Suspended::Suspense { state, .. } => !state.is_pending(),
+        }
+    }
+}
+
+/// Defines `$sl(id)`, which moves the content of `<template id="sl-{id}">`
+/// in place of the fallback between the boundary markers, leaving the end
+/// marker as the empty anchor the client renders after a fragment
+const SWAP_SCRIPT: &str = "<script>function $sl(i){var t=document.getElementById(\"sl-\"+i),w=document.createTreeWalker(document,128),s,n,x;while((n=w.nextNode()))if(n.data===\"sl:\"+i){s=n;break}if(!t||!s)return;n=s.nextSibling;while(n&&n.data!==\"/sl:\"+i){x=n.nextSibling;n.remove();n=x}if(n){n.before(t.content);n.data=\"\"}s.remove();t.remove()}</script>";
+
+impl HtmlStream {
+    /// Next chunk, `Pending` while no waiting boundary has resolved, or
+    /// `None` once everything was sent
+    pub fn poll_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<String>> {
+        if let Some(root) = self.root.take() {
+            let mut writer = HtmlWriter::new(Some(&mut self.pending));
+            writer.write_node(&root);
+            return Poll::Ready(Some(writer.out));
+        }
+        if self.pending.boundaries.is_empty() {
+            return Poll::Ready(None);
+        }
+
+        // Resources resolve as their futures make progress
+        wake_on_progress(cx.waker());
+        poll_resources();
+
+        let mut index = 0;
+        while index < self.pending.boundaries.len() {
+            if !self.pending.boundaries[index].1.is_ready(cx) {
+                index += 1;
+                continue;
+            }
+            let (id, boundary) = self.pending.boundaries.remove(index);
+
+            let mut writer = HtmlWriter::new(Some(&mut self.pending));
+            match boundary {
+                Suspended::Async(boundary) => {
+                    let content = boundary.try_resolve().expect("resolved above");
+                    writer.write_node(&content);
+                }
+                Suspended::Suspense {
+                    content,
+                    state,
+                    kept,
+                } => {
+                    // Rendering it again may read resources still pending
+                    if let Err(kept) = writer.write_suspense_content(&content, &state, kept) {
+                        let boundary = Suspended::Suspense {
+                            content,
+                            state,
+                            kept,
+                        };
+                        self.pending.boundaries.insert(index, (id, boundary));
+                        index += 1;
+                        continue;
+                    }
+                }
+            }
+
+            let mut chunk = String::new();
+            if !self.swap_sent {
+                chunk.push_str(SWAP_SCRIPT);
+                self.swap_sent = true;
+            }
+            chunk.push_str(&format!("<template id=\"sl-{}\">", id));
+            chunk.push_str(&writer.out);
+            chunk.push_str(&format!("</template><script>$sl({})</script>", id));
+            return Poll::Ready(Some(chunk));
+        }
+        Poll::Pending
+    }
+
+    /// Write every chunk to `writer`, flushing after each one
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn write_to(self, mut writer: impl io::Write) -> io::Result<()> {
+        for chunk in self {
+            writer.write_all(chunk.as_bytes())?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Blocks the current thread until the next chunk is ready
+#[cfg(not(target_arch = "wasm32"))]
+impl Iterator for HtmlStream {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match self.poll_chunk(&mut cx) {
+                Poll::Ready(chunk) => return chunk,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct ThreadWaker(thread::Thread);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+struct HtmlWriter<'a> {
+    out: String,
+    /// Where pending boundaries are recorded when streaming; otherwise
+    /// only their fallback is written
+    streaming: Option<&'a mut PendingBoundaries>,
+    /// Whether the last node written was text
+    after_text: bool,
+    /// Components of the suspense boundary being written, if any
+    kept: Option<KeptComponents>,
+    /// First node the client would fail to render, e.g. an element with an
+    /// invalid tag name, for the enclosing error boundary
+    failure: Option<RenderError>,
+}
+
+impl<'a> HtmlWriter<'a> {
+    fn new(streaming: Option<&'a mut PendingBoundaries>) -> Self {
+        HtmlWriter {
+            out: String::new(),
+            streaming,
+            after_text: false,
+            kept: None,
+            failure: None,
+        }
+    }
+
+    fn write_node(&mut self, vnode: &VNode) {
+        if let Some(component) = &vnode.component {
+            let output = match &mut self.kept {
+                Some(kept) => kept.render(&component.spec, &vnode.children),
+                None => component.spec.render_once(&vnode.children),
+            };
+            self.write_node(&output);
+            return;
+        }
+        if let Some(boundary) = &vnode.boundary {
+            self.write_boundary(vnode, boundary);
+            return;
+        }
+        if vnode.suspense.is_some() {
+            self.write_suspense(vnode);
+            return;
+        }
+        if vnode.error_boundary.is_some() {
+            self.write_error_boundary(vnode);
+            return;
+        }
+        // The children of a portal are mounted by the client
+        if vnode.portal.is_some() {
+            self.write_comment("");
+            return;
+        }
+
+        match vnode.kind {
+            NodeKind::Text => self.write_text(text_of(vnode)),
+            NodeKind::Comment => self.write_comment(text_of(vnode)),
+            NodeKind::Fragment => {
+                for child in &vnode.children {
+                    self.write_node(child);
+                }
+                self.write_comment("");
+            }
+            _ => self.write_element(&vnode.node_type, vnode),
+        }
+    }
+
+    /// Content if ready, like the fragment the client renders, or else the
+    /// fallback, between markers when streaming
+    fn write_boundary(&mut self, vnode: &VNode, boundary: &AsyncBoundary) {
+        if let Some(content) = boundary.try_resolve() {
+            self.write_node(&content);
+            self.write_comment("");
+            return;
+        }
+
+        let id = self
+            .streaming
+            .as_deref_mut()
+            .map(|pending| pending.push(Suspended::Async(boundary.clone())));
+        if let Some(id) = id {
+            self.out.push_str(&format!("<!--sl:{}-->", id));
+            self.after_text = false;
+        }
+        for child in &vnode.children {
+            self.write_node(child);
+        }
+        match id {
+            Some(id) => {
+                self.out.push_str(&format!("<!--/sl:{}-->", id));
+                self.after_text = false;
+            }
+            None => self.write_comment(""),
+        }
+    }
+
+    /// Content unless a resource it reads is pending, like the fragment the
+    /// client renders, or else the fallback after a `<!--sl-->` marker, or
+    /// between markers when streaming
+    fn write_suspense(&mut self, vnode: &VNode) {
+        let state = Rc::new(SuspenseState::default());
+        let kept = match self.write_suspense_content(&vnode.children, &state, Default::default()) {
+            Ok(()) => {
+                self.write_comment("");
+                return;
+            }
+            Err(kept) => kept,
+        };
+
+        let content = vnode.children.clone();
+        let id = self.streaming.as_deref_mut().map(|pending| {
+            pending.push(Suspended::Suspense {
+                content,
+                state,
+                kept,
+            })
+        });
+        match id {
+            Some(id) => {
+                self.out.push_str(&format!("<!--sl:{}-->", id));
+                self.after_text = false;
+            }
+            None => self.write_comment(FALLBACK_MARKER),
+        }
+        if let Some(suspense) = &vnode.suspense {
+            self.write_node(&suspense.fallback);
+        }
+        match id {
+            Some(id) => {
+                self.out.push_str(&format!("<!--/sl:{}-->", id));
+                self.after_text = false;
+            }
+            None => self.write_comment(""),
+        }
+    }
+
+    /// Write the content of a suspense boundary rendering its components
+    /// with `kept`, unless a resource it reads is pending, in which case
+    /// nothing is written and `kept` is handed back
+    fn write_suspense_content(
+        &mut self,
+        content: &[VNode],
+        state: &Rc<SuspenseState>,
+        mut kept: KeptComponents,
+    ) -> Result<(), KeptComponents> {
+        kept.restart();
+        // Boundaries nested in content that is not written are dropped
+        let mut nested = self.streaming.as_deref().map(|pending| PendingBoundaries {
+            boundaries: Vec::new(),
+            next_id: pending.next_id,
+        });
+        let mut writer = HtmlWriter {
+            out: String::new(),
+            streaming: nested.as_mut(),
+            after_text: self.after_text,
+            kept: Some(kept),
+            failure: None,
+        };
+        with_suspense(Some(Rc::clone(state)), || {
+            for child in content {
+                writer.write_node(child);
+            }
+        });
+        let HtmlWriter {
+            out,
+            after_text,
+            kept,
+            failure,
+            ..
+        } = writer;
+        let kept = kept.expect("set above");
+        if state.is_pending() {
+            return Err(kept);
+        }
+
+        if let Some(failure) = failure {
+            self.fail(failure);
+        }
+        if let (Some(pending), Some(nested)) = (self.streaming.as_deref_mut(), nested) {
+            pending.boundaries.extend(nested.boundaries);
+            pending.next_id = nested.next_id;
+        }
+        self.out.push_str(&out);
+        self.after_text = after_text;
+        Ok(())
+    }
+
+    /// Content, like the fragment the client renders, or the fallback
+    /// between markers if a component in it panics or it has a node the
+    /// client fails to render
+    fn write_error_boundary(&mut self, vnode: &VNode) {
+        if let Err(error) = self.write_error_boundary_content(&vnode.children) {
+            self.write_comment(ERROR_MARKER);
+            if let Some(boundary) = &vnode.error_boundary {
+                self.write_node(&(boundary.fallback)(&error, ErrorReset::inert()));
+            }
+            self.write_comment(ERROR_END_MARKER);
+        }
+        self.write_comment("");
+    }
+
+    /// Write the content of an error boundary, unless a component in it
+    /// panics or it has a node the client fails to render, in which case
+    /// nothing is written
+    fn write_error_boundary_content(&mut self, content: &[VNode]) -> Result<(), RenderError> {
+        // Boundaries nested in content that is not written are dropped
+        let mut nested = self.streaming.as_deref().map(|pending| PendingBoundaries {
+            boundaries: Vec::new(),
+            next_id: pending.next_id,
+        });
+        let mut writer = HtmlWriter {
+            out: String::new(),
+            streaming: nested.as_mut(),
+            after_text: self.after_text,
+            kept: self.kept.take(),
+            failure: None,
+        };
+        let written = catch_unwind(AssertUnwindSafe(|| {
+            for child in content {
+                writer.write_node(child);
+            }
+        }));
+        let HtmlWriter {
+            out,
+            after_text,
+            kept,
+            failure,
+            ..
+        } = writer;
+        self.kept = kept;
+        if let Err(panic) = written {
+            return Err(RenderError::Panic(panic_message(panic.as_ref())));
+        }
+        if let Some(failure) = failure {
+            return Err(failure);
+        }
+
+        if let (Some(pending), Some(nested)) = (self.streaming.as_deref_mut(), nested) {
+            pending.boundaries.extend(nested.boundaries);
+            pending.next_id = nested.next_id;
+        }
+        self.out.push_str(&out);
+        self.after_text = after_text;
+        Ok(())
+    }
+
+    /// Record that the client fails to render the content being written,
+    /// so the error boundary around it writes its fallback instead
+    fn fail(&mut self, error: RenderError) {
+        self.failure.get_or_insert(error);
+    }
+
+    fn write_element(&mut self, tag: &str, vnode: &VNode) {
+        // The client fails to create it; keep its place like a placeholder
+        if !is_valid_name(tag) {
+            self.fail(RenderError::Backend(format!("invalid tag name {:?}", tag)));
+            self.write_comment("");
+            return;
+        }
+
+        self.out.push('<');
+        self.out.push_str(tag);
+        let (attributes, content) = server_attributes(tag, &vnode.props);
+        for (name, value) in attributes {
+            self.out.push(' ');
+            self.out.push_str(name);
+            if let Some(value) = value {
+                self.out.push_str("=\"");
+                self.out.push_str(&escape_attribute(&value));
+                self.out.push('"');
+            }
+        }
+        self.out.push('>');
+        self.after_text = false;
+
+        if is_void_element(tag) {
+            return;
+        }
+
+        if let Some(content) = content {
+            self.out.push_str(&escape_text(&content));
+        } else if matches!(tag, "script" | "style") {
+            // Raw text: entities are not decoded, so only a closing tag matters
+            for child in &vnode.children {
+                if child.kind == NodeKind::Text {
+                    self.out.push_str(&text_of(child).replace("</", "<\\/"));
+                }
+            }
+        } else {
+            for child in &vnode.children {
+                self.write_node(child);
+            }
+        }
+
+        self.out.push_str("</");
+        self.out.push_str(tag);
+        self.out.push('>');
+        self.after_text = false;
+    }
+
+    /// Adjacent text nodes are separated by a `<!-- -->` comment, or the
+    /// browser would parse them back as a single node
+    fn write_text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if self.after_text {
+            self.out.push_str(TEXT_SEPARATOR);
+        }
+        self.out.push_str(&escape_text(text));
+        self.after_text = true;
+    }
+
+    fn write_comment(&mut self, text: &str) {
+        self.out.push_str("<!--");
+        // `--` could end the comment early
+        self.out.push_str(&text.replace("--", "- -"));
+        self.out.push_str("-->");
+        self.after_text = false;
+    }
+}
+
+/// Comment separating adjacent text nodes in server-rendered markup
+pub(crate) const TEXT_SEPARATOR: &str = "<!-- -->";
+
+/// Comment preceding the fallback of a pending suspense boundary
+const FALLBACK_MARKER: &str = "sl";
+
+/// Comments around the fallback of an error boundary whose content panicked
+pub(crate) const ERROR_MARKER: &str = "eb";
+pub(crate) const ERROR_END_MARKER: &str = "/eb";
+
+/// Whether a comment starts the server-rendered fallback of a boundary,
+/// written by `render_to_string` or still waiting for streamed content
+pub(crate) fn is_fallback_marker(text: &str) -> bool {
+    text == FALLBACK_MARKER || text.starts_with("sl:")
+}
+
+/// Attributes an element is server-rendered with, in name order and `None`
+/// for bare boolean ones, and the content a `textarea` gets from `value`
+///
+/// Names that are not valid HTML are skipped.
+pub(crate) fn server_attributes<'a>(
+    tag: &str,
+    props: &'a BTreeMap<String, PropValue>,
+) -> (Vec<(&'a str, Option<String>)>, Option<String>) {
+    let mut attributes = Vec::new();
+    let mut content = None;
+    for (name, value) in props {
+        if !is_valid_name(name) {
+            continue;
+        }
+        match value {
+            PropValue::Style(style) => {
+                if style.is_empty() {
+                    continue;
+                }
+                let declarations = style
+                    .iter()
+                    .map(|(name, value)| format!("{}: {};", name, value))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                attributes.push((name.as_str(), Some(declarations)));
+            }
+            PropValue::Property(property) => match (name.as_str(), property) {
+                ("value", value) if tag == "textarea" => content = Some(property_string(value)),
+                ("value", value) => attributes.push((name.as_str(), Some(property_string(value)))),
+                ("checked" | "selected" | "muted", PropertyValue::Bool(true)) => {
+                    attributes.push((name.as_str(), None))
+                }
+                _ => {}
+            },
+            PropValue::Bool(true) => attributes.push((name.as_str(), None)),
+            value => {
+                if let Some(value) = value.attribute_value() {
+                    attributes.push((name.as_str(), Some(value)));
+                }
+            }
+        }
+    }
+    (attributes, content)
+}
+
+fn text_of(vnode: &VNode) -> &str {
+    vnode.text_content.as_deref().unwrap_or_default()
+}
+
+fn property_string(value: &PropertyValue) -> String {
+    match value {
+        PropertyValue::Str(value) => value.clone(),
+        PropertyValue::Bool(value) => value.to_string(),
+        PropertyValue::Number(value) => value.to_string(),
+    }
+}
+
+/// Whether `name` can be written as a tag or attribute name as is
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.chars().any(|c| {
+            c.is_whitespace() || c.is_control() || matches!(c, '"' | '\'' | '<' | '>' | '/' | '=')
+        })
+}
+
+pub(crate) fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub(crate) fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
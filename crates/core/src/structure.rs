@@ -0,0 +1,501 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::portal::PortalTarget;
+use crate::prop::{PropValue, PropertyValue};
+use crate::render::{NodeKind, VNode};
+
+/// Vnodes are equal when they describe the same tree
+///
+/// Props compare by value whatever order they were set in, and children by
+/// position. Handlers only count by the events they handle, components by
+/// their type and props, and error boundaries and async boundaries by
+/// being one. Mounted state, refs, patch flags and transitions are left
+/// out, so a rendered tree equals the tree it was rendered from.
+impl PartialEq for VNode {
+    fn eq(&self, other: &Self) -> bool {
+        same_kind(self, other)
+            && self.key == other.key
+            && self.text_content == other.text_content
+            && self.props == other.props
+            && self.handlers.keys().eq(other.handlers.keys())
+            && match (&self.component, &other.component) {
+                (Some(a), Some(b)) => a.spec.same_as(&b.spec),
+                _ => true,
+            }
+            && self.suspense.as_ref().map(|suspense| &suspense.fallback)
+                == other.suspense.as_ref().map(|suspense| &suspense.fallback)
+            && portal_target(self) == portal_target(other)
+            && self.children == other.children
+    }
+}
+
+impl Eq for VNode {}
+
+/// Hashes what `PartialEq` compares, except component props, which only
+/// need to be equal
+impl Hash for VNode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+        self.node_type.hash(state);
+        self.key.hash(state);
+        self.text_content.hash(state);
+        self.props.hash(state);
+        self.handlers.len().hash(state);
+        for event in self.handlers.keys() {
+            event.hash(state);
+        }
+        self.component
+            .as_ref()
+            .map(|component| component.spec.type_id())
+            .hash(state);
+        self.suspense
+            .as_ref()
+            .map(|suspense| &suspense.fallback)
+            .hash(state);
+        self.boundary.is_some().hash(state);
+        self.error_boundary.is_some().hash(state);
+        // Elements cannot be hashed, only the ids of backend nodes
+        match portal_target(self) {
+            Some(PortalTarget::Node(id)) => Some(id).hash(state),
+            _ => None::<&crate::patch::NodeId>.hash(state),
+        }
+        self.children.hash(state);
+    }
+}
+
+/// Pretty-prints the tree as indented HTML-like markup, one node per line
+///
+/// Keys and props come first, in name order, then the handled events as
+/// `on:click`. Text is quoted, DOM properties are written `.value={"a"}`,
+/// and other values than strings go in braces. Components show their type
+/// name, and other special nodes a `#` name like `<#suspense>`, whose
+/// fallback comes first as `<#fallback>`. The output is stable, e.g. for
+/// snapshot tests:
+///
+/// ```text
+/// <ul class="todos">
+///   <li key="1" on:click>
+///     "Write docs"
+///   </li>
+/// </ul>
+/// ```
+impl fmt::Display for VNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_node(f, self, 0)
+    }
+}
+
+fn write_node(f: &mut fmt::Formatter<'_>, vnode: &VNode, depth: usize) -> fmt::Result {
+    let indent = "  ".repeat(depth);
+    if vnode.component.is_none() {
+        let text = vnode.text_content.as_deref().unwrap_or_default();
+        match vnode.kind {
+            NodeKind::Text => return write!(f, "{}{:?}", indent, text),
+            NodeKind::Comment => return write!(f, "{}<!--{}-->", indent, text),
+            _ => {}
+        }
+    }
+
+    let name = display_name(vnode);
+    write!(f, "{}<{}", indent, name)?;
+    if let Some(key) = &vnode.key {
+        write!(f, " key={:?}", key)?;
+    }
+    if let Some(target) = portal_target(vnode) {
+        write!(f, " target={{{:?}}}", target)?;
+    }
+    for (name, value) in &vnode.props {
+        match value {
+            PropValue::Bool(true) => write!(f, " {}", name)?,
+            PropValue::Property(_) => write!(f, " .{}={}", name, value_source(value))?,
+            _ => write!(f, " {}={}", name, value_source(value))?,
+        }
+    }
+    for event in vnode.handlers.keys() {
+        write!(f, " on:{}", event)?;
+    }
+
+    let fallback = vnode.suspense.as_ref().map(|suspense| &suspense.fallback);
+    if vnode.children.is_empty() && fallback.is_none() {
+        return write!(f, " />");
+    }
+    write!(f, ">")?;
+    if let Some(fallback) = fallback {
+        write!(f, "\n{}  <#fallback>\n", indent)?;
+        write_node(f, fallback, depth + 2)?;
+        write!(f, "\n{}  </#fallback>", indent)?;
+    }
+    for child in &vnode.children {
+        writeln!(f)?;
+        write_node(f, child, depth + 1)?;
+    }
+    write!(f, "\n{}</{}>", indent, name)
+}
+
+/// How a prop value is written, e.g. `"a b"` or `{3}`
+fn value_source(value: &PropValue) -> String {
+    match value {
+        PropValue::Str(value) => format!("{:?}", value),
+        PropValue::Bool(value) => format!("{{{}}}", value),
+        PropValue::Number(value) => format!("{{{}}}", value),
+        PropValue::Classes(classes) => {
+            format!(
+                "{:?}",
+                classes.iter().cloned().collect::<Vec<_>>().join(" ")
+            )
+        }
+        PropValue::Style(style) => {
+            let declarations: Vec<String> = style
+                .iter()
+                .map(|(name, value)| format!("{}: {};", name, value))
+                .collect();
+            format!("{:?}", declarations.join(" "))
+        }
+        PropValue::Property(PropertyValue::Str(value)) => format!("{{{:?}}}", value),
+        PropValue::Property(PropertyValue::Bool(value)) => format!("{{{}}}", value),
+        PropValue::Property(PropertyValue::Number(value)) => format!("{{{}}}", value),
+    }
+}
+
+/// Short name of a vnode in paths, e.g. `li`, `#text` or `<Counter>`
+pub(crate) fn vnode_label(vnode: &VNode) -> String {
+    if vnode.component.is_some() {
+        let name = vnode.node_type.rsplit("::").next().unwrap_or_default();
+        return format!("<{}>", name);
+    }
+    match vnode.kind {
+        NodeKind::Element => vnode.node_type.clone(),
+        NodeKind::Text => "#text".to_string(),
+        NodeKind::Comment => "#comment".to_string(),
+        NodeKind::Fragment => "#fragment".to_string(),
+        NodeKind::Portal => "#portal".to_string(),
+        NodeKind::Suspense => "#suspense".to_string(),
+        NodeKind::ErrorBoundary => "#error-boundary".to_string(),
+    }
+}
+
+/// Name of a vnode's tag in `Display` and differences
+fn display_name(vnode: &VNode) -> String {
+    if vnode.boundary.is_some() {
+        return "#async".to_string();
+    }
+    let label = vnode_label(vnode);
+    match label.strip_prefix('<') {
+        Some(component) => component.trim_end_matches('>').to_string(),
+        None => label,
+    }
+}
+
+/// What a vnode is, for differences, e.g. `<li key="a">` or `text "hi"`
+fn describe(vnode: &VNode) -> String {
+    let text = vnode.text_content.as_deref().unwrap_or_default();
+    if vnode.component.is_none() {
+        match vnode.kind {
+            NodeKind::Text => return format!("text {:?}", text),
+            NodeKind::Comment => return format!("comment {:?}", text),
+            _ => {}
+        }
+    }
+    match &vnode.key {
+        Some(key) => format!("<{} key={:?}>", display_name(vnode), key),
+        None => format!("<{}>", display_name(vnode)),
+    }
+}
+
+/// Whether two vnodes are the same kind of node, down to the component
+/// type
+fn same_kind(a: &VNode, b: &VNode) -> bool {
+    let component_type = |vnode: &VNode| {
+        vnode
+            .component
+            .as_ref()
+            .map(|component| component.spec.type_id())
+    };
+    a.kind == b.kind
+        && a.node_type == b.node_type
+        && component_type(a) == component_type(b)
+        && a.boundary.is_some() == b.boundary.is_some()
+        && a.suspense.is_some() == b.suspense.is_some()
+        && a.error_boundary.is_some() == b.error_boundary.is_some()
+        && a.portal.is_some() == b.portal.is_some()
+}
+
+fn portal_target(vnode: &VNode) -> Option<&PortalTarget> {
+    vnode.portal.as_ref().map(|portal| &portal.target)
+}
+
+/// One way two vnode trees differ, see `VNode::differences`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VNodeDifference {
+    /// Location in the trees, e.g. `ul > li[key="a"] > #text[0]`
+    pub path: String,
+    pub kind: DifferenceKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DifferenceKind {
+    /// Different kinds of nodes are at the same place, e.g. `<div>` and
+    /// `text "hi"`, so nothing beneath was compared
+    Node { old: String, new: String },
+    /// The key changed; `None` means there is none
+    Key {
+        old: Option<String>,
+        new: Option<String>,
+    },
+    /// A text or comment node holds different text
+    Text { old: String, new: String },
+    /// A prop changed, written as in `Display`; `None` means it is absent
+    Prop {
+        name: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    /// An event is handled in only one of the trees
+    Handler { event: String, added: bool },
+    /// A component is given props that are not equal
+    ComponentProps,
+    /// A portal renders into another target
+    PortalTarget { old: String, new: String },
+    /// A child is only in the new tree
+    Added { node: String },
+    /// A child is only in the old tree
+    Removed { node: String },
+    /// Keyed children in both trees come in another order
+    Order { old: Vec<String>, new: Vec<String> },
+}
+
+impl fmt::Display for VNodeDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.path)?;
+        match &self.kind {
+            DifferenceKind::Node { old, new } => write!(f, "{} became {}", old, new),
+            DifferenceKind::Key { old, new } => write!(
+                f,
+                "key changed from {} to {}",
+                describe_value(old),
+                describe_value(new)
+            ),
+            DifferenceKind::Text { old, new } => {
+                write!(f, "text changed from {:?} to {:?}", old, new)
+            }
+            DifferenceKind::Prop {
+                name,
+                old: None,
+                new: Some(new),
+            } => write!(f, "prop `{}` added as {}", name, new),
+            DifferenceKind::Prop {
+                name,
+                old: Some(old),
+                new: None,
+            } => write!(f, "prop `{}` removed, was {}", name, old),
+            DifferenceKind::Prop { name, old, new } => write!(
+                f,
+                "prop `{}` changed from {} to {}",
+                name,
+                old.as_deref().unwrap_or("absent"),
+                new.as_deref().unwrap_or("absent")
+            ),
+            DifferenceKind::Handler { event, added: true } => {
+                write!(f, "handler for `{}` added", event)
+            }
+            DifferenceKind::Handler {
+                event,
+                added: false,
+            } => write!(f, "handler for `{}` removed", event),
+            DifferenceKind::ComponentProps => write!(f, "component props changed"),
+            DifferenceKind::PortalTarget { old, new } => {
+                write!(f, "portal target changed from {} to {}", old, new)
+            }
+            DifferenceKind::Added { node } => write!(f, "{} added", node),
+            DifferenceKind::Removed { node } => write!(f, "{} removed", node),
+            DifferenceKind::Order { old, new } => {
+                write!(f, "children reordered from {:?} to {:?}", old, new)
+            }
+        }
+    }
+}
+
+fn describe_value(value: &Option<String>) -> String {
+    match value {
+        Some(value) => format!("{:?}", value),
+        None => "none".to_string(),
+    }
+}
+
+impl VNode {
+    /// Every structural difference between this tree and `other`, which
+    /// is empty exactly when they are equal
+    ///
+    /// Children are matched by key when every child of both lists has a
+    /// distinct key, and by position otherwise. Differences display as
+    /// readable sentences, e.g. `ul > li[key="a"]: prop `class` changed
+    /// from "done" to "todo"`.
+    pub fn differences(&self, other: &VNode) -> Vec<VNodeDifference> {
+        let mut differences = Vec::new();
+        compare(&display_name(self), self, other, &mut differences);
+        differences
+    }
+}
+
+fn compare(path: &str, old: &VNode, new: &VNode, out: &mut Vec<VNodeDifference>) {
+    let mut push = |kind| {
+        out.push(VNodeDifference {
+            path: path.to_string(),
+            kind,
+        })
+    };
+    if !same_kind(old, new) {
+        push(DifferenceKind::Node {
+            old: describe(old),
+            new: describe(new),
+        });
+        return;
+    }
+    if old.key != new.key {
+        push(DifferenceKind::Key {
+            old: old.key.clone(),
+            new: new.key.clone(),
+        });
+    }
+    if old.text_content != new.text_content {
+        push(DifferenceKind::Text {
+            old: old.text_content.clone().unwrap_or_default(),
+            new: new.text_content.clone().unwrap_or_default(),
+        });
+    }
+
+    let names: BTreeSet<&String> = old.props.keys().chain(new.props.keys()).collect();
+    for name in names {
+        let (old_value, new_value) = (old.props.get(name), new.props.get(name));
+        if old_value != new_value {
+            push(DifferenceKind::Prop {
+                name: name.clone(),
+                old: old_value.map(value_source),
+                new: new_value.map(value_source),
+            });
+        }
+    }
+    let events: BTreeSet<&String> = old.handlers.keys().chain(new.handlers.keys()).collect();
+    for event in events {
+        let added = new.handlers.contains_key(event);
+        if added != old.handlers.contains_key(event) {
+            push(DifferenceKind::Handler {
+                event: event.clone(),
+                added,
+            });
+        }
+    }
+
+    if let (Some(a), Some(b)) = (&old.component, &new.component) {
+        if !a.spec.same_as(&b.spec) {
+            push(DifferenceKind::ComponentProps);
+        }
+    }
+    if portal_target(old) != portal_target(new) {
+        push(DifferenceKind::PortalTarget {
+            old: format!("{:?}", portal_target(old)),
+            new: format!("{:?}", portal_target(new)),
+        });
+    }
+    if let (Some(a), Some(b)) = (&old.suspense, &new.suspense) {
+        compare(
+            &format!("{} > #fallback", path),
+            &a.fallback,
+            &b.fallback,
+            out,
+        );
+    }
+    compare_children(path, &old.children, &new.children, out);
+}
+
+fn compare_children(path: &str, old: &[VNode], new: &[VNode], out: &mut Vec<VNodeDifference>) {
+    let (Some(old_keys), Some(new_keys)) = (distinct_keys(old), distinct_keys(new)) else {
+        for (index, (a, b)) in old.iter().zip(new).enumerate() {
+            compare(&child_path(path, b, index.to_string()), a, b, out);
+        }
+        for (index, child) in new.iter().enumerate().skip(old.len()) {
+            out.push(VNodeDifference {
+                path: child_path(path, child, index.to_string()),
+                kind: DifferenceKind::Added {
+                    node: describe(child),
+                },
+            });
+        }
+        for (index, child) in old.iter().enumerate().skip(new.len()) {
+            out.push(VNodeDifference {
+                path: child_path(path, child, index.to_string()),
+                kind: DifferenceKind::Removed {
+                    node: describe(child),
+                },
+            });
+        }
+        return;
+    };
+
+    let keyed_path = |child: &VNode, key: &str| child_path(path, child, format!("key={:?}", key));
+    for (key, index) in sorted(&old_keys) {
+        let child = &old[index];
+        match new_keys.get(key) {
+            Some(&new_index) => compare(&keyed_path(child, key), child, &new[new_index], out),
+            None => out.push(VNodeDifference {
+                path: keyed_path(child, key),
+                kind: DifferenceKind::Removed {
+                    node: describe(child),
+                },
+            }),
+        }
+    }
+    for (key, index) in sorted(&new_keys) {
+        if !old_keys.contains_key(key) {
+            out.push(VNodeDifference {
+                path: keyed_path(&new[index], key),
+                kind: DifferenceKind::Added {
+                    node: describe(&new[index]),
+                },
+            });
+        }
+    }
+
+    // Order of the children both lists have
+    let kept = |children: &[VNode], other: &HashMap<&str, usize>| -> Vec<String> {
+        children
+            .iter()
+            .filter_map(|child| child.key.clone())
+            .filter(|key| other.contains_key(key.as_str()))
+            .collect()
+    };
+    let (old_order, new_order) = (kept(old, &new_keys), kept(new, &old_keys));
+    if old_order != new_order {
+        out.push(VNodeDifference {
+            path: path.to_string(),
+            kind: DifferenceKind::Order {
+                old: old_order,
+                new: new_order,
+            },
+        });
+    }
+}
+
+/// Index of each child by key, if every child has a distinct one
+fn distinct_keys(children: &[VNode]) -> Option<HashMap<&str, usize>> {
+    let mut keys = HashMap::new();
+    for (index, child) in children.iter().enumerate() {
+        if keys.insert(child.key.as_deref()?, index).is_some() {
+            return None;
+        }
+    }
+    Some(keys)
+}
+
+/// Entries of a key index in the order of the children
+fn sorted<'a>(keys: &HashMap<&'a str, usize>) -> Vec<(&'a str, usize)> {
+    let mut entries: Vec<_> = keys.iter().map(|(key, index)| (*key, *index)).collect();
+    entries.sort_by_key(|(_, index)| *index);
+    entries
+}
+
+fn child_path(path: &str, child: &VNode, position: String) -> String {
+    format!("{} > {}[{}]", path, display_name(child), position)
+}
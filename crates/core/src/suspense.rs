@@ -0,0 +1,362 @@
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::JsCast;
+
+use crate::component::ComponentInstance;
+use crate::patch::NodeId;
+use crate::reactivity::Signal;
+use crate::render::VNode;
+
+// Thread-local suspense runtime: the boundary pending reads suspend while
+// rendering beneath one, and the futures driving resources.
+thread_local! {
+    static ACTIVE_SUSPENSE: RefCell<Option<Rc<SuspenseState>>> = const { RefCell::new(None) };
+    static TASKS: RefCell<Vec<Rc<Task>>> = const { RefCell::new(Vec::new()) };
+    static DRIVER: Arc<Driver> = Arc::new(Driver::default());
+}
+
+/// Value loaded by a future, read while rendering
+///
+/// Created with `resource`. Reading it inside a `VNode::suspense` boundary
+/// while the future is still pending makes the boundary show its fallback
+/// until the value is there. Reads are tracked like `Signal::get`, so
+/// components re-render once it resolves. Clones share the same value.
+///
+/// ```ignore
+/// struct Profile {
+///     user: Resource<User>,
+/// }
+///
+/// impl Component for Profile {
+///     type Props = u32;
+///
+///     fn create(id: &u32) -> Self {
+///         Profile { user: resource(fetch_user(*id)) }
+///     }
+///
+///     fn render(&self, _id: &u32, _children: &[VNode]) -> VNode {
+///         match self.user.read() {
+///             Some(user) => VNode::text(&user.name),
+///             None => VNode::empty(),
+///         }
+///     }
+/// }
+///
+/// let view = VNode::suspense(
+///     VNode::text("Loading..."),
+///     VNode::component::<Profile>(1),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct Resource<T: Clone + PartialEq + 'static> {
+    value: Signal<Option<T>>,
+    state: Rc<ResourceState>,
+}
+
+#[derive(Default)]
+struct ResourceState {
+    resolved: Cell<bool>,
+    /// Boundaries suspended until the value is there
+    waiting: RefCell<Vec<Weak<SuspenseState>>>,
+}
+
+/// Start loading a resource from `future`
+///
+/// The future is polled right away, so one that is already complete
+/// resolves the resource before it is first read. On wasm it is then driven
+/// from microtasks whenever it is woken; native builds drive woken futures
+/// with `poll_resources`, which server-side HTML streams call on their own.
+pub fn resource<T, F>(future: F) -> Resource<T>
+where
+    T: Clone + PartialEq + 'static,
+    F: Future<Output = T> + 'static,
+{
+    let resource = Resource {
+        value: Signal::new(None),
+        state: Rc::new(ResourceState::default()),
+    };
+    let loading = resource.clone();
+    spawn(async move {
+        let value = future.await;
+        loading.resolve(value);
+    });
+    resource
+}
+
+impl<T: Clone + PartialEq + 'static> Resource<T> {
+    /// The value once loaded, tracking the read
+    ///
+    /// While it is pending, the suspense boundary being rendered, if any,
+    /// shows its fallback until the value is there.
+    pub fn read(&self) -> Option<T> {
+        let value = self.value.get();
+        if value.is_none() {
+            if let Some(boundary) = ACTIVE_SUSPENSE.with(|active| active.borrow().clone()) {
+                self.suspend(&boundary);
+            }
+        }
+        value
+    }
+
+    /// The value once loaded, without tracking or suspending
+    pub fn peek(&self) -> Option<T> {
+        self.value.peek()
+    }
+
+    /// Whether the future has not completed yet
+    pub fn is_pending(&self) -> bool {
+        !self.state.resolved.get()
+    }
+
+    /// Keep `boundary` pending until this resource resolves
+    fn suspend(&self, boundary: &Rc<SuspenseState>) {
+        let mut waiting = self.state.waiting.borrow_mut();
+        if waiting
+            .iter()
+            .any(|waiting| std::ptr::eq(waiting.as_ptr(), Rc::as_ptr(boundary)))
+        {
+            return;
+        }
+        waiting.push(Rc::downgrade(boundary));
+        boundary.pending.set(boundary.pending.get() + 1);
+    }
+
+    fn resolve(&self, value: T) {
+        self.state.resolved.set(true);
+        self.value.set(Some(value));
+        let waiting = std::mem::take(&mut *self.state.waiting.borrow_mut());
+        for boundary in waiting.iter().filter_map(Weak::upgrade) {
+            boundary.settle();
+        }
+    }
+}
+
+/// Poll the resource futures woken since they were last polled
+///
+/// On wasm this happens on its own in a microtask; native code calls it
+/// once the futures it awaits may have made progress.
+pub fn poll_resources() {
+    loop {
+        let woken: Vec<Rc<Task>> = TASKS.with(|tasks| {
+            tasks
+                .borrow()
+                .iter()
+                .filter(|task| task.waker.woken.load(Ordering::SeqCst))
+                .cloned()
+                .collect()
+        });
+        if woken.is_empty() {
+            return;
+        }
+        for task in woken {
+            task.poll();
+        }
+        TASKS.with(|tasks| tasks.borrow_mut().retain(|task| !task.done.get()));
+    }
+}
+
+/// Wake `waker` the next time a resource future is woken, e.g. to poll
+/// the resources again from a server-side HTML stream
+pub(crate) fn wake_on_progress(waker: &Waker) {
+    DRIVER.with(|driver| *driver.waker.lock().unwrap() = Some(waker.clone()));
+}
+
+fn spawn(future: impl Future<Output = ()> + 'static) {
+    let task = Rc::new(Task {
+        future: RefCell::new(Some(Box::pin(future))),
+        waker: Arc::new(TaskWaker {
+            woken: AtomicBool::new(true),
+            driver: DRIVER.with(Arc::clone),
+        }),
+        done: Cell::new(false),
+    });
+    task.poll();
+    if !task.done.get() {
+        TASKS.with(|tasks| tasks.borrow_mut().push(task));
+    }
+}
+
+/// Future driving a resource
+struct Task {
+    future: RefCell<Option<Pin<Box<dyn Future<Output = ()>>>>>,
+    waker: Arc<TaskWaker>,
+    done: Cell<bool>,
+}
+
+impl Task {
+    fn poll(&self) {
+        self.waker.woken.store(false, Ordering::SeqCst);
+        // Released while polling: completing the future renders, which may
+        // create and poll other tasks
+        let Some(mut future) = self.future.borrow_mut().take() else {
+            return;
+        };
+        let waker = Waker::from(Arc::clone(&self.waker));
+        match future.as_mut().poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(()) => self.done.set(true),
+            Poll::Pending => *self.future.borrow_mut() = Some(future),
+        }
+    }
+}
+
+/// Marks its task as woken and passes the wake-up on to the driver
+struct TaskWaker {
+    woken: AtomicBool,
+    driver: Arc<Driver>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::SeqCst);
+        self.driver.wake();
+    }
+}
+
+/// Whoever polls the woken tasks of a thread
+#[derive(Default)]
+struct Driver {
+    waker: Mutex<Option<Waker>>,
+    /// Whether a microtask polling the tasks is queued
+    #[cfg(target_arch = "wasm32")]
+    scheduled: AtomicBool,
+}
+
+impl Driver {
+    fn wake(&self) {
+        let waker = self.waker.lock().unwrap().take();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        #[cfg(target_arch = "wasm32")]
+        if !self.scheduled.swap(true, Ordering::SeqCst) {
+            let poll = Closure::once_into_js(|| {
+                DRIVER.with(|driver| driver.scheduled.store(false, Ordering::SeqCst));
+                poll_resources();
+            });
+            if let Some(window) = web_sys::window() {
+                window.queue_microtask(poll.unchecked_ref());
+            }
+        }
+    }
+}
+
+/// Suspense boundary being rendered: counts the resources read beneath it
+/// that are still pending
+#[derive(Default)]
+pub(crate) struct SuspenseState {
+    pending: Cell<usize>,
+    /// Called once the last of them resolves
+    on_ready: RefCell<Option<Rc<dyn Fn()>>>,
+}
+
+impl SuspenseState {
+    pub(crate) fn is_pending(&self) -> bool {
+        self.pending.get() > 0
+    }
+
+    pub(crate) fn set_on_ready(&self, on_ready: Option<Rc<dyn Fn()>>) {
+        *self.on_ready.borrow_mut() = on_ready;
+    }
+
+    fn settle(&self) {
+        self.pending.set(self.pending.get() - 1);
+        if self.pending.get() == 0 {
+            let on_ready = self.on_ready.borrow().clone();
+            if let Some(on_ready) = on_ready {
+                on_ready();
+            }
+        }
+    }
+}
+
+/// Boundary rendering is currently beneath, if any
+pub(crate) fn current_suspense() -> Option<Rc<SuspenseState>> {
+    ACTIVE_SUSPENSE.with(|active| active.borrow().clone())
+}
+
+/// Run `f` with pending resource reads suspending `boundary`
+pub(crate) fn with_suspense<R>(boundary: Option<Rc<SuspenseState>>, f: impl FnOnce() -> R) -> R {
+    struct SuspenseGuard(Option<Rc<SuspenseState>>);
+    impl Drop for SuspenseGuard {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            ACTIVE_SUSPENSE.with(|active| *active.borrow_mut() = previous);
+        }
+    }
+
+    let previous = ACTIVE_SUSPENSE.with(|active| active.replace(boundary));
+    let _suspense = SuspenseGuard(previous);
+    f()
+}
+
+/// Suspense part of a `VNode`, holding the boundary once mounted
+#[derive(Clone)]
+pub(crate) struct SuspenseNode {
+    pub(crate) fallback: Box<VNode>,
+    pub(crate) instance: Option<Rc<SuspenseInstance>>,
+}
+
+impl fmt::Debug for SuspenseNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SuspenseNode")
+            .field("fallback", &self.fallback)
+            .field("mounted", &self.instance.is_some())
+            .finish()
+    }
+}
+
+/// A mounted `VNode::suspense`
+///
+/// Its content is mounted into a detached container and moved in place of
+/// the fallback once no resource read beneath it is pending.
+pub(crate) struct SuspenseInstance {
+    pub(crate) state: Rc<SuspenseState>,
+    /// Content currently rendered, with node ids assigned
+    pub(crate) content: RefCell<Vec<VNode>>,
+    /// Fallback rendered while the content is hidden
+    pub(crate) fallback: RefCell<Option<VNode>>,
+    /// Element holding the content while it is hidden
+    pub(crate) container: Cell<Option<NodeId>>,
+    /// DOM parent of the boundary and the root it was rendered into
+    pub(crate) parent: Cell<NodeId>,
+    pub(crate) root: Cell<NodeId>,
+    /// Comment marking the end of the boundary
+    pub(crate) anchor: Cell<Option<NodeId>>,
+    /// Components of the hidden content whose mount hook waits for it to
+    /// be shown
+    pub(crate) deferred: RefCell<Vec<Rc<ComponentInstance>>>,
+}
+
+impl SuspenseInstance {
+    pub(crate) fn new(parent: NodeId, root: NodeId) -> Self {
+        SuspenseInstance {
+            state: Rc::new(SuspenseState::default()),
+            content: RefCell::new(Vec::new()),
+            fallback: RefCell::new(None),
+            container: Cell::new(None),
+            parent: Cell::new(parent),
+            root: Cell::new(root),
+            anchor: Cell::new(None),
+            deferred: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Whether the content is still hidden behind the fallback
+    pub(crate) fn is_hidden(&self) -> bool {
+        self.container.get().is_some()
+    }
+}
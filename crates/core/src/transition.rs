@@ -0,0 +1,498 @@
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{closure::Closure, JsCast};
+#[cfg(target_arch = "wasm32")]
+use web_sys::{Element, Window};
+
+use crate::backend::{DomBackend, RenderError};
+use crate::component::ComponentQueue;
+use crate::patch::NodeId;
+
+/// Called when an enter or leave phase starts
+type TransitionHook = Rc<dyn Fn(TransitionEvent)>;
+
+/// Backend shared with the callbacks playing transitions after a pass
+pub(crate) type SharedBackend = Rc<RefCell<Box<dyn DomBackend>>>;
+
+/// Enter and leave animations of an element, see `VNode::transition`
+///
+/// With a name, the element goes through the CSS classes Vue uses:
+/// `{name}-enter-from` and `{name}-enter-active` are added as it is
+/// inserted, `{name}-enter-from` is swapped for `{name}-enter-to` on the
+/// next frame, and both remaining classes are removed once its CSS
+/// transitions and animations ended. Leaving works the same way with
+/// `{name}-leave-*` classes, after which the element is removed. A hook
+/// runs as its phase starts and makes it last until it calls
+/// `TransitionEvent::done` instead. Native builds have no styles to wait
+/// for, so CSS phases end right away there.
+#[derive(Clone, Default)]
+pub struct Transition {
+    name: Option<String>,
+    duration: Option<u32>,
+    flip: bool,
+    on_enter: Option<TransitionHook>,
+    on_leave: Option<TransitionHook>,
+}
+
+impl Transition {
+    /// Create a transition without classes or hooks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prefix of the CSS classes applied in each phase, e.g. `fade`
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// End CSS phases after `duration` milliseconds, instead of reading the
+    /// computed durations and delays of the element's transitions and
+    /// animations
+    pub fn duration(mut self, duration: u32) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Animate the element when it moves within a keyed list, using FLIP
+    ///
+    /// The positions of the kept children are read before and after the
+    /// patches are applied. Each child that moved is translated back to
+    /// where it was, then given the `{name}-move` class on the next frame
+    /// as the translation is dropped, so a CSS transition on `transform`
+    /// in that class plays the move. Requires a name; only backends that
+    /// report positions animate, so not `MockDom`.
+    pub fn flip(mut self) -> Self {
+        self.flip = true;
+        self
+    }
+
+    /// Run `hook` when the element is inserted
+    pub fn on_enter(mut self, hook: impl Fn(TransitionEvent) + 'static) -> Self {
+        self.on_enter = Some(Rc::new(hook));
+        self
+    }
+
+    /// Run `hook` when the element is removed; it stays in place until the
+    /// hook calls `TransitionEvent::done`
+    pub fn on_leave(mut self, hook: impl Fn(TransitionEvent) + 'static) -> Self {
+        self.on_leave = Some(Rc::new(hook));
+        self
+    }
+
+    /// Class applied at `step` of `phase`, e.g. `fade-enter-from`
+    pub(crate) fn class(&self, phase: Phase, step: &str) -> Option<String> {
+        let name = self.name.as_ref()?;
+        Some(match phase {
+            Phase::Enter => format!("{}-enter-{}", name, step),
+            Phase::Leave => format!("{}-leave-{}", name, step),
+            Phase::Move => format!("{}-move", name),
+        })
+    }
+
+    /// Whether moves in keyed lists are animated
+    pub(crate) fn flips(&self) -> bool {
+        self.flip && self.name.is_some()
+    }
+
+    fn hook(&self, phase: Phase) -> Option<TransitionHook> {
+        match phase {
+            Phase::Enter => self.on_enter.clone(),
+            Phase::Leave => self.on_leave.clone(),
+            Phase::Move => None,
+        }
+    }
+}
+
+impl fmt::Debug for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transition")
+            .field("name", &self.name)
+            .field("duration", &self.duration)
+            .field("flip", &self.flip)
+            .field("on_enter", &self.on_enter.is_some())
+            .field("on_leave", &self.on_leave.is_some())
+            .finish()
+    }
+}
+
+/// Handed to the hooks of a `Transition` as their phase starts
+#[derive(Clone)]
+pub struct TransitionEvent {
+    id: NodeId,
+    run: Rc<TransitionRun>,
+    finish: Rc<dyn Fn()>,
+    #[cfg(target_arch = "wasm32")]
+    element: Option<Element>,
+}
+
+impl TransitionEvent {
+    /// Id of the element
+    pub fn node(&self) -> NodeId {
+        self.id
+    }
+
+    /// The DOM element, e.g. to animate it with the Web Animations API
+    #[cfg(target_arch = "wasm32")]
+    pub fn element(&self) -> Option<&Element> {
+        self.element.as_ref()
+    }
+
+    /// End the phase: an entering element drops its classes, a leaving one
+    /// is removed
+    ///
+    /// Does nothing once the phase ended.
+    pub fn done(&self) {
+        (self.finish)();
+    }
+
+    /// Whether the phase is still playing
+    ///
+    /// A phase ends once `done` is called, or when it is cut short: an
+    /// entering element removed starts leaving instead, and a keyed element
+    /// re-added while leaving is removed right away.
+    pub fn is_active(&self) -> bool {
+        self.run.is_active()
+    }
+}
+
+impl fmt::Debug for TransitionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransitionEvent")
+            .field("id", &self.id)
+            .field("phase", &self.run.phase)
+            .field("active", &self.run.is_active())
+            .finish()
+    }
+}
+
+/// What a transition animates
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Phase {
+    Enter,
+    Leave,
+    Move,
+}
+
+/// One phase played on an element, until it ends or is cut short
+pub(crate) struct TransitionRun {
+    pub(crate) phase: Phase,
+    active: Cell<bool>,
+}
+
+impl TransitionRun {
+    pub(crate) fn new(phase: Phase) -> Rc<Self> {
+        Rc::new(TransitionRun {
+            phase,
+            active: Cell::new(true),
+        })
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.active.get()
+    }
+
+    /// End the phase, returning whether it was still playing
+    pub(crate) fn end(&self) -> bool {
+        self.active.replace(false)
+    }
+}
+
+/// Transition part of a `VNode`, with the phase it plays once mounted
+#[derive(Clone)]
+pub(crate) struct TransitionNode {
+    pub(crate) transition: Rc<Transition>,
+    pub(crate) run: Option<Rc<TransitionRun>>,
+}
+
+impl fmt::Debug for TransitionNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransitionNode")
+            .field("transition", &self.transition)
+            .field(
+                "playing",
+                &self
+                    .run
+                    .as_ref()
+                    .filter(|run| run.is_active())
+                    .map(|run| run.phase),
+            )
+            .finish()
+    }
+}
+
+/// Phase started by a reconciler pass, played once its patches are applied
+pub(crate) struct TransitionStart {
+    pub(crate) id: NodeId,
+    pub(crate) transition: Rc<Transition>,
+    pub(crate) run: Rc<TransitionRun>,
+    /// Told when a leave phase ends, to remove the element
+    pub(crate) queue: Weak<ComponentQueue>,
+}
+
+/// Element kept in the DOM until its leave phase ends
+pub(crate) struct Leaving {
+    /// DOM parent and key it had, to cut the phase short if the same key
+    /// is mounted there again
+    pub(crate) parent: Option<NodeId>,
+    pub(crate) key: Option<String>,
+    pub(crate) run: Rc<TransitionRun>,
+    /// Ids of its subtree, reused once it is removed
+    pub(crate) ids: Vec<NodeId>,
+}
+
+/// Play the enter and leave phases a pass started, and animate the moves
+/// it made from where the elements were before it
+pub(crate) fn play(
+    starts: Vec<TransitionStart>,
+    moves: Vec<(TransitionStart, (f64, f64))>,
+    backend: &SharedBackend,
+) {
+    for start in starts {
+        play_phase(start, backend);
+    }
+    for (start, first) in moves {
+        play_move(start, first, backend);
+    }
+}
+
+fn play_phase(start: TransitionStart, backend: &SharedBackend) {
+    let TransitionStart {
+        id,
+        transition,
+        run,
+        queue,
+    } = start;
+    let phase = run.phase;
+    let weak = Rc::downgrade(backend);
+
+    let finish: Rc<dyn Fn()> = {
+        let (transition, run, weak) = (Rc::clone(&transition), Rc::clone(&run), weak.clone());
+        Rc::new(move || {
+            if !run.end() {
+                return;
+            }
+            match phase {
+                Phase::Leave => {
+                    if let Some(queue) = queue.upgrade() {
+                        queue.push_left(id, Rc::clone(&run));
+                    }
+                }
+                _ => update(&weak, |backend| {
+                    remove_classes(backend, id, &transition, phase, &["active", "to"])
+                }),
+            }
+        })
+    };
+
+    let hook = transition.hook(phase);
+    {
+        let (run, finish, hooked) = (Rc::clone(&run), Rc::clone(&finish), hook.is_some());
+        next_frame(move || {
+            if !run.is_active() {
+                return;
+            }
+            update(&weak, |backend| {
+                remove_classes(backend, id, &transition, phase, &["from"])?;
+                if let Some(to) = transition.class(phase, "to") {
+                    backend.add_class(id, &to)?;
+                }
+                Ok(())
+            });
+            if !hooked {
+                after_transition(&weak, id, transition.duration, move || finish());
+            }
+        });
+    }
+
+    if let Some(hook) = hook {
+        hook(TransitionEvent {
+            id,
+            #[cfg(target_arch = "wasm32")]
+            element: backend
+                .try_borrow()
+                .ok()
+                .and_then(|backend| backend.dom_node(id))
+                .and_then(|node| node.dyn_into::<Element>().ok()),
+            run,
+            finish,
+        });
+    }
+}
+
+/// Translate an element that moved back to `first`, then let it slide to
+/// its new position
+fn play_move(start: TransitionStart, first: (f64, f64), backend: &SharedBackend) {
+    let TransitionStart {
+        id,
+        transition,
+        run,
+        ..
+    } = start;
+    let Some(class) = transition.class(Phase::Move, "") else {
+        return;
+    };
+    let last = backend
+        .try_borrow()
+        .ok()
+        .and_then(|backend| backend.position(id));
+    let weak = Rc::downgrade(backend);
+    let Some((dx, dy)) = last.map(|last| (first.0 - last.0, first.1 - last.1)) else {
+        run.end();
+        return;
+    };
+    if dx.abs() < 0.5 && dy.abs() < 0.5 {
+        // A move cut short by this pass leaves its class behind
+        run.end();
+        update(&weak, |backend| backend.remove_class(id, &class));
+        return;
+    }
+
+    update(&weak, |backend| {
+        backend.remove_class(id, &class)?;
+        backend.set_style(id, "transform", &format!("translate({}px, {}px)", dx, dy))?;
+        backend.set_style(id, "transition-duration", "0s")
+    });
+    next_frame(move || {
+        if !run.is_active() {
+            return;
+        }
+        update(&weak, |backend| {
+            backend.add_class(id, &class)?;
+            backend.remove_style(id, "transform")?;
+            backend.remove_style(id, "transition-duration")
+        });
+        let end = weak.clone();
+        after_transition(&weak, id, transition.duration, move || {
+            if run.end() {
+                update(&end, |backend| backend.remove_class(id, &class));
+            }
+        });
+    });
+}
+
+fn remove_classes(
+    backend: &mut dyn DomBackend,
+    id: NodeId,
+    transition: &Transition,
+    phase: Phase,
+    steps: &[&str],
+) -> Result<(), RenderError> {
+    for step in steps {
+        if let Some(class) = transition.class(phase, step) {
+            backend.remove_class(id, &class)?;
+        }
+    }
+    Ok(())
+}
+
+/// Change an element outside of a pass
+///
+/// Skipped while the backend is busy applying one; a failure means the
+/// element is gone, which leaves nothing to animate.
+fn update(
+    backend: &Weak<RefCell<Box<dyn DomBackend>>>,
+    f: impl FnOnce(&mut dyn DomBackend) -> Result<(), RenderError>,
+) {
+    let Some(backend) = backend.upgrade() else {
+        return;
+    };
+    let Ok(mut backend) = backend.try_borrow_mut() else {
+        return;
+    };
+    let _ = f(backend.as_mut());
+}
+
+/// Run `f` once the current styles were painted, so CSS transitions start
+/// from them; right away on native builds
+fn next_frame(f: impl FnOnce() + 'static) {
+    #[cfg(target_arch = "wasm32")]
+    if let Some(window) = web_sys::window() {
+        // Styles set before the first frame are only painted by its end
+        let then = Closure::once_into_js(f);
+        let first = Closure::once_into_js(move || {
+            if let Some(window) = web_sys::window() {
+                let _ = window.request_animation_frame(then.unchecked_ref());
+            }
+        });
+        let _ = window.request_animation_frame(first.unchecked_ref());
+        return;
+    }
+    f();
+}
+
+/// Run `f` once the CSS transitions and animations of `id` ended, or after
+/// `duration` milliseconds when given; right away on native builds
+fn after_transition(
+    backend: &Weak<RefCell<Box<dyn DomBackend>>>,
+    id: NodeId,
+    duration: Option<u32>,
+    f: impl FnOnce() + 'static,
+) {
+    #[cfg(target_arch = "wasm32")]
+    if let Some(window) = web_sys::window() {
+        let duration = duration.unwrap_or_else(|| {
+            backend
+                .upgrade()
+                .and_then(|backend| backend.try_borrow().ok()?.dom_node(id))
+                .and_then(|node| node.dyn_into::<Element>().ok())
+                .map_or(0, |element| css_duration(&window, &element))
+        });
+        let callback = Closure::once_into_js(f);
+        let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+            callback.unchecked_ref(),
+            duration.min(i32::MAX as u32) as i32,
+        );
+        return;
+    }
+    let _ = (backend, id, duration);
+    f();
+}
+
+/// Longest computed transition or animation of `element`, delays included,
+/// in milliseconds
+#[cfg(target_arch = "wasm32")]
+fn css_duration(window: &Window, element: &Element) -> u32 {
+    let Ok(Some(style)) = window.get_computed_style(element) else {
+        return 0;
+    };
+    let times = |property: String| -> Vec<f64> {
+        style
+            .get_property_value(&property)
+            .unwrap_or_default()
+            .split(',')
+            .map(parse_time)
+            .collect()
+    };
+    let longest = ["transition", "animation"]
+        .into_iter()
+        .map(|kind| {
+            let durations = times(format!("{}-duration", kind));
+            let delays = times(format!("{}-delay", kind));
+            // Shorter lists repeat, as in CSS
+            durations
+                .iter()
+                .enumerate()
+                .map(|(index, duration)| duration + delays[index % delays.len()])
+                .fold(0.0, f64::max)
+        })
+        .fold(0.0, f64::max);
+    longest.ceil() as u32
+}
+
+/// Milliseconds of a CSS time such as `0.3s` or `150ms`
+#[cfg(target_arch = "wasm32")]
+fn parse_time(time: &str) -> f64 {
+    let time = time.trim();
+    let parsed = match time.strip_suffix("ms") {
+        Some(ms) => ms.parse().ok(),
+        None => time
+            .strip_suffix('s')
+            .and_then(|s| s.parse::<f64>().ok())
+            .map(|s| s * 1000.0),
+    };
+    parsed.unwrap_or(0.0)
+}
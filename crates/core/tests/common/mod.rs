@@ -0,0 +1,46 @@
+use std::cell::RefCell;
+use std::future::{poll_fn, Future};
+use std::rc::Rc;
+use std::task::{Poll, Waker};
+
+/// Future source completed by hand, standing in for a network request
+pub struct Gate<T> {
+    state: Rc<RefCell<(Option<T>, Option<Waker>)>>,
+}
+
+impl<T> Default for Gate<T> {
+    fn default() -> Self {
+        Gate {
+            state: Rc::new(RefCell::new((None, None))),
+        }
+    }
+}
+
+impl<T: 'static> Gate<T> {
+    /// Future pending until `open` is called
+    pub fn wait(&self) -> impl Future<Output = T> + 'static {
+        let state = Rc::clone(&self.state);
+        poll_fn(move |cx| {
+            let mut state = state.borrow_mut();
+            match state.0.take() {
+                Some(value) => Poll::Ready(value),
+                None => {
+                    state.1 = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+
+    /// Complete the future with `value`, waking whoever waits for it
+    pub fn open(&self, value: T) {
+        let waker = {
+            let mut state = self.state.borrow_mut();
+            state.0 = Some(value);
+            state.1.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use selene_core::compile_template;
+
+#[test]
+fn static_template_is_hoisted_out_of_the_render_function() {
+    assert_eq!(
+        compile_template(r#"<div class="a"><p>hi</p><br/></div>"#),
+        r#"(() => { const _hoisted_1 = h("div", {"class": "a", "$static": true}, [h("p", {}, "hi"), h("br", {})]); return () => _hoisted_1; })()"#
+    );
+}
+
+#[test]
+fn mixed_template_hoists_static_children_and_lists_dynamic_props() {
+    assert_eq!(
+        compile_template(r#"<div class={cls} id="x"><span>static</span><b>${name}</b></div>"#),
+        concat!(
+            r#"(() => { const _hoisted_1 = h("span", {"$static": true}, "static"); "#,
+            r#"return () => h("div", {"class": cls, "id": "x", "$dynamic": ["class"]}, "#,
+            r#"[_hoisted_1, h("b", {"$dynamic": []}, `${name}`)]); })()"#
+        )
+    );
+}
+
+#[test]
+fn template_without_static_parts_keeps_a_plain_render_function() {
+    assert_eq!(
+        compile_template(r#"<p title={t}>${x}</p>"#),
+        r#"() => h("p", {"title": t, "$dynamic": ["title"]}, `${x}`)"#
+    );
+}
@@ -0,0 +1,282 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::cell::{Cell, RefCell};
+
+use selene_core::{
+    computed, effect, signal, Component, MockDom, NodeId, NodeRef, Renderer, Signal, VNode,
+};
+
+fn renderer() -> (MockDom, Renderer) {
+    let dom = MockDom::new();
+    let renderer = Renderer::with_backend(dom.clone()).unwrap();
+    CONTAINER.with(|container| *container.borrow_mut() = Some((dom.clone(), renderer.root())));
+    (dom, renderer)
+}
+
+thread_local! {
+    static SOURCE: Signal<i32> = signal(0);
+    static COMPUTED_RUNS: Cell<u32> = const { Cell::new(0) };
+    /// Components rendered so far, in order
+    static RENDERS: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+    /// Signals read by `Parent`: its own count and the label it passes on
+    static TICK: Signal<i32> = signal(0);
+    static LABEL: Signal<String> = signal("n".to_string());
+    /// Whether `Parent` gives `Counter` children
+    static PASS_CHILDREN: Cell<bool> = const { Cell::new(false) };
+    /// Local count of the last `Counter` created
+    static COUNT: RefCell<Option<Signal<i32>>> = const { RefCell::new(None) };
+    /// DOM and container the test renders into
+    static CONTAINER: RefCell<Option<(MockDom, NodeId)>> = const { RefCell::new(None) };
+    /// Hooks `Hooked` ran, with what they saw
+    static HOOKS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    /// Signal the effect `Hooked` creates on mount subscribes to
+    static OUTSIDE: Signal<i32> = signal(0);
+    static OUTSIDE_RUNS: Cell<u32> = const { Cell::new(0) };
+}
+
+fn computed_runs() -> u32 {
+    COMPUTED_RUNS.with(Cell::get)
+}
+
+fn renders() -> Vec<&'static str> {
+    RENDERS.with(|renders| renders.take())
+}
+
+fn rendered(name: &'static str) {
+    RENDERS.with(|renders| renders.borrow_mut().push(name));
+}
+
+fn hooks() -> Vec<String> {
+    HOOKS.with(|hooks| hooks.take())
+}
+
+fn container_html() -> String {
+    CONTAINER.with(|container| {
+        let (dom, root) = container.borrow().clone().unwrap();
+        dom.inner_html(root)
+    })
+}
+
+/// Derives a computed from `SOURCE` on every render
+struct Doubled;
+
+impl Component for Doubled {
+    type Props = ();
+
+    fn create(_: &()) -> Self {
+        Doubled
+    }
+
+    fn render(&self, _: &(), _children: &[VNode]) -> VNode {
+        let source = SOURCE.with(Signal::clone);
+        let doubled = computed(move || {
+            COMPUTED_RUNS.with(|runs| runs.set(runs.get() + 1));
+            source.get() * 2
+        });
+        VNode::element("p").add_child(VNode::text(&doubled.get().to_string()))
+    }
+}
+
+/// Shows `label: count` with a count of its own, then its children
+struct Counter {
+    count: Signal<i32>,
+}
+
+impl Component for Counter {
+    type Props = String;
+
+    fn create(_: &String) -> Self {
+        let count = signal(0);
+        COUNT.with(|slot| *slot.borrow_mut() = Some(count.clone()));
+        Counter { count }
+    }
+
+    fn render(&self, label: &String, children: &[VNode]) -> VNode {
+        rendered("counter");
+        let text = format!("{}: {}", label, self.count.get());
+        children.iter().fold(
+            VNode::element("span").add_child(VNode::text(&text)),
+            |span, child| span.add_child(child.clone()),
+        )
+    }
+}
+
+/// Shows `TICK` and a `Counter` labelled with `LABEL`
+struct Parent;
+
+impl Component for Parent {
+    type Props = ();
+
+    fn create(_: &()) -> Self {
+        Parent
+    }
+
+    fn render(&self, _: &(), _children: &[VNode]) -> VNode {
+        rendered("parent");
+        let tick = TICK.with(Signal::clone).get();
+        let mut counter = VNode::component::<Counter>(LABEL.with(Signal::clone).get());
+        if PASS_CHILDREN.with(Cell::get) {
+            counter = counter.add_child(VNode::element("i"));
+        }
+        VNode::element("div")
+            .add_child(VNode::element("p").add_child(VNode::text(&tick.to_string())))
+            .add_child(counter)
+    }
+}
+
+/// Records each hook with its ref and the container's HTML at that time
+struct Hooked {
+    count: Signal<i32>,
+    node: NodeRef,
+}
+
+impl Hooked {
+    fn record(&self, hook: &str) {
+        let node = self.node.id().map(|id| id.0);
+        let entry = format!("{} {:?} {}", hook, node, container_html());
+        HOOKS.with(|hooks| hooks.borrow_mut().push(entry));
+    }
+}
+
+impl Component for Hooked {
+    type Props = ();
+
+    fn create(_: &()) -> Self {
+        let count = signal(0);
+        COUNT.with(|slot| *slot.borrow_mut() = Some(count.clone()));
+        Hooked {
+            count,
+            node: NodeRef::new(),
+        }
+    }
+
+    fn render(&self, _: &(), _children: &[VNode]) -> VNode {
+        VNode::element("b")
+            .node_ref(&self.node)
+            .add_child(VNode::text(&self.count.get().to_string()))
+    }
+
+    fn on_mount(&self, _: &()) {
+        self.record("mount");
+        let outside = OUTSIDE.with(Signal::clone);
+        effect(move || {
+            outside.get();
+            OUTSIDE_RUNS.with(|runs| runs.set(runs.get() + 1));
+        });
+    }
+
+    fn on_update(&self, _: &()) {
+        self.record("update");
+    }
+
+    fn on_unmount(&self, _: &()) {
+        self.record("unmount");
+    }
+}
+
+#[test]
+fn effects_created_in_render_are_disposed_on_re_render_and_unmount() {
+    let (dom, renderer) = renderer();
+    let source = SOURCE.with(Signal::clone);
+    renderer.render(&VNode::component::<Doubled>(())).unwrap();
+    assert_eq!(dom.inner_html(renderer.root()), "<p>0</p>");
+    assert_eq!(computed_runs(), 1);
+
+    // The computed of the previous render runs once more, then the render
+    // that replaces it creates a new one
+    for value in 1..=3 {
+        source.set(value);
+        assert_eq!(computed_runs(), 1 + 2 * value as u32);
+    }
+    assert_eq!(dom.inner_html(renderer.root()), "<p>6</p>");
+
+    renderer.render(&VNode::empty()).unwrap();
+    let runs = computed_runs();
+    source.set(4);
+    assert_eq!(computed_runs(), runs);
+}
+
+#[test]
+fn own_signal_re_renders_only_the_component() {
+    let (dom, renderer) = renderer();
+    renderer.render(&VNode::component::<Parent>(())).unwrap();
+    assert_eq!(renders(), ["parent", "counter"]);
+
+    COUNT.with(|count| count.borrow().clone()).unwrap().set(1);
+
+    assert_eq!(renders(), ["counter"]);
+    assert_eq!(
+        dom.inner_html(renderer.root()),
+        "<div><p>0</p><span>n: 1</span></div>"
+    );
+}
+
+#[test]
+fn parent_re_render_skips_a_child_with_equal_props() {
+    let (dom, renderer) = renderer();
+    renderer.render(&VNode::component::<Parent>(())).unwrap();
+    renders();
+
+    TICK.with(Signal::clone).set(1);
+    assert_eq!(renders(), ["parent"]);
+
+    LABEL.with(Signal::clone).set("m".to_string());
+    assert_eq!(renders(), ["parent", "counter"]);
+    assert_eq!(
+        dom.inner_html(renderer.root()),
+        "<div><p>1</p><span>m: 0</span></div>"
+    );
+}
+
+#[test]
+fn parent_re_render_updates_a_child_given_children() {
+    let (dom, renderer) = renderer();
+    PASS_CHILDREN.with(|pass| pass.set(true));
+    renderer.render(&VNode::component::<Parent>(())).unwrap();
+    renders();
+
+    TICK.with(Signal::clone).set(1);
+
+    assert_eq!(renders(), ["parent", "counter"]);
+    assert_eq!(
+        dom.inner_html(renderer.root()),
+        "<div><p>1</p><span>n: 0<i></i></span></div>"
+    );
+}
+
+#[test]
+fn hooks_run_around_the_dom_changes() {
+    let (_dom, renderer) = renderer();
+    let page = |hooked: bool| {
+        let div = VNode::element("div");
+        match hooked {
+            true => div.add_child(VNode::component::<Hooked>(())),
+            false => div,
+        }
+    };
+
+    renderer.render(&page(true)).unwrap();
+    assert_eq!(hooks(), ["mount Some(2) <div><b>0</b></div>"]);
+
+    COUNT.with(|count| count.borrow().clone()).unwrap().set(1);
+    assert_eq!(hooks(), ["update Some(2) <div><b>1</b></div>"]);
+
+    renderer.render(&page(false)).unwrap();
+    assert_eq!(hooks(), ["unmount Some(2) <div><b>1</b></div>"]);
+    assert_eq!(container_html(), "<div></div>");
+}
+
+#[test]
+fn effects_created_on_mount_are_disposed_on_unmount() {
+    let (_dom, renderer) = renderer();
+    let outside = OUTSIDE.with(Signal::clone);
+    renderer.render(&VNode::component::<Hooked>(())).unwrap();
+    assert_eq!(OUTSIDE_RUNS.with(Cell::get), 1);
+    outside.set(1);
+    assert_eq!(OUTSIDE_RUNS.with(Cell::get), 2);
+
+    renderer.unmount().unwrap();
+    outside.set(2);
+
+    assert_eq!(OUTSIDE_RUNS.with(Cell::get), 2);
+}
@@ -0,0 +1,139 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::cell::{Cell, RefCell};
+
+use selene_core::{signal, Component, ErrorReset, MockDom, RenderError, Renderer, Signal, VNode};
+
+thread_local! {
+    /// Whether `Flaky` panics when created
+    static FAIL_CREATE: Cell<bool> = const { Cell::new(true) };
+    /// Times `Flaky` was created
+    static CREATED: Cell<u32> = const { Cell::new(0) };
+    /// Signal making `Fragile` panic on its next render once true
+    static BREAK: RefCell<Option<Signal<bool>>> = const { RefCell::new(None) };
+}
+
+fn renderer() -> (MockDom, Renderer) {
+    let dom = MockDom::new();
+    let renderer = Renderer::with_backend(dom.clone()).unwrap();
+    (dom, renderer)
+}
+
+/// Panics when created while `FAIL_CREATE` is set
+struct Flaky;
+
+impl Component for Flaky {
+    type Props = ();
+
+    fn create(_: &()) -> Self {
+        CREATED.with(|created| created.set(created.get() + 1));
+        if FAIL_CREATE.with(Cell::get) {
+            panic!("no data");
+        }
+        Flaky
+    }
+
+    fn render(&self, _: &(), _children: &[VNode]) -> VNode {
+        VNode::element("p").add_child(VNode::text("data"))
+    }
+}
+
+/// Panics when rendering once `BREAK` is set
+struct Fragile {
+    broken: Signal<bool>,
+}
+
+impl Component for Fragile {
+    type Props = ();
+
+    fn create(_: &()) -> Self {
+        let broken = signal(false);
+        BREAK.with(|slot| *slot.borrow_mut() = Some(broken.clone()));
+        Fragile { broken }
+    }
+
+    fn render(&self, _: &(), _children: &[VNode]) -> VNode {
+        if self.broken.get() {
+            panic!("broken");
+        }
+        VNode::element("p").add_child(VNode::text("fine"))
+    }
+}
+
+/// Boundary showing the error message, with a button resetting it
+fn boundary(content: VNode) -> VNode {
+    VNode::error_boundary(
+        |error: &RenderError, reset: ErrorReset| {
+            VNode::element("button")
+                .on("click", move |_| reset.reset())
+                .add_child(VNode::text(&error.to_string()))
+        },
+        content,
+    )
+}
+
+#[test]
+fn panic_while_creating_a_component_shows_the_fallback() {
+    let (dom, renderer) = renderer();
+
+    renderer
+        .render(&boundary(VNode::component::<Flaky>(())))
+        .unwrap();
+
+    assert_eq!(
+        dom.inner_html(renderer.root()),
+        "<button>component panicked: no data</button><!---->"
+    );
+}
+
+#[test]
+fn panic_while_re_rendering_a_component_shows_the_fallback() {
+    let (dom, renderer) = renderer();
+    renderer
+        .render(&boundary(VNode::component::<Fragile>(())))
+        .unwrap();
+    assert_eq!(dom.inner_html(renderer.root()), "<p>fine</p><!---->");
+
+    BREAK.with(|slot| slot.borrow().clone()).unwrap().set(true);
+
+    assert_eq!(
+        dom.inner_html(renderer.root()),
+        "<button>component panicked: broken</button><!---->"
+    );
+}
+
+#[test]
+fn reset_remounts_the_content() {
+    let (dom, renderer) = renderer();
+    renderer
+        .render(&boundary(VNode::component::<Flaky>(())))
+        .unwrap();
+    assert_eq!(CREATED.with(Cell::get), 1);
+
+    FAIL_CREATE.with(|fail| fail.set(false));
+    let button = dom.query_selector(renderer.root(), "button").unwrap();
+    dom.dispatch(button, "click");
+
+    assert_eq!(CREATED.with(Cell::get), 2);
+    assert_eq!(dom.inner_html(renderer.root()), "<p>data</p><!---->");
+}
+
+#[test]
+fn failing_fallback_reaches_the_outer_boundary() {
+    let (dom, renderer) = renderer();
+    let inner = VNode::error_boundary(
+        |_, _| VNode::component::<Flaky>(()),
+        VNode::element("bad tag"),
+    );
+    let outer = VNode::error_boundary(
+        |error: &RenderError, _| VNode::element("p").add_child(VNode::text(&error.to_string())),
+        VNode::element("section").add_child(inner),
+    );
+
+    renderer.render(&outer).unwrap();
+
+    assert_eq!(
+        dom.inner_html(renderer.root()),
+        "<p>component panicked: no data</p><!---->"
+    );
+}
@@ -0,0 +1,178 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use selene_core::{Event, MockDom, NodeId, Patch, Renderer, VNode};
+
+fn delegating_renderer() -> (MockDom, Renderer) {
+    let dom = MockDom::new();
+    let renderer = Renderer::with_backend(dom.clone()).unwrap();
+    renderer.set_event_delegation(true);
+    (dom, renderer)
+}
+
+/// Handlers recording `"<name>"` for each call, in order
+#[derive(Clone, Default)]
+struct Calls(Rc<RefCell<Vec<String>>>);
+
+impl Calls {
+    fn handler(&self, name: &str) -> impl Fn(&Event) + 'static {
+        let calls = self.clone();
+        let name = name.to_string();
+        move |_| calls.0.borrow_mut().push(name.clone())
+    }
+
+    fn take(&self) -> Vec<String> {
+        self.0.take()
+    }
+}
+
+/// `div > p > button`, each with a click handler
+fn nested(calls: &Calls) -> VNode {
+    VNode::element("div")
+        .on("click", calls.handler("div"))
+        .add_child(
+            VNode::element("p")
+                .on("click", calls.handler("p"))
+                .add_child(VNode::element("button").on("click", calls.handler("button"))),
+        )
+}
+
+fn button_of(dom: &MockDom, renderer: &Renderer) -> NodeId {
+    dom.query_selector(renderer.root(), "button").unwrap()
+}
+
+fn listener_patches(patches: &[Patch]) -> Vec<&Patch> {
+    patches
+        .iter()
+        .filter(|patch| {
+            matches!(
+                patch,
+                Patch::AddEventListener { .. }
+                    | Patch::AddDelegatedListener { .. }
+                    | Patch::RemoveEventListener { .. }
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn delegation_listens_once_per_root_and_event_type() {
+    let (dom, renderer) = delegating_renderer();
+    let calls = Calls::default();
+    let vnode =
+        nested(&calls).add_child(VNode::element("input").on("focus", calls.handler("input")));
+
+    let patches = renderer.render(&vnode).unwrap();
+
+    let root = renderer.root();
+    assert_eq!(
+        listener_patches(&patches),
+        [
+            &Patch::AddDelegatedListener {
+                id: root,
+                event: "click".to_string(),
+            },
+            &Patch::AddDelegatedListener {
+                id: root,
+                event: "focus".to_string(),
+            },
+        ]
+    );
+    let mut events = dom.listeners(root);
+    events.sort();
+    assert_eq!(events, ["click", "focus"]);
+    assert!(dom.listeners(button_of(&dom, &renderer)).is_empty());
+}
+
+#[test]
+fn delegated_events_bubble_through_ancestors_in_order() {
+    let (dom, renderer) = delegating_renderer();
+    let calls = Calls::default();
+    let targets = Rc::new(RefCell::new(Vec::new()));
+    // The div's handler is replaced by one recording the event's targets
+    let vnode = nested(&calls).on("click", {
+        let targets = Rc::clone(&targets);
+        move |event: &Event| {
+            targets
+                .borrow_mut()
+                .push((event.target(), event.current_target()))
+        }
+    });
+    renderer.render(&vnode).unwrap();
+    let div = dom.children(renderer.root())[0];
+    let button = button_of(&dom, &renderer);
+
+    dom.dispatch(button, "click");
+
+    assert_eq!(calls.take(), ["button", "p"]);
+    assert_eq!(*targets.borrow(), [(Some(button), Some(div))]);
+}
+
+#[test]
+fn stop_propagation_stops_at_the_handler_that_called_it() {
+    let (dom, renderer) = delegating_renderer();
+    let calls = Calls::default();
+    let vnode = VNode::element("div")
+        .on("click", calls.handler("div"))
+        .add_child(
+            VNode::element("p")
+                .on("click", {
+                    let record = calls.handler("p");
+                    move |event: &Event| {
+                        record(event);
+                        event.stop_propagation();
+                    }
+                })
+                .add_child(VNode::element("button").on("click", calls.handler("button"))),
+        );
+    renderer.render(&vnode).unwrap();
+
+    let event = dom.dispatch(button_of(&dom, &renderer), "click");
+
+    assert_eq!(calls.take(), ["button", "p"]);
+    assert!(event.is_propagation_stopped());
+}
+
+#[test]
+fn focus_and_blur_only_reach_the_target() {
+    let (dom, renderer) = delegating_renderer();
+    let calls = Calls::default();
+    let vnode = VNode::element("div")
+        .on("focus", calls.handler("div focus"))
+        .on("blur", calls.handler("div blur"))
+        .add_child(
+            VNode::element("input")
+                .on("focus", calls.handler("input focus"))
+                .on("blur", calls.handler("input blur")),
+        )
+        .add_child(VNode::element("textarea"));
+    renderer.render(&vnode).unwrap();
+    let input = dom.query_selector(renderer.root(), "input").unwrap();
+    let textarea = dom.query_selector(renderer.root(), "textarea").unwrap();
+
+    dom.dispatch(input, "focus");
+    dom.dispatch(input, "blur");
+    dom.dispatch(textarea, "focus");
+
+    assert_eq!(calls.take(), ["input focus", "input blur"]);
+}
+
+#[test]
+fn switching_handlers_on_re_render_adds_no_listeners() {
+    for delegation in [true, false] {
+        let dom = MockDom::new();
+        let renderer = Renderer::with_backend(dom.clone()).unwrap();
+        renderer.set_event_delegation(delegation);
+        let calls = Calls::default();
+        let button = |name| VNode::element("button").on("click", calls.handler(name));
+        renderer.render(&button("first")).unwrap();
+
+        let patches = renderer.render(&button("second")).unwrap();
+        dom.dispatch(button_of(&dom, &renderer), "click");
+
+        assert!(listener_patches(&patches).is_empty(), "{:?}", patches);
+        assert_eq!(calls.take(), ["second"]);
+    }
+}
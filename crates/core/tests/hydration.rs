@@ -0,0 +1,126 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use selene_core::{render_to_string, MockDom, NodeId, Patch, RenderError, Renderer, VNode};
+
+/// Renderer whose container holds the server-rendered HTML of `vnode`
+fn server_rendered(vnode: &VNode) -> (MockDom, Renderer) {
+    let dom = MockDom::new();
+    let renderer = Renderer::with_backend(dom.clone()).unwrap();
+    dom.set_inner_html(renderer.root(), &render_to_string(vnode))
+        .unwrap();
+    (dom, renderer)
+}
+
+fn text(tag: &str, content: &str) -> VNode {
+    VNode::element(tag).add_child(VNode::text(content))
+}
+
+fn created_tags(patches: &[Patch]) -> Vec<&str> {
+    patches
+        .iter()
+        .filter_map(|patch| match patch {
+            Patch::CreateElement { tag, .. } => Some(tag.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn hydration_claims_nodes_without_creating_any() {
+    let vnode = VNode::element("div")
+        .set_prop("class", "box")
+        .add_child(text("h1", "Title"))
+        .add_child(VNode::text("body"))
+        .add_child(VNode::element("input").prop("value", "x"));
+    let (dom, renderer) = server_rendered(&vnode);
+    let html = dom.inner_html(renderer.root());
+
+    let patches = renderer.hydrate(&vnode).unwrap();
+
+    assert!(
+        patches
+            .iter()
+            .all(|patch| matches!(patch, Patch::Claim { .. } | Patch::SetProperty { .. })),
+        "{:?}",
+        patches
+    );
+    assert_eq!(dom.inner_html(renderer.root()), html);
+    assert_eq!(dom.children(renderer.root()), [NodeId(1)]);
+}
+
+#[test]
+fn hydrated_nodes_get_their_listeners() {
+    let clicks = Rc::new(Cell::new(0));
+    let vnode = VNode::element("div").add_child(text("button", "+1").on("click", {
+        let clicks = Rc::clone(&clicks);
+        move |_| clicks.set(clicks.get() + 1)
+    }));
+    let (dom, renderer) = server_rendered(&vnode);
+
+    renderer.hydrate(&vnode).unwrap();
+    let button = dom.query_selector(renderer.root(), "button").unwrap();
+    dom.dispatch(button, "click");
+
+    assert_eq!(dom.listeners(button), ["click"]);
+    assert_eq!(clicks.get(), 1);
+}
+
+#[test]
+fn recovery_re_renders_only_the_mismatched_subtree() {
+    let page = |inner: &str| {
+        VNode::element("div")
+            .add_child(text("p", "a"))
+            .add_child(VNode::element("section").add_child(text(inner, "x")))
+    };
+    let (dom, renderer) = server_rendered(&page("span"));
+
+    let Err(RenderError::HydrationMismatch(mismatches)) = renderer.hydrate(&page("em")) else {
+        panic!("hydration without recovery should fail");
+    };
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].path, "div > section[1] > em[0]");
+
+    renderer.set_hydration_recovery(true);
+    let patches = renderer.hydrate(&page("em")).unwrap();
+
+    assert_eq!(created_tags(&patches), ["em"]);
+    let removed: Vec<_> = patches
+        .iter()
+        .filter(|patch| matches!(patch, Patch::Remove { .. }))
+        .collect();
+    assert_eq!(removed.len(), 1, "{:?}", patches);
+    assert_eq!(
+        dom.inner_html(renderer.root()),
+        "<div><p>a</p><section><em>x</em></section></div>"
+    );
+}
+
+#[test]
+fn text_separators_round_trip() {
+    let paragraph = |second: &str| {
+        VNode::element("p")
+            .add_child(VNode::text("a"))
+            .add_child(VNode::text(second))
+    };
+    let (dom, renderer) = server_rendered(&paragraph("b"));
+    assert_eq!(dom.inner_html(renderer.root()), "<p>a<!-- -->b</p>");
+
+    let patches = renderer.hydrate(&paragraph("b")).unwrap();
+    assert!(created_tags(&patches).is_empty());
+    // The separator has done its job and is removed
+    assert_eq!(dom.inner_html(renderer.root()), "<p>ab</p>");
+
+    let p = dom.children(renderer.root())[0];
+    let second = dom.children(p)[1];
+    assert_eq!(
+        renderer.render(&paragraph("c")).unwrap(),
+        vec![Patch::SetText {
+            id: second,
+            text: "c".to_string(),
+        }]
+    );
+    assert_eq!(dom.inner_html(renderer.root()), "<p>ac</p>");
+}
@@ -0,0 +1,86 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use selene_core::{MockDom, NodeRef, Renderer, VNode};
+
+fn renderer() -> (MockDom, Renderer) {
+    let dom = MockDom::new();
+    let renderer = Renderer::with_backend(dom.clone()).unwrap();
+    (dom, renderer)
+}
+
+#[test]
+fn ref_is_set_once_mounted() {
+    let (dom, renderer) = renderer();
+    let input = NodeRef::new();
+    assert_eq!(input.id(), None);
+
+    renderer
+        .render(&VNode::element("form").add_child(VNode::element("input").node_ref(&input)))
+        .unwrap();
+
+    let form = dom.children(renderer.root())[0];
+    assert_eq!(input.id(), Some(dom.children(form)[0]));
+}
+
+#[test]
+fn ref_follows_the_node_that_replaces_its_own() {
+    let (dom, renderer) = renderer();
+    let target = NodeRef::new();
+    renderer
+        .render(&VNode::element("p").node_ref(&target))
+        .unwrap();
+    let p = target.id().unwrap();
+
+    renderer
+        .render(&VNode::element("section").node_ref(&target))
+        .unwrap();
+
+    let section = dom.children(renderer.root())[0];
+    assert_ne!(section, p);
+    assert_eq!(target.id(), Some(section));
+    assert_eq!(dom.tag(section).as_deref(), Some("section"));
+}
+
+#[test]
+fn ref_moved_to_a_new_node_is_not_cleared_by_the_old_one() {
+    let (dom, renderer) = renderer();
+    let target = NodeRef::new();
+    renderer
+        .render(
+            &VNode::element("div")
+                .add_child(VNode::element("p").set_key("a").node_ref(&target))
+                .add_child(VNode::element("p").set_key("b")),
+        )
+        .unwrap();
+
+    renderer
+        .render(
+            &VNode::element("div").add_child(VNode::element("p").set_key("b").node_ref(&target)),
+        )
+        .unwrap();
+
+    let div = dom.children(renderer.root())[0];
+    assert_eq!(target.id(), Some(dom.children(div)[0]));
+}
+
+#[test]
+fn ref_is_cleared_on_unmount() {
+    let (_dom, renderer) = renderer();
+    let removed = NodeRef::new();
+    let kept = NodeRef::new();
+    let page = |with_removed: bool| {
+        let div = VNode::element("div").node_ref(&kept);
+        match with_removed {
+            true => div.add_child(VNode::element("span").node_ref(&removed)),
+            false => div,
+        }
+    };
+    renderer.render(&page(true)).unwrap();
+
+    renderer.render(&page(false)).unwrap();
+    assert_eq!(removed.id(), None);
+    assert!(kept.id().is_some());
+
+    renderer.unmount().unwrap();
+    assert_eq!(kept.id(), None);
+}
@@ -0,0 +1,564 @@
+use std::collections::BTreeMap;
+
+use selene_core::{
+    ExistingNode, NodeId, Patch, PropValue, Reconciler, VNode, MATHML_NAMESPACE, SVG_NAMESPACE,
+    XLINK_NAMESPACE, XML_NAMESPACE,
+};
+
+fn mounted(vnode: &VNode) -> (Reconciler, NodeId) {
+    let mut reconciler = Reconciler::new();
+    let root = reconciler.create_root();
+    reconciler.render(root, vnode);
+    (reconciler, root)
+}
+
+fn insert(parent: u32, id: u32, before: Option<u32>) -> Patch {
+    Patch::InsertBefore {
+        parent: NodeId(parent),
+        id: NodeId(id),
+        before: before.map(NodeId),
+    }
+}
+
+fn item(content: &str) -> VNode {
+    VNode::element("li").add_child(VNode::text(content))
+}
+
+fn list(items: &[&str]) -> VNode {
+    items.iter().fold(VNode::element("ul"), |list, content| {
+        list.add_child(item(content))
+    })
+}
+
+fn keyed_list(keys: &[&str]) -> VNode {
+    keys.iter().fold(VNode::element("ul"), |list, key| {
+        list.add_child(item(key).set_key(key))
+    })
+}
+
+fn fragment(items: &[&str]) -> VNode {
+    items.iter().fold(VNode::fragment(), |fragment, content| {
+        fragment.add_child(item(content))
+    })
+}
+
+fn create_ns(id: u32, namespace: &str, tag: &str) -> Patch {
+    Patch::CreateElementNs {
+        id: NodeId(id),
+        namespace: namespace.to_string(),
+        tag: tag.to_string(),
+    }
+}
+
+/// `ul` holding `fragment` followed by an `hr`
+fn around(fragment: VNode) -> VNode {
+    VNode::element("ul")
+        .add_child(fragment)
+        .add_child(VNode::element("hr"))
+}
+
+#[test]
+fn first_render_creates_and_inserts_bottom_up() {
+    let mut reconciler = Reconciler::new();
+    let root = reconciler.create_root();
+
+    let patches = reconciler.render(
+        root,
+        &VNode::element("p")
+            .set_prop("class", "a")
+            .add_child(VNode::text("x")),
+    );
+
+    assert_eq!(
+        patches,
+        vec![
+            Patch::CreateElement {
+                id: NodeId(1),
+                tag: "p".to_string(),
+            },
+            Patch::SetAttribute {
+                id: NodeId(1),
+                name: "class".to_string(),
+                value: "a".to_string(),
+            },
+            Patch::CreateText {
+                id: NodeId(2),
+                text: "x".to_string(),
+            },
+            insert(1, 2, None),
+            insert(0, 1, None),
+        ]
+    );
+}
+
+#[test]
+fn prop_changes_set_and_remove_attributes() {
+    let (mut reconciler, root) = mounted(&VNode::element("p").set_prop("class", "a"));
+
+    let patches = reconciler.render(root, &VNode::element("p").set_prop("id", "b"));
+
+    assert_eq!(
+        patches,
+        vec![
+            Patch::SetAttribute {
+                id: NodeId(1),
+                name: "id".to_string(),
+                value: "b".to_string(),
+            },
+            Patch::RemoveAttribute {
+                id: NodeId(1),
+                name: "class".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn props_named_like_handlers_are_attributes() {
+    let mut reconciler = Reconciler::new();
+    let root = reconciler.create_root();
+
+    let patches = reconciler.render(
+        root,
+        &VNode::element("p")
+            .set_prop("one-time", "a")
+            .set_prop("only", "b")
+            .set_prop("onclick", "go()"),
+    );
+
+    let attributes: Vec<_> = patches
+        .iter()
+        .filter_map(|patch| match patch {
+            Patch::SetAttribute { name, value, .. } => Some((name.as_str(), value.as_str())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        attributes,
+        [("onclick", "go()"), ("one-time", "a"), ("only", "b")]
+    );
+}
+
+#[test]
+fn false_boolean_removes_the_attribute() {
+    let (mut reconciler, root) = mounted(&VNode::element("button").prop("disabled", true));
+
+    let patches = reconciler.render(root, &VNode::element("button").prop("disabled", false));
+
+    assert_eq!(
+        patches,
+        vec![Patch::RemoveAttribute {
+            id: NodeId(1),
+            name: "disabled".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn style_declarations_are_diffed_one_by_one() {
+    let (mut reconciler, root) = mounted(&VNode::element("p").prop(
+        "style",
+        PropValue::style([("color", "red"), ("margin", "0")]),
+    ));
+
+    let patches = reconciler.render(
+        root,
+        &VNode::element("p").prop(
+            "style",
+            PropValue::style([("color", "blue"), ("padding", "1px")]),
+        ),
+    );
+
+    assert_eq!(
+        patches,
+        vec![
+            Patch::SetStyle {
+                id: NodeId(1),
+                name: "color".to_string(),
+                value: "blue".to_string(),
+            },
+            Patch::SetStyle {
+                id: NodeId(1),
+                name: "padding".to_string(),
+                value: "1px".to_string(),
+            },
+            Patch::RemoveStyle {
+                id: NodeId(1),
+                name: "margin".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn class_sets_compare_regardless_of_order() {
+    let classes = |names| VNode::element("p").prop("class", PropValue::classes([names]));
+    let (mut reconciler, root) = mounted(&classes("b a"));
+
+    assert_eq!(reconciler.render(root, &classes("a  b")), vec![]);
+    assert_eq!(
+        reconciler.render(root, &classes("c a")),
+        vec![Patch::SetAttribute {
+            id: NodeId(1),
+            name: "class".to_string(),
+            value: "a c".to_string(),
+        }]
+    );
+    assert_eq!(
+        reconciler.render(root, &classes("")),
+        vec![Patch::RemoveAttribute {
+            id: NodeId(1),
+            name: "class".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn text_change_sets_text() {
+    let (mut reconciler, root) = mounted(&item("x"));
+
+    let patches = reconciler.render(root, &item("y"));
+
+    assert_eq!(
+        patches,
+        vec![Patch::SetText {
+            id: NodeId(2),
+            text: "y".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn unchanged_tree_emits_nothing() {
+    let (mut reconciler, root) = mounted(&list(&["a", "b"]));
+
+    assert_eq!(reconciler.render(root, &list(&["a", "b"])), vec![]);
+}
+
+#[test]
+fn appended_child_is_created_and_inserted() {
+    let (mut reconciler, root) = mounted(&list(&["a", "b"]));
+
+    let patches = reconciler.render(root, &list(&["a", "b", "c"]));
+
+    assert_eq!(
+        patches,
+        vec![
+            Patch::CreateElement {
+                id: NodeId(6),
+                tag: "li".to_string(),
+            },
+            Patch::CreateText {
+                id: NodeId(7),
+                text: "c".to_string(),
+            },
+            insert(6, 7, None),
+            insert(1, 6, None),
+        ]
+    );
+}
+
+#[test]
+fn trailing_children_are_removed() {
+    let (mut reconciler, root) = mounted(&list(&["a", "b", "c"]));
+
+    let patches = reconciler.render(root, &list(&["a"]));
+
+    assert_eq!(
+        patches,
+        vec![
+            Patch::Remove { id: NodeId(4) },
+            Patch::Remove { id: NodeId(6) }
+        ]
+    );
+}
+
+#[test]
+fn different_tag_is_replaced() {
+    let (mut reconciler, root) = mounted(&VNode::element("p"));
+
+    let patches = reconciler.render(root, &VNode::element("span"));
+
+    assert_eq!(
+        patches,
+        vec![
+            Patch::CreateElement {
+                id: NodeId(2),
+                tag: "span".to_string(),
+            },
+            insert(0, 2, Some(1)),
+            Patch::Remove { id: NodeId(1) },
+        ]
+    );
+}
+
+#[test]
+fn keyed_reorder_moves_only_nodes_off_the_longest_increasing_run() {
+    // ul is 1, then each li and its text: a 2/3, b 4/5, c 6/7, d 8/9, e 10/11
+    let (mut reconciler, root) = mounted(&keyed_list(&["a", "b", "c", "d", "e"]));
+
+    let patches = reconciler.render(root, &keyed_list(&["e", "b", "a", "d", "c", "f"]));
+
+    assert_eq!(
+        patches,
+        vec![
+            Patch::CreateElement {
+                id: NodeId(12),
+                tag: "li".to_string(),
+            },
+            Patch::CreateText {
+                id: NodeId(13),
+                text: "f".to_string(),
+            },
+            insert(12, 13, None),
+            insert(1, 12, None),
+            // a and c stay, the other three move
+            insert(1, 8, Some(6)),
+            insert(1, 4, Some(2)),
+            insert(1, 10, Some(4)),
+        ]
+    );
+}
+
+#[test]
+fn keyed_removal_removes_only_the_missing_key() {
+    let (mut reconciler, root) = mounted(&keyed_list(&["a", "b", "c"]));
+
+    let patches = reconciler.render(root, &keyed_list(&["a", "c"]));
+
+    assert_eq!(patches, vec![Patch::Remove { id: NodeId(4) }]);
+}
+
+#[test]
+fn failed_hydration_forgets_the_events_it_listened_for() {
+    let mut reconciler = Reconciler::new();
+    let root = reconciler.create_root();
+    let target = reconciler.create_root();
+    let clickable = VNode::element("button")
+        .on("click", |_| {})
+        .add_child(VNode::text("a"));
+    let existing = [ExistingNode::Element {
+        tag: "button".to_string(),
+        attributes: BTreeMap::new(),
+        children: vec![ExistingNode::Text("b".to_string())],
+    }];
+
+    // The button is adopted, its text differs, and without recovery that fails
+    assert!(reconciler.hydrate(root, &clickable, &existing).is_err());
+    let patches = reconciler.render(root, &VNode::portal(target));
+
+    assert!(
+        !patches
+            .iter()
+            .any(|patch| matches!(patch, Patch::AddPortalListener { .. })),
+        "{:?}",
+        patches
+    );
+}
+
+#[test]
+fn fragment_children_are_inserted_before_its_anchor() {
+    // ul 1 > li 2 > "a" 3, anchor 4, hr 5
+    let (mut reconciler, root) = mounted(&around(fragment(&["a"])));
+
+    let patches = reconciler.render(root, &around(fragment(&["a", "b"])));
+
+    assert_eq!(
+        patches,
+        vec![
+            Patch::CreateElement {
+                id: NodeId(6),
+                tag: "li".to_string(),
+            },
+            Patch::CreateText {
+                id: NodeId(7),
+                text: "b".to_string(),
+            },
+            insert(6, 7, None),
+            insert(1, 6, Some(4)),
+        ]
+    );
+
+    let patches = reconciler.render(root, &around(fragment(&[])));
+
+    assert_eq!(
+        patches,
+        vec![
+            Patch::Remove { id: NodeId(2) },
+            Patch::Remove { id: NodeId(6) },
+        ]
+    );
+}
+
+#[test]
+fn keyed_fragments_move_with_their_anchor() {
+    let fragments = |keys: &[&str]| {
+        keys.iter().fold(VNode::element("div"), |div, key| {
+            div.add_child(
+                VNode::fragment()
+                    .set_key(key)
+                    .add_child(VNode::text(key))
+                    .add_child(VNode::element("br")),
+            )
+        })
+    };
+    // div 1 > "a" 2, br 3, anchor 4, "b" 5, br 6, anchor 7, "c" 8, br 9,
+    // anchor 10
+    let (mut reconciler, root) = mounted(&fragments(&["a", "b", "c"]));
+
+    let patches = reconciler.render(root, &fragments(&["c", "a", "b"]));
+
+    assert_eq!(
+        patches,
+        vec![
+            insert(1, 8, Some(2)),
+            insert(1, 9, Some(2)),
+            insert(1, 10, Some(2))
+        ]
+    );
+}
+
+#[test]
+fn optional_child_toggles_with_an_empty_placeholder() {
+    let optional = |child: Option<VNode>| {
+        VNode::element("div")
+            .add_child(VNode::text("a"))
+            .add_child(child.into())
+            .add_child(VNode::text("b"))
+    };
+    // div 1 > "a" 2, placeholder 3, "b" 4
+    let (mut reconciler, root) = mounted(&optional(None));
+
+    let patches = reconciler.render(root, &optional(Some(VNode::element("i"))));
+
+    assert_eq!(
+        patches,
+        vec![
+            Patch::CreateElement {
+                id: NodeId(5),
+                tag: "i".to_string(),
+            },
+            insert(1, 5, Some(3)),
+            Patch::Remove { id: NodeId(3) },
+        ]
+    );
+
+    let patches = reconciler.render(root, &optional(None));
+
+    assert_eq!(
+        patches,
+        vec![
+            Patch::CreateComment {
+                id: NodeId(3),
+                text: String::new(),
+            },
+            insert(1, 3, Some(5)),
+            Patch::Remove { id: NodeId(5) },
+        ]
+    );
+    assert_eq!(
+        reconciler.render(root, &optional(Some(VNode::empty()))),
+        vec![]
+    );
+}
+
+#[test]
+fn svg_and_math_subtrees_are_created_in_their_namespace() {
+    let mut reconciler = Reconciler::new();
+    let root = reconciler.create_root();
+
+    let patches = reconciler.render(
+        root,
+        &VNode::element("div")
+            .add_child(VNode::element("svg").add_child(VNode::element("circle")))
+            .add_child(VNode::element("math").add_child(VNode::element("mi"))),
+    );
+
+    assert_eq!(
+        patches,
+        vec![
+            Patch::CreateElement {
+                id: NodeId(1),
+                tag: "div".to_string(),
+            },
+            create_ns(2, SVG_NAMESPACE, "svg"),
+            create_ns(3, SVG_NAMESPACE, "circle"),
+            insert(2, 3, None),
+            insert(1, 2, None),
+            create_ns(4, MATHML_NAMESPACE, "math"),
+            create_ns(5, MATHML_NAMESPACE, "mi"),
+            insert(4, 5, None),
+            insert(1, 4, None),
+            insert(0, 1, None),
+        ]
+    );
+}
+
+#[test]
+fn foreign_object_children_are_html() {
+    let mut reconciler = Reconciler::new();
+    let root = reconciler.create_root();
+
+    let patches = reconciler.render(
+        root,
+        &VNode::element("svg")
+            .add_child(VNode::element("foreignObject").add_child(VNode::element("div"))),
+    );
+
+    assert_eq!(
+        patches,
+        vec![
+            create_ns(1, SVG_NAMESPACE, "svg"),
+            create_ns(2, SVG_NAMESPACE, "foreignObject"),
+            Patch::CreateElement {
+                id: NodeId(3),
+                tag: "div".to_string(),
+            },
+            insert(2, 3, None),
+            insert(1, 2, None),
+            insert(0, 1, None),
+        ]
+    );
+}
+
+#[test]
+fn prefixed_attributes_are_set_in_their_namespace() {
+    let image = |href: Option<&str>| {
+        let image = VNode::element("image").set_prop("xml:lang", "en");
+        let image = match href {
+            Some(href) => image.set_prop("xlink:href", href),
+            None => image,
+        };
+        VNode::element("svg").add_child(image)
+    };
+    let mut reconciler = Reconciler::new();
+    let root = reconciler.create_root();
+
+    let patches = reconciler.render(root, &image(Some("a.png")));
+
+    assert_eq!(
+        patches[2..4],
+        [
+            Patch::SetAttributeNs {
+                id: NodeId(2),
+                namespace: XLINK_NAMESPACE.to_string(),
+                name: "xlink:href".to_string(),
+                value: "a.png".to_string(),
+            },
+            Patch::SetAttributeNs {
+                id: NodeId(2),
+                namespace: XML_NAMESPACE.to_string(),
+                name: "xml:lang".to_string(),
+                value: "en".to_string(),
+            },
+        ]
+    );
+    assert_eq!(
+        reconciler.render(root, &image(None)),
+        vec![Patch::RemoveAttributeNs {
+            id: NodeId(2),
+            namespace: XLINK_NAMESPACE.to_string(),
+            name: "xlink:href".to_string(),
+        }]
+    );
+}
@@ -0,0 +1,113 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use selene_core::{Event, MockDom, NodeId, Patch, Renderer, VNode};
+
+/// Renderer with a portal target standing in for `document.body`
+fn renderer() -> (MockDom, Renderer, NodeId) {
+    let dom = MockDom::new();
+    let renderer = Renderer::with_backend(dom.clone()).unwrap();
+    let body = renderer.create_portal_target().unwrap();
+    (dom, renderer, body)
+}
+
+/// `div` holding a portal with a button and `label`, then a `span`
+fn page(body: NodeId, label: &str) -> VNode {
+    VNode::element("div")
+        .add_child(
+            VNode::portal(body)
+                .add_child(VNode::element("button"))
+                .add_child(VNode::text(label)),
+        )
+        .add_child(VNode::element("span"))
+}
+
+#[test]
+fn portal_children_render_into_the_target() {
+    let (dom, renderer, body) = renderer();
+
+    renderer.render(&page(body, "x")).unwrap();
+
+    assert_eq!(
+        dom.inner_html(renderer.root()),
+        "<div><!----><span></span></div>"
+    );
+    assert_eq!(dom.inner_html(body), "<div><button></button>x</div>");
+}
+
+#[test]
+fn portal_children_are_diffed_in_place() {
+    let (dom, renderer, body) = renderer();
+    renderer.render(&page(body, "x")).unwrap();
+    let label = dom.children(dom.children(body)[0])[1];
+
+    let patches = renderer.render(&page(body, "y")).unwrap();
+
+    assert_eq!(
+        patches,
+        vec![Patch::SetText {
+            id: label,
+            text: "y".to_string(),
+        }]
+    );
+    assert_eq!(dom.inner_html(body), "<div><button></button>y</div>");
+}
+
+#[test]
+fn removing_the_portal_removes_its_children() {
+    let (dom, renderer, body) = renderer();
+    renderer.render(&page(body, "x")).unwrap();
+    let button = dom.query_selector(body, "button").unwrap();
+
+    renderer
+        .render(&VNode::element("div").add_child(VNode::element("span")))
+        .unwrap();
+
+    assert_eq!(dom.inner_html(body), "");
+    assert!(!dom.contains(button));
+    assert_eq!(dom.inner_html(renderer.root()), "<div><span></span></div>");
+}
+
+#[test]
+fn events_bubble_out_of_the_portal_to_its_ancestors() {
+    for delegation in [false, true] {
+        let (dom, renderer, body) = renderer();
+        renderer.set_event_delegation(delegation);
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let record = |name: &'static str| {
+            let calls = Rc::clone(&calls);
+            move |event: &Event| {
+                calls
+                    .borrow_mut()
+                    .push((name, event.target(), event.current_target()))
+            }
+        };
+        let vnode = VNode::element("section")
+            .on("click", record("section"))
+            .add_child(
+                VNode::element("div").on("click", record("div")).add_child(
+                    VNode::portal(body)
+                        .add_child(VNode::element("button").on("click", record("button"))),
+                ),
+            );
+        renderer.render(&vnode).unwrap();
+        let section = dom.children(renderer.root())[0];
+        let div = dom.children(section)[0];
+        let button = dom.query_selector(body, "button").unwrap();
+
+        dom.dispatch(button, "click");
+
+        assert_eq!(
+            *calls.borrow(),
+            [
+                ("button", Some(button), Some(button)),
+                ("div", Some(button), Some(div)),
+                ("section", Some(button), Some(section)),
+            ],
+            "delegation: {}",
+            delegation
+        );
+    }
+}
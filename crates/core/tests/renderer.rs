@@ -0,0 +1,228 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use selene_core::{
+    reactive_render, render_to_string, signal, MockDom, PropertyValue, RenderError, Renderer, VNode,
+};
+
+fn renderer() -> (MockDom, Renderer) {
+    let dom = MockDom::new();
+    let renderer = Renderer::with_backend(dom.clone()).unwrap();
+    (dom, renderer)
+}
+
+fn text(tag: &str, content: &str) -> VNode {
+    VNode::element(tag).add_child(VNode::text(content))
+}
+
+fn list(keys: &[&str]) -> VNode {
+    keys.iter().fold(VNode::element("ul"), |list, key| {
+        list.add_child(text("li", key).set_key(key))
+    })
+}
+
+#[test]
+fn first_render_creates_the_tree() {
+    let (dom, renderer) = renderer();
+    let vnode = VNode::element("div")
+        .set_prop("class", "box")
+        .add_child(text("h1", "Title"))
+        .add_child(VNode::text("body"));
+
+    renderer.render(&vnode).unwrap();
+
+    assert_eq!(
+        dom.inner_html(renderer.root()),
+        r#"<div class="box"><h1>Title</h1>body</div>"#
+    );
+}
+
+#[test]
+fn re_render_updates_nodes_in_place() {
+    let (dom, renderer) = renderer();
+    renderer
+        .render(
+            &VNode::element("div")
+                .set_prop("class", "a")
+                .add_child(VNode::text("one")),
+        )
+        .unwrap();
+    let div = dom.children(renderer.root())[0];
+    let text_node = dom.children(div)[0];
+
+    renderer
+        .render(
+            &VNode::element("div")
+                .set_prop("id", "x")
+                .add_child(VNode::text("two")),
+        )
+        .unwrap();
+
+    assert_eq!(dom.children(renderer.root()), vec![div]);
+    assert_eq!(dom.children(div), vec![text_node]);
+    assert_eq!(dom.inner_html(renderer.root()), r#"<div id="x">two</div>"#);
+}
+
+#[test]
+fn changing_the_tag_replaces_the_node() {
+    let (dom, renderer) = renderer();
+    renderer.render(&text("p", "a")).unwrap();
+    let p = dom.children(renderer.root())[0];
+
+    renderer.render(&text("span", "a")).unwrap();
+
+    assert!(!dom.contains(p));
+    assert_eq!(dom.inner_html(renderer.root()), "<span>a</span>");
+}
+
+#[test]
+fn value_and_checked_are_set_as_properties() {
+    let (dom, renderer) = renderer();
+    let input = |value: &str, checked: bool| {
+        VNode::element("input")
+            .prop("value", value)
+            .prop("checked", checked)
+    };
+
+    renderer.render(&input("a", true)).unwrap();
+    let id = dom.children(renderer.root())[0];
+    assert_eq!(
+        dom.property(id, "value"),
+        Some(PropertyValue::Str("a".to_string()))
+    );
+    assert_eq!(dom.property(id, "checked"), Some(PropertyValue::Bool(true)));
+    assert_eq!(dom.attribute(id, "value"), None);
+    assert_eq!(dom.attribute(id, "checked"), None);
+
+    renderer.render(&input("b", false)).unwrap();
+    assert_eq!(
+        dom.property(id, "value"),
+        Some(PropertyValue::Str("b".to_string()))
+    );
+    assert_eq!(
+        dom.property(id, "checked"),
+        Some(PropertyValue::Bool(false))
+    );
+}
+
+#[test]
+fn keyed_reorder_moves_existing_nodes() {
+    let (dom, renderer) = renderer();
+    renderer.render(&list(&["a", "b", "c", "d", "e"])).unwrap();
+    let ul = dom.children(renderer.root())[0];
+    let by_text = |dom: &MockDom| {
+        dom.children(ul)
+            .into_iter()
+            .map(|id| (dom.text_content(id), id))
+            .collect::<Vec<_>>()
+    };
+    let before = by_text(&dom);
+
+    renderer
+        .render(&list(&["e", "b", "a", "d", "c", "f"]))
+        .unwrap();
+
+    let after = by_text(&dom);
+    let texts: Vec<&str> = after.iter().map(|(text, _)| text.as_str()).collect();
+    assert_eq!(texts, ["e", "b", "a", "d", "c", "f"]);
+    // Every surviving item is still the node it was mounted as
+    for (text, id) in &before {
+        assert!(after.contains(&(text.clone(), *id)));
+    }
+}
+
+#[test]
+fn removed_children_leave_the_dom() {
+    let (dom, renderer) = renderer();
+    renderer.render(&list(&["a", "b", "c"])).unwrap();
+    let ul = dom.children(renderer.root())[0];
+    let b = dom.children(ul)[1];
+    let b_text = dom.children(b)[0];
+
+    renderer.render(&list(&["a", "c"])).unwrap();
+
+    assert!(!dom.contains(b));
+    assert!(!dom.contains(b_text));
+    assert_eq!(dom.inner_html(ul), "<li>a</li><li>c</li>");
+}
+
+#[test]
+fn unmount_empties_the_container() {
+    let (dom, renderer) = renderer();
+    renderer.render(&list(&["a", "b"])).unwrap();
+    let ul = dom.children(renderer.root())[0];
+
+    renderer.unmount().unwrap();
+
+    assert!(!dom.contains(ul));
+    assert_eq!(dom.inner_html(renderer.root()), "");
+}
+
+#[test]
+fn static_instance_is_skipped_on_re_render() {
+    let (dom, renderer) = renderer();
+    let hoisted = text("p", "A").mark_static();
+
+    renderer.render(&hoisted.clone()).unwrap();
+    let patches = renderer.render(&hoisted.clone()).unwrap();
+
+    assert!(patches.is_empty());
+    assert_eq!(dom.inner_html(renderer.root()), "<p>A</p>");
+}
+
+#[test]
+fn different_static_vnodes_are_diffed() {
+    let (dom, renderer) = renderer();
+
+    renderer.render(&text("p", "A").mark_static()).unwrap();
+    let patches = renderer.render(&text("p", "B").mark_static()).unwrap();
+
+    assert_eq!(patches.len(), 1);
+    assert_eq!(dom.inner_html(renderer.root()), "<p>B</p>");
+}
+
+#[test]
+fn reactive_render_fails_when_the_first_render_fails() {
+    let (_dom, renderer) = renderer();
+
+    let result = reactive_render(renderer, || VNode::element("not a tag"));
+
+    assert!(matches!(result, Err(RenderError::Backend(_))));
+}
+
+#[test]
+fn reactive_render_keeps_the_error_of_the_latest_re_render() {
+    let (_dom, renderer) = renderer();
+    let tag = signal("p".to_string());
+    let handle = reactive_render(renderer, {
+        let tag = tag.clone();
+        move || VNode::element(&tag.get())
+    })
+    .unwrap();
+    assert_eq!(handle.last_error(), None);
+
+    tag.set("not a tag".to_string());
+    assert!(matches!(handle.last_error(), Some(RenderError::Backend(_))));
+}
+
+#[test]
+fn elements_named_like_special_nodes_are_plain_elements() {
+    let (dom, renderer) = renderer();
+    let names = ["FRAGMENT", "COMMENT", "TEXT", "PORTAL", "SUSPENSE"];
+    let vnode = names.iter().fold(VNode::element("div"), |div, name| {
+        div.add_child(text(name, "x"))
+    });
+
+    renderer.render(&vnode).unwrap();
+
+    let html: String = names
+        .iter()
+        .map(|name| format!("<{0}>x</{0}>", name))
+        .collect();
+    assert_eq!(
+        dom.inner_html(renderer.root()),
+        format!("<div>{}</div>", html)
+    );
+    assert_eq!(render_to_string(&vnode), format!("<div>{}</div>", html));
+    assert_ne!(VNode::element("TEXT"), VNode::text(""));
+    assert_ne!(VNode::element("FRAGMENT"), VNode::fragment());
+}
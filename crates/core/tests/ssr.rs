@@ -0,0 +1,176 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use selene_core::{render_to_string, MockDom, PropValue, RenderError, Renderer, VNode};
+
+fn invalid() -> VNode {
+    VNode::element("bad tag").add_child(VNode::text("lost"))
+}
+
+fn boundary(content: VNode) -> VNode {
+    VNode::error_boundary(
+        |error: &RenderError, _| VNode::element("p").add_child(VNode::text(&error.to_string())),
+        content,
+    )
+}
+
+#[test]
+fn invalid_tag_outside_a_boundary_leaves_a_placeholder() {
+    let vnode = VNode::element("div")
+        .add_child(invalid())
+        .add_child(VNode::element("span"));
+
+    assert_eq!(render_to_string(&vnode), "<div><!----><span></span></div>");
+}
+
+#[test]
+fn invalid_tag_fails_the_enclosing_boundary_like_on_the_client() {
+    let vnode =
+        VNode::element("div").add_child(boundary(VNode::element("section").add_child(invalid())));
+
+    let html = render_to_string(&vnode);
+
+    let dom = MockDom::new();
+    let renderer = Renderer::with_backend(dom.clone()).unwrap();
+    renderer.render(&vnode).unwrap();
+    let fallback = dom.inner_html(dom.children(renderer.root())[0]);
+    assert!(fallback.contains("invalid tag name"), "{}", fallback);
+    let fallback = fallback.trim_end_matches("<!---->");
+    assert_eq!(
+        html,
+        format!("<div><!--eb-->{}<!--/eb--><!----></div>", fallback)
+    );
+}
+
+#[test]
+fn inner_boundary_catches_before_the_outer_one() {
+    let vnode = boundary(
+        VNode::element("div")
+            .add_child(boundary(invalid()))
+            .add_child(VNode::text("kept")),
+    );
+
+    let html = render_to_string(&vnode);
+
+    assert!(html.starts_with("<div><!--eb--><p>"), "{}", html);
+    assert!(
+        html.ends_with("<!--/eb--><!---->kept</div><!---->"),
+        "{}",
+        html
+    );
+}
+
+#[test]
+fn props_named_like_handlers_are_rendered() {
+    let vnode = VNode::element("p")
+        .set_prop("only", "a")
+        .set_prop("onclick", "go()");
+
+    assert_eq!(
+        render_to_string(&vnode),
+        r#"<p onclick="go()" only="a"></p>"#
+    );
+}
+
+#[test]
+fn text_and_attributes_are_escaped() {
+    let vnode = VNode::element("p")
+        .set_prop("title", r#"a&<>"b"#)
+        .add_child(VNode::text(r#"x & <y> "z""#));
+
+    assert_eq!(
+        render_to_string(&vnode),
+        r#"<p title="a&amp;&lt;&gt;&quot;b">x &amp; &lt;y&gt; "z"</p>"#
+    );
+}
+
+#[test]
+fn void_elements_have_no_closing_tag() {
+    let vnode = VNode::element("div")
+        .add_child(VNode::element("br"))
+        .add_child(VNode::element("img").set_prop("src", "a.png"))
+        .add_child(VNode::element("input"));
+
+    assert_eq!(
+        render_to_string(&vnode),
+        r#"<div><br><img src="a.png"><input></div>"#
+    );
+}
+
+#[test]
+fn true_booleans_are_bare_and_false_ones_omitted() {
+    let vnode = VNode::element("button")
+        .prop("disabled", true)
+        .prop("hidden", false);
+
+    assert_eq!(render_to_string(&vnode), "<button disabled></button>");
+}
+
+#[test]
+fn styles_and_classes_are_sorted() {
+    let vnode = VNode::element("p")
+        .prop(
+            "style",
+            PropValue::style([("margin", "0"), ("color", "red")]),
+        )
+        .prop("class", PropValue::classes(["c a", "b"]));
+
+    assert_eq!(
+        render_to_string(&vnode),
+        r#"<p class="a b c" style="color: red; margin: 0;"></p>"#
+    );
+}
+
+#[test]
+fn properties_render_as_attributes_and_textarea_value_as_content() {
+    let input = VNode::element("input")
+        .prop("value", "v")
+        .prop("checked", true);
+    let textarea = VNode::element("textarea").prop("value", "hi <b>");
+
+    assert_eq!(render_to_string(&input), r#"<input checked value="v">"#);
+    assert_eq!(
+        render_to_string(&textarea),
+        "<textarea>hi &lt;b&gt;</textarea>"
+    );
+}
+
+#[test]
+fn adjacent_texts_are_separated() {
+    let vnode = VNode::element("p")
+        .add_child(VNode::text("a"))
+        .add_child(VNode::text("b"))
+        .add_child(VNode::element("i"))
+        .add_child(VNode::text("c"));
+
+    assert_eq!(render_to_string(&vnode), "<p>a<!-- -->b<i></i>c</p>");
+}
+
+#[test]
+fn fragments_end_with_an_anchor() {
+    let fragment = VNode::fragment()
+        .add_child(VNode::text("a"))
+        .add_child(VNode::element("i"));
+    let vnode = VNode::element("div")
+        .add_child(fragment)
+        .add_child(VNode::fragment());
+
+    assert_eq!(
+        render_to_string(&vnode),
+        "<div>a<i></i><!----><!----></div>"
+    );
+}
+
+#[test]
+fn closing_tags_are_escaped_in_scripts_and_styles() {
+    let script = VNode::element("script").add_child(VNode::text("if (a </b) '</script>'"));
+    let style = VNode::element("style").add_child(VNode::text("a::after { content: '</style>' }"));
+
+    assert_eq!(
+        render_to_string(&script),
+        r"<script>if (a <\/b) '<\/script>'</script>"
+    );
+    assert_eq!(
+        render_to_string(&style),
+        r"<style>a::after { content: '<\/style>' }</style>"
+    );
+}
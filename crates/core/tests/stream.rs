@@ -0,0 +1,118 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+mod common;
+
+use std::cell::Cell;
+use std::future::poll_fn;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use common::Gate;
+use selene_core::{render_to_stream, HtmlStream, VNode};
+
+/// Start of the script defining `$sl`, sent before the first streamed boundary
+const SWAP_SCRIPT_START: &str = "<script>function $sl(i){";
+
+fn poll(stream: &mut HtmlStream) -> Poll<Option<String>> {
+    stream.poll_chunk(&mut Context::from_waker(Waker::noop()))
+}
+
+/// Chunk of boundary `id` with `content`, past the swap script if any
+fn swap(id: u32, content: &str) -> String {
+    format!(
+        "<template id=\"sl-{0}\">{1}</template><script>$sl({0})</script>",
+        id, content
+    )
+}
+
+/// Next chunk, with the swap script cut off; returns whether it had one
+fn next_chunk(stream: &mut HtmlStream) -> (bool, String) {
+    let Poll::Ready(Some(chunk)) = poll(stream) else {
+        panic!("expected a chunk");
+    };
+    match chunk.find("</script><template") {
+        Some(end) if chunk.starts_with(SWAP_SCRIPT_START) => {
+            (true, chunk[end + "</script>".len()..].to_string())
+        }
+        _ => (false, chunk),
+    }
+}
+
+#[test]
+fn first_chunk_holds_fallbacks_between_markers() {
+    let first = Gate::default();
+    let second = Gate::default();
+    let page = VNode::element("main")
+        .add_child(VNode::async_boundary(VNode::text("loading"), first.wait()))
+        .add_child(VNode::async_boundary(VNode::element("i"), second.wait()));
+    let mut stream = render_to_stream(&page);
+
+    assert_eq!(
+        poll(&mut stream),
+        Poll::Ready(Some(
+            "<main><!--sl:0-->loading<!--/sl:0--><!--sl:1--><i></i><!--/sl:1--></main>".to_string()
+        ))
+    );
+    assert_eq!(poll(&mut stream), Poll::Pending);
+}
+
+#[test]
+fn boundaries_are_streamed_as_they_resolve_with_the_swap_script_once() {
+    let first = Gate::default();
+    let second = Gate::default();
+    let page = VNode::element("main")
+        .add_child(VNode::async_boundary(VNode::text("..."), first.wait()))
+        .add_child(VNode::async_boundary(VNode::text("..."), second.wait()));
+    let mut stream = render_to_stream(&page);
+    assert!(poll(&mut stream).is_ready());
+
+    second.open(VNode::text("b"));
+    assert_eq!(next_chunk(&mut stream), (true, swap(1, "b")));
+    assert_eq!(poll(&mut stream), Poll::Pending);
+    first.open(VNode::text("a"));
+    assert_eq!(next_chunk(&mut stream), (false, swap(0, "a")));
+    assert_eq!(poll(&mut stream), Poll::Ready(None));
+}
+
+#[test]
+fn boundaries_in_streamed_content_are_streamed_in_turn() {
+    let outer = Gate::default();
+    let inner = Gate::default();
+    let page = VNode::async_boundary(VNode::text("..."), outer.wait());
+    let mut stream = render_to_stream(&page);
+    assert!(poll(&mut stream).is_ready());
+
+    outer.open(
+        VNode::element("p").add_child(VNode::async_boundary(VNode::text("more"), inner.wait())),
+    );
+    assert_eq!(
+        next_chunk(&mut stream),
+        (true, swap(0, "<p><!--sl:1-->more<!--/sl:1--></p>"))
+    );
+    inner.open(VNode::text("done"));
+    assert_eq!(next_chunk(&mut stream), (false, swap(1, "done")));
+    assert_eq!(poll(&mut stream), Poll::Ready(None));
+}
+
+#[test]
+fn write_to_writes_every_chunk() {
+    // Pending once, waking the stream right away
+    let polled = Rc::new(Cell::new(false));
+    let content = poll_fn(move |cx| {
+        if polled.replace(true) {
+            return Poll::Ready(VNode::text("ready"));
+        }
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    });
+    let page = VNode::element("div").add_child(VNode::async_boundary(VNode::text("..."), content));
+    let mut out = Vec::new();
+
+    render_to_stream(&page).write_to(&mut out).unwrap();
+
+    let html = String::from_utf8(out).unwrap();
+    let (first, rest) = html.split_at(html.find(SWAP_SCRIPT_START).unwrap());
+    assert_eq!(first, "<div><!--sl:0-->...<!--/sl:0--></div>");
+    assert!(rest.ends_with(&swap(0, "ready")), "{}", rest);
+    assert_eq!(html.matches(SWAP_SCRIPT_START).count(), 1);
+}
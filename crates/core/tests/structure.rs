@@ -0,0 +1,112 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use selene_core::{DifferenceKind, PropValue, VNode, VNodeDifference};
+
+fn hash_of<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn todo(key: &str, content: &str) -> VNode {
+    VNode::element("li")
+        .set_key(key)
+        .add_child(VNode::text(content))
+}
+
+#[test]
+fn props_compare_whatever_order_they_were_set_in() {
+    let a = VNode::element("p")
+        .set_prop("id", "x")
+        .set_prop("title", "y");
+    let b = VNode::element("p")
+        .set_prop("title", "y")
+        .set_prop("id", "x");
+
+    assert_eq!(a, b);
+    assert_eq!(hash_of(&a), hash_of(&b));
+    assert_ne!(a, VNode::element("p").set_prop("id", "x"));
+}
+
+#[test]
+fn nan_props_are_equal() {
+    let nan = PropValue::Number(f64::NAN);
+
+    assert_eq!(nan, PropValue::Number(-f64::NAN));
+    assert_eq!(hash_of(&nan), hash_of(&PropValue::Number(f64::NAN)));
+    assert_eq!(
+        VNode::element("meter").prop("value", f64::NAN),
+        VNode::element("meter").prop("value", f64::NAN)
+    );
+}
+
+#[test]
+fn equal_trees_hash_equally() {
+    let tree = || {
+        VNode::element("ul")
+            .prop("class", PropValue::classes(["b a"]))
+            .on("click", |_| {})
+            .add_child(todo("1", "one"))
+    };
+
+    assert_eq!(tree(), tree());
+    assert_eq!(hash_of(&tree()), hash_of(&tree()));
+}
+
+#[test]
+fn display_prints_an_indented_snapshot() {
+    let vnode = VNode::element("ul")
+        .set_prop("class", "todos")
+        .add_child(
+            todo("1", "Write docs")
+                .prop("data-n", 3)
+                .on("click", |_| {}),
+        )
+        .add_child(
+            VNode::element("input")
+                .prop("value", "a")
+                .prop("disabled", true),
+        )
+        .add_child(VNode::suspense(VNode::text("..."), VNode::element("p")));
+
+    assert_eq!(
+        vnode.to_string(),
+        r#"<ul class="todos">
+  <li key="1" data-n={3} on:click>
+    "Write docs"
+  </li>
+  <input disabled .value={"a"} />
+  <#suspense>
+    <#fallback>
+      "..."
+    </#fallback>
+    <p />
+  </#suspense>
+</ul>"#
+    );
+}
+
+#[test]
+fn differences_are_located_by_path() {
+    let old = VNode::element("ul").add_child(todo("a", "x"));
+    let new = VNode::element("ul").add_child(todo("a", "y"));
+
+    let differences = old.differences(&new);
+
+    assert_eq!(
+        differences,
+        [VNodeDifference {
+            path: r#"ul > li[key="a"] > #text[0]"#.to_string(),
+            kind: DifferenceKind::Text {
+                old: "x".to_string(),
+                new: "y".to_string(),
+            },
+        }]
+    );
+    assert_eq!(
+        differences[0].to_string(),
+        r#"ul > li[key="a"] > #text[0]: text changed from "x" to "y""#
+    );
+    assert!(old.differences(&old.clone()).is_empty());
+}
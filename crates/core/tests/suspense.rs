@@ -0,0 +1,134 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+mod common;
+
+use std::cell::RefCell;
+
+use common::Gate;
+use selene_core::{
+    poll_resources, render_to_string, resource, Component, MockDom, NodeId, Renderer, Resource,
+    VNode,
+};
+
+thread_local! {
+    /// Resource the next `Profile` loads its name from
+    static NAME: RefCell<Option<Resource<String>>> = const { RefCell::new(None) };
+    /// DOM and container the test renders into
+    static CONTAINER: RefCell<Option<(MockDom, NodeId)>> = const { RefCell::new(None) };
+    /// HTML the container held each time a `Profile` was mounted
+    static MOUNTED_IN: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Gate completing the name the next `Profile` loads
+fn load_name() -> Gate<String> {
+    let gate = Gate::default();
+    NAME.with(|name| *name.borrow_mut() = Some(resource(gate.wait())));
+    gate
+}
+
+fn mounted_in() -> Vec<String> {
+    MOUNTED_IN.with(|mounted| mounted.take())
+}
+
+struct Profile {
+    name: Resource<String>,
+}
+
+impl Component for Profile {
+    type Props = ();
+
+    fn create(_: &()) -> Self {
+        Profile {
+            name: NAME.with(|name| name.borrow().clone()).unwrap(),
+        }
+    }
+
+    fn render(&self, _: &(), _children: &[VNode]) -> VNode {
+        let name = self.name.read().unwrap_or_default();
+        VNode::element("span").add_child(VNode::text(&name))
+    }
+
+    fn on_mount(&self, _: &()) {
+        let html = CONTAINER.with(|container| {
+            let (dom, root) = container.borrow().clone().unwrap();
+            dom.inner_html(root)
+        });
+        MOUNTED_IN.with(|mounted| mounted.borrow_mut().push(html));
+    }
+}
+
+fn page() -> VNode {
+    VNode::element("div").add_child(VNode::suspense(
+        VNode::element("p").add_child(VNode::text("Loading")),
+        VNode::component::<Profile>(()),
+    ))
+}
+
+fn renderer() -> (MockDom, Renderer) {
+    let dom = MockDom::new();
+    let renderer = Renderer::with_backend(dom.clone()).unwrap();
+    CONTAINER.with(|container| *container.borrow_mut() = Some((dom.clone(), renderer.root())));
+    (dom, renderer)
+}
+
+#[test]
+fn pending_content_shows_the_fallback() {
+    let (dom, renderer) = renderer();
+    let _gate = load_name();
+
+    renderer.render(&page()).unwrap();
+
+    assert_eq!(
+        dom.inner_html(renderer.root()),
+        "<div><p>Loading</p><!----></div>"
+    );
+    assert!(mounted_in().is_empty());
+}
+
+#[test]
+fn ready_content_replaces_the_fallback_and_is_mounted_then() {
+    let (dom, renderer) = renderer();
+    let gate = load_name();
+    renderer.render(&page()).unwrap();
+
+    gate.open("Ada".to_string());
+    assert!(mounted_in().is_empty());
+    poll_resources();
+
+    let html = "<div><span>Ada</span><!----></div>";
+    assert_eq!(dom.inner_html(renderer.root()), html);
+    assert_eq!(mounted_in(), [html]);
+}
+
+#[test]
+fn server_renders_the_fallback_of_pending_content() {
+    let _gate = load_name();
+
+    assert_eq!(
+        render_to_string(&page()),
+        "<div><!--sl--><p>Loading</p><!----></div>"
+    );
+}
+
+#[test]
+fn hydrated_fallback_is_replaced_once_the_content_is_ready() {
+    let (dom, renderer) = renderer();
+    let _server_gate = load_name();
+    let html = render_to_string(&page());
+    dom.set_inner_html(renderer.root(), &html).unwrap();
+
+    let gate = load_name();
+    renderer.hydrate(&page()).unwrap();
+    assert_eq!(
+        dom.inner_html(renderer.root()),
+        "<div><p>Loading</p><!----></div>"
+    );
+    assert!(mounted_in().is_empty());
+
+    gate.open("Bob".to_string());
+    poll_resources();
+
+    let html = "<div><span>Bob</span><!----></div>";
+    assert_eq!(dom.inner_html(renderer.root()), html);
+    assert_eq!(mounted_in(), [html]);
+}
@@ -0,0 +1,157 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use selene_core::{MockDom, NodeId, Patch, Renderer, Transition, TransitionEvent, VNode};
+
+fn renderer() -> (MockDom, Renderer) {
+    let dom = MockDom::new();
+    let renderer = Renderer::with_backend(dom.clone()).unwrap();
+    (dom, renderer)
+}
+
+/// Phase started, with the classes of the element as it started
+type Phase = (TransitionEvent, Option<String>);
+
+/// Phases started so far
+#[derive(Clone, Default)]
+struct Phases(Rc<RefCell<Vec<Phase>>>);
+
+impl Phases {
+    /// `fade` transition whose phases last until `done` is called
+    fn transition(&self, dom: &MockDom) -> Transition {
+        let record = |phases: &Phases| {
+            let (phases, dom) = (phases.clone(), dom.clone());
+            move |event: TransitionEvent| {
+                let classes = dom.attribute(event.node(), "class");
+                phases.0.borrow_mut().push((event, classes));
+            }
+        };
+        Transition::new()
+            .name("fade")
+            .on_enter(record(self))
+            .on_leave(record(self))
+    }
+
+    fn take(&self) -> Vec<Phase> {
+        self.0.take()
+    }
+}
+
+fn list(item: Option<&Transition>) -> VNode {
+    let list = VNode::element("div");
+    match item {
+        Some(transition) => list.add_child(
+            VNode::element("p")
+                .set_key("a")
+                .transition(transition.clone()),
+        ),
+        None => list,
+    }
+}
+
+fn add_class(id: NodeId, name: &str) -> Patch {
+    Patch::AddClass {
+        id,
+        name: name.to_string(),
+    }
+}
+
+#[test]
+fn css_phases_end_right_away_on_native() {
+    let (dom, renderer) = renderer();
+    let fade = Transition::new().name("fade");
+
+    renderer.render(&list(Some(&fade))).unwrap();
+    assert_eq!(
+        dom.inner_html(renderer.root()),
+        r#"<div><p class=""></p></div>"#
+    );
+
+    renderer.render(&list(None)).unwrap();
+    assert_eq!(dom.inner_html(renderer.root()), "<div></div>");
+}
+
+#[test]
+fn entering_element_goes_through_the_enter_classes() {
+    let (dom, renderer) = renderer();
+    let phases = Phases::default();
+
+    let patches = renderer
+        .render(&list(Some(&phases.transition(&dom))))
+        .unwrap();
+
+    let p = NodeId(2);
+    let classes_added = patches
+        .iter()
+        .position(|patch| *patch == add_class(p, "fade-enter-from"))
+        .unwrap();
+    let inserted = patches
+        .iter()
+        .position(|patch| matches!(patch, Patch::InsertBefore { id, .. } if *id == p))
+        .unwrap();
+    assert!(classes_added < inserted, "{:?}", patches);
+    assert!(patches.contains(&add_class(p, "fade-enter-active")));
+    let (enter, classes) = phases.take().pop().unwrap();
+    assert_eq!(classes.as_deref(), Some("fade-enter-active fade-enter-to"));
+
+    enter.done();
+    assert_eq!(dom.attribute(p, "class").as_deref(), Some(""));
+    assert!(!enter.is_active());
+}
+
+#[test]
+fn leaving_element_stays_until_done() {
+    let (dom, renderer) = renderer();
+    let phases = Phases::default();
+    renderer
+        .render(&list(Some(&phases.transition(&dom))))
+        .unwrap();
+    phases.take().pop().unwrap().0.done();
+
+    let patches = renderer.render(&list(None)).unwrap();
+
+    let p = NodeId(2);
+    assert_eq!(
+        patches,
+        [
+            add_class(p, "fade-leave-from"),
+            add_class(p, "fade-leave-active")
+        ]
+    );
+    let (leave, classes) = phases.take().pop().unwrap();
+    assert_eq!(classes.as_deref(), Some("fade-leave-active fade-leave-to"));
+    assert_eq!(
+        dom.inner_html(renderer.root()),
+        r#"<div><p class="fade-leave-active fade-leave-to"></p></div>"#
+    );
+
+    leave.done();
+    assert_eq!(dom.inner_html(renderer.root()), "<div></div>");
+    assert!(!dom.contains(p));
+}
+
+#[test]
+fn re_adding_a_leaving_key_removes_the_old_element_right_away() {
+    let (dom, renderer) = renderer();
+    let phases = Phases::default();
+    let fade = phases.transition(&dom);
+    renderer.render(&list(Some(&fade))).unwrap();
+    phases.take().pop().unwrap().0.done();
+    renderer.render(&list(None)).unwrap();
+    let (leave, _) = phases.take().pop().unwrap();
+
+    let patches = renderer.render(&list(Some(&fade))).unwrap();
+
+    assert!(
+        patches.contains(&Patch::Remove { id: NodeId(2) }),
+        "{:?}",
+        patches
+    );
+    assert!(!leave.is_active());
+    assert_eq!(
+        dom.inner_html(renderer.root()),
+        r#"<div><p class="fade-enter-active fade-enter-to"></p></div>"#
+    );
+}
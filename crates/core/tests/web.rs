@@ -0,0 +1,264 @@
+#![cfg(target_arch = "wasm32")]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use js_sys::{Function, Reflect};
+use selene_core::{
+    js_batch, js_computed, js_effect, DomBackend, Event, FromJsError, FromJsErrorKind, JsSignal,
+    NodeId, Renderer, VNode, WebDom,
+};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn web_dom() -> WebDom {
+    WebDom::new(web_sys::window().unwrap().document().unwrap())
+}
+
+fn click(dom: &WebDom, id: NodeId) {
+    let node = dom.node(id).unwrap();
+    node.unchecked_ref::<web_sys::HtmlElement>().click();
+}
+
+/// Value of the JS expression `source`
+fn js(source: &str) -> JsValue {
+    Function::new_no_args(&format!("return ({})", source))
+        .call0(&JsValue::NULL)
+        .unwrap()
+}
+
+fn message(err: &JsValue) -> Option<String> {
+    Reflect::get(err, &JsValue::from_str("message"))
+        .ok()?
+        .as_string()
+}
+
+#[wasm_bindgen_test]
+fn effect_throwing_on_its_first_run_throws() {
+    let err = js_effect(Function::new_no_args("throw new Error('boom')")).unwrap_err();
+    assert_eq!(message(&err).as_deref(), Some("boom"));
+}
+
+#[wasm_bindgen_test]
+fn effect_throwing_later_throws_from_the_write() {
+    let source = JsValue::from(JsSignal::new(0.0));
+    Reflect::set(&js_sys::global(), &"seleneSource".into(), &source).unwrap();
+    let _dispose = js_effect(Function::new_no_args(
+        "if (globalThis.seleneSource.value > 0) throw new Error('later')",
+    ))
+    .unwrap();
+
+    let err = Reflect::set(&source, &"value".into(), &1.0.into()).unwrap_err();
+    assert_eq!(message(&err).as_deref(), Some("later"));
+}
+
+#[wasm_bindgen_test]
+fn computed_throwing_on_its_first_run_throws() {
+    let err = js_computed(Function::new_no_args("throw new Error('bad')")).err();
+    assert_eq!(err.as_ref().and_then(message).as_deref(), Some("bad"));
+}
+
+#[wasm_bindgen_test]
+fn batch_throws_what_its_function_threw() {
+    let err = js_batch(&Function::new_no_args("throw new Error('inner')")).unwrap_err();
+    assert_eq!(message(&err).as_deref(), Some("inner"));
+}
+
+#[wasm_bindgen_test]
+fn removing_a_node_forgets_its_descendants() {
+    let mut dom = web_dom();
+    dom.create_element(NodeId(1), "div").unwrap();
+    dom.create_element(NodeId(2), "button").unwrap();
+    dom.insert_before(NodeId(1), NodeId(2), None).unwrap();
+    dom.add_event_listener(NodeId(2), "click", Rc::new(|_| {}))
+        .unwrap();
+
+    dom.remove(NodeId(1)).unwrap();
+
+    assert!(dom.node(NodeId(1)).is_none());
+    assert!(dom.node(NodeId(2)).is_none());
+}
+
+#[wasm_bindgen_test]
+fn removing_a_node_keeps_descendant_ids_already_reused() {
+    let mut dom = web_dom();
+    dom.create_element(NodeId(1), "div").unwrap();
+    dom.create_element(NodeId(2), "span").unwrap();
+    dom.insert_before(NodeId(1), NodeId(2), None).unwrap();
+    // Replacing the div creates its successor before removing it
+    dom.create_element(NodeId(2), "p").unwrap();
+
+    dom.remove(NodeId(1)).unwrap();
+
+    let reused = dom.node(NodeId(2)).expect("reused id stays mapped");
+    assert_eq!(reused.node_name(), "P");
+}
+
+#[wasm_bindgen_test]
+fn direct_listener_reports_the_clicked_node_as_target() {
+    let mut dom = web_dom();
+    dom.create_element(NodeId(1), "div").unwrap();
+    dom.create_element(NodeId(2), "button").unwrap();
+    dom.insert_before(NodeId(1), NodeId(2), None).unwrap();
+    let seen = Rc::new(RefCell::new(None));
+    dom.add_event_listener(NodeId(1), "click", {
+        let seen = Rc::clone(&seen);
+        Rc::new(move |event: &Event| {
+            *seen.borrow_mut() = Some((
+                event.target(),
+                event.current_target(),
+                event.path().to_vec(),
+            ));
+        })
+    })
+    .unwrap();
+
+    click(&dom, NodeId(2));
+
+    assert_eq!(
+        seen.borrow_mut().take(),
+        Some((Some(NodeId(2)), Some(NodeId(1)), vec![NodeId(2), NodeId(1)]))
+    );
+}
+
+#[wasm_bindgen_test]
+fn direct_listeners_are_replaced_and_removed() {
+    let mut dom = web_dom();
+    dom.create_element(NodeId(1), "button").unwrap();
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let listener = |name: &'static str| {
+        let calls = Rc::clone(&calls);
+        Rc::new(move |_: &Event| calls.borrow_mut().push(name))
+    };
+
+    dom.add_event_listener(NodeId(1), "click", listener("first"))
+        .unwrap();
+    click(&dom, NodeId(1));
+    dom.add_event_listener(NodeId(1), "click", listener("second"))
+        .unwrap();
+    click(&dom, NodeId(1));
+    dom.remove_event_listener(NodeId(1), "click").unwrap();
+    click(&dom, NodeId(1));
+
+    assert_eq!(*calls.borrow(), ["first", "second"]);
+}
+
+#[wasm_bindgen_test]
+fn js_handler_exceptions_are_thrown_from_the_listener() {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let container = document.create_element("div").unwrap();
+    let renderer = Renderer::new().unwrap();
+    let button = VNode::element("button")
+        .on_js("click", Function::new_no_args("throw new Error('handler')"));
+    renderer.render(&button, &container).unwrap();
+    // Uncaught listener exceptions are reported to `window.onerror`
+    let window = JsValue::from(web_sys::window().unwrap());
+    let on_error = Function::new_with_args(
+        "message, source, line, column, error",
+        "globalThis.seleneHandlerError = error; return true",
+    );
+    Reflect::set(&window, &"onerror".into(), &on_error).unwrap();
+
+    let button = container.first_element_child().unwrap();
+    button.unchecked_ref::<web_sys::HtmlElement>().click();
+
+    Reflect::set(&window, &"onerror".into(), &JsValue::NULL).unwrap();
+    let err = Reflect::get(&js_sys::global(), &"seleneHandlerError".into()).unwrap();
+    assert_eq!(message(&err).as_deref(), Some("handler"));
+}
+
+#[wasm_bindgen_test]
+fn from_js_reports_where_the_input_is_malformed() {
+    let err = VNode::from_js(&js("{type: 'ul', children: [{type: 3}]}")).unwrap_err();
+    assert_eq!(
+        err,
+        FromJsError {
+            path: "vnode.children[0].type".to_string(),
+            kind: FromJsErrorKind::InvalidType {
+                found: "number 3".to_string(),
+            },
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "vnode.children[0].type: expected a tag name, Fragment or component function as type, found number 3"
+    );
+
+    let err = VNode::from_js(&js("{type: 'ul', children: 'x'}")).unwrap_err();
+    assert_eq!(err.path, "vnode.children");
+    assert_eq!(
+        err.kind,
+        FromJsErrorKind::InvalidChildren {
+            found: r#"string "x""#.to_string(),
+        }
+    );
+
+    let err = VNode::from_js(&js("{type: 'p', props: {class: () => 1}}")).unwrap_err();
+    assert_eq!(err.path, "vnode.props.class");
+    assert_eq!(
+        err.kind,
+        FromJsErrorKind::InvalidProp {
+            reason: "functions are only supported as `on*` event handlers".to_string(),
+        }
+    );
+}
+
+#[wasm_bindgen_test]
+fn from_js_turns_the_fragment_symbol_and_arrays_into_fragments() {
+    let vnode = VNode::from_js(&js(
+        "{type: Symbol.for('selene.fragment'), props: null, children: ['a', ['b', 1]]}",
+    ))
+    .unwrap();
+
+    assert_eq!(
+        vnode,
+        VNode::fragment().add_child(VNode::text("a")).add_child(
+            VNode::fragment()
+                .add_child(VNode::text("b"))
+                .add_child(VNode::text("1"))
+        )
+    );
+}
+
+#[wasm_bindgen_test]
+fn from_js_keeps_holes_as_empty_placeholders() {
+    let vnode = VNode::from_js(&js(
+        "{type: 'div', children: [null, 'a', false, undefined, true]}",
+    ))
+    .unwrap();
+
+    assert_eq!(
+        vnode,
+        VNode::element("div")
+            .add_child(VNode::empty())
+            .add_child(VNode::text("a"))
+            .add_child(VNode::empty())
+            .add_child(VNode::empty())
+            .add_child(VNode::empty())
+    );
+}
+
+#[wasm_bindgen_test]
+fn from_js_turns_on_props_into_handlers() {
+    let vnode = VNode::from_js(&js(
+        "{type: 'button', props: {title: 'x', onClick: () => { globalThis.seleneClicked = true }}}",
+    ))
+    .unwrap();
+    assert_eq!(
+        vnode,
+        VNode::element("button")
+            .set_prop("title", "x")
+            .on("click", |_| {})
+    );
+
+    let document = web_sys::window().unwrap().document().unwrap();
+    let container = document.create_element("div").unwrap();
+    Renderer::new().unwrap().render(&vnode, &container).unwrap();
+    let button = container.first_element_child().unwrap();
+    button.unchecked_ref::<web_sys::HtmlElement>().click();
+
+    let clicked = Reflect::get(&js_sys::global(), &"seleneClicked".into()).unwrap();
+    assert_eq!(clicked.as_bool(), Some(true));
+}
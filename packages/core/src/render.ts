@@ -39,7 +39,7 @@ function createElement(vnode: VNode): Node | null {
     for (const [key, value] of Object.entries(vnode.props)) {
       if (key.startsWith('on') && typeof value === 'function') {
         element.addEventListener(key.slice(2).toLowerCase(), value)
-      } else if (key !== 'children') {
+      } else if (key !== 'children' && key !== '$static' && key !== '$dynamic') {
         element.setAttribute(key, String(value))
       }
     }
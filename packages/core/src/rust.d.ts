@@ -5,6 +5,12 @@ declare module "./rust/selene_core.js" {
     get value(): number;
     set value(value: number);
   }
+  export class JsComputed {
+    get value(): any;
+  }
+  export function effect(fn: () => void): () => void;
+  export function computed(fn: () => any): JsComputed;
+  export function batch<T>(fn: () => T): T;
   export function compile_template(input: string): string;
 }
 
@@ -16,6 +22,12 @@ declare module "./rust/selene_core" {
     get value(): number;
     set value(value: number);
   }
+  export class JsComputed {
+    get value(): any;
+  }
+  export function effect(fn: () => void): () => void;
+  export function computed(fn: () => any): JsComputed;
+  export function batch<T>(fn: () => T): T;
   export function compile_template(input: string): string;
 }
 
@@ -26,21 +38,61 @@ declare module "@selene/core/rust" {
     set value(value: number);
   }
 
+  export class JsComputed {
+    get value(): any;
+  }
+
+  export function effect(fn: () => void): () => void;
+  export function computed(fn: () => any): JsComputed;
+  export function batch<T>(fn: () => T): T;
+
   export class VNode {
     constructor();
     element(nodeType: string): VNode;
     text(content: string): VNode;
-    set_prop(key: string, value: string): VNode;
+    fragment(): VNode;
+    empty(): VNode;
+    portal(target: Element): VNode;
+    mark_static(): VNode;
+    dynamic_props(names: string[]): VNode;
+    static from_js(value: unknown): VNode;
+    set_prop(
+      key: string,
+      value: string | number | boolean | string[] | Record<string, unknown> | null | undefined
+    ): VNode;
+    set_key(key: string): VNode;
+    on(event: string, handler: (event: Event) => void): VNode;
     add_child(child: VNode): VNode;
+    node_ref(ref: NodeRef): VNode;
     set_text(content: string): VNode;
+    equals(other: VNode): boolean;
+    toString(): string;
+  }
+
+  export class NodeRef {
+    constructor();
+    readonly current: Node | null;
+    free(): void;
   }
 
   export class Renderer {
     constructor();
     render(vnode: VNode, container: HTMLElement): void;
+    unmount(container: HTMLElement): void;
+    set_event_delegation(enabled: boolean): void;
+    hydrate(vnode: VNode, container: HTMLElement): void;
+    set_hydration_recovery(enabled: boolean): void;
   }
 
   export function h(nodeType: string): VNode;
-  export function reactive_render(renderFn: () => VNode, container: HTMLElement): void;
+  export class RenderHandle {
+    last_error(): unknown;
+    unmount(): void;
+    free(): void;
+  }
+
+  export function reactive_render(renderFn: () => VNode, container: HTMLElement): RenderHandle;
+  export function reactive_hydrate(renderFn: () => VNode, container: HTMLElement): RenderHandle;
+  export function render_to_string(vnode: VNode): string;
   export function compile_template(input: string): string;
 }